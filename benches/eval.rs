@@ -0,0 +1,109 @@
+//! Comparison of [`Tokamak::eval`] with individual evaluation of every quantity.
+//!
+//! The numerical equilibrium is sampled from an analytical one, so that all of its profiles share
+//! the same ψ grid. The individual evaluations use a separate ψ-accelerator for each profile,
+//! while [`Tokamak::eval`] locates the ψ cell once for all of them.
+
+use std::f64::consts::PI;
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use tokamak_equilibria::*;
+
+#[path = "../src/testing/temp_file.rs"]
+mod temp_file;
+
+use temp_file::TempFile;
+
+/// Evaluates every quantity returned by [`Tokamak::eval`] with a separate call.
+fn individual<Q, B, C, E>(
+    eq: &Tokamak<Q, B, C, E>,
+    psi: f64,
+    theta: f64,
+    cache: &mut TokamakCache<Q, B, C, E>,
+) -> Result<f64>
+where
+    Q: Qfactor,
+    B: Bfield,
+    C: Current,
+    E: Efield,
+{
+    Ok(eq.qfactor.q(psi, &mut cache.qfactor)?
+        + eq.qfactor.psip(psi, &mut cache.qfactor)?
+        + eq.bfield.b(psi, theta, &mut cache.bfield)?
+        + eq.bfield.db_dpsi(psi, theta, &mut cache.bfield)?
+        + eq.bfield.db_dtheta(psi, theta, &mut cache.bfield)?
+        + eq.current.i(psi, &mut cache.current)?
+        + eq.current.g(psi, &mut cache.current)?
+        + eq.current.i_der(psi, &mut cache.current)?
+        + eq.current.g_der(psi, &mut cache.current)?
+        + eq.efield.phi(psi, theta, &mut cache.efield)?
+        + eq.efield.dphi_dpsi(psi, theta, &mut cache.efield)?
+        + eq.efield.dphi_dtheta(psi, theta, &mut cache.efield)?)
+}
+
+fn bench_points<Q, B, C, E>(
+    c: &mut Criterion,
+    name: &str,
+    eq: &Tokamak<Q, B, C, E>,
+    points: &[(f64, f64)],
+) where
+    Q: Qfactor,
+    B: Bfield,
+    C: Current,
+    E: Efield,
+{
+    let mut group = c.benchmark_group(name);
+    group.bench_function("individual", |bencher| {
+        let mut cache = eq.cache();
+        bencher.iter(|| {
+            for (psi, theta) in points.iter() {
+                black_box(individual(eq, *psi, *theta, &mut cache).unwrap());
+            }
+        })
+    });
+    group.bench_function("eval", |bencher| {
+        let mut cache = eq.cache();
+        bencher.iter(|| {
+            for (psi, theta) in points.iter() {
+                black_box(eq.eval(*psi, *theta, &mut cache).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+fn numerical(c: &mut Criterion) {
+    let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125).unwrap();
+    let bfield = bfield::Lar::new().unwrap();
+    let current = current::Lar::new().unwrap();
+    let efield = efield::NoEfield::new().unwrap();
+    let analytical = Tokamak::build(qfactor, bfield, current, efield).unwrap();
+
+    let file = TempFile::new("bench");
+    analytical
+        .to_dataset(&file.path, &SampleGrid::new(0.125))
+        .unwrap();
+    let eq = Tokamak::from_dataset(&file.path, InterpOptions::default()).unwrap();
+
+    // Points along an orbit-like path, so that consecutive evaluations fall in the same cells.
+    let orbit: Vec<(f64, f64)> = (0..100)
+        .map(|k| {
+            let t = k as f64 / 100.0;
+            (0.01 + 0.02 * t, 2.0 * PI * t)
+        })
+        .collect();
+    // Scattered points, so that every evaluation has to locate a new cell.
+    let scattered: Vec<(f64, f64)> = (0..100)
+        .map(|k| {
+            let t = (k * 37 % 100) as f64 / 100.0;
+            (0.001 + 0.12 * t, 2.0 * PI * (k as f64 / 100.0))
+        })
+        .collect();
+
+    bench_points(c, "numerical orbit", &eq, &orbit);
+    bench_points(c, "numerical scattered", &eq, &scattered);
+}
+
+criterion_group!(benches, numerical);
+criterion_main!(benches);
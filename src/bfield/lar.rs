@@ -1,7 +1,6 @@
-use rsl_interpolation::Accelerator;
-
 use crate::Result;
-use crate::bfield::Bfield;
+use crate::bfield::{Bfield, Bfield3d};
+use crate::cache::NoCache;
 
 /// Representation of Large Aspect Ratio magnetic field.
 pub struct Lar;
@@ -27,86 +26,110 @@ impl Lar {
 }
 
 impl Bfield for Lar {
+    type Cache = NoCache;
+
     /// Returns `1 − √(2𝜓)⋅cos𝜃`
     #[allow(unused_variables)]
-    fn b(
-        &self,
-        psi: f64,
-        theta: f64,
-        xacc: &mut Accelerator,
-        yacc: &mut Accelerator,
-    ) -> Result<f64> {
+    fn b(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
         debug_assert!(psi.is_sign_positive());
         Ok(1.0 - (2.0 * psi).sqrt() * theta.cos())
     }
 
     /// Returns `√(2𝜓)⋅sin𝜃`
     #[allow(unused_variables)]
-    fn db_dtheta(
-        &self,
-        psi: f64,
-        theta: f64,
-        xacc: &mut Accelerator,
-        yacc: &mut Accelerator,
-    ) -> Result<f64> {
+    fn db_dtheta(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
         debug_assert!(psi.is_sign_positive());
         Ok((2.0 * psi).sqrt() * theta.sin())
     }
 
     /// Returns `-cosθ/√(2𝜓)`
     #[allow(unused_variables)]
-    fn db_dpsi(
-        &self,
-        psi: f64,
-        theta: f64,
-        xacc: &mut Accelerator,
-        yacc: &mut Accelerator,
-    ) -> Result<f64> {
+    fn db_dpsi(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
         debug_assert!(psi.is_sign_positive());
         Ok(-theta.cos() / (2.0 * psi).sqrt())
     }
 
-    /// Returns `-cosθ/(2*𝜓)³ᐟ²`
+    /// Returns `cosθ/(2𝜓)³ᐟ²`
+    #[allow(unused_variables)]
+    fn d2b_dpsi2(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        Ok(theta.cos() / (2.0 * psi).powf(3.0 / 2.0))
+    }
+
+    /// Returns `√(2𝜓)⋅cos𝜃`
     #[allow(unused_variables)]
-    fn d2b_dpsi2(
-        &self,
-        psi: f64,
-        theta: f64,
-        xacc: &mut Accelerator,
-        yacc: &mut Accelerator,
-    ) -> Result<f64> {
+    fn d2b_dtheta2(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
         debug_assert!(psi.is_sign_positive());
-        Ok(theta.cos() / (2.0 * psi.sqrt()).powf(3.0 / 2.0))
+        Ok((2.0 * psi).sqrt() * theta.cos())
+    }
+
+    /// Returns `sinθ/√(2𝜓)`
+    #[allow(unused_variables)]
+    fn d2b_dpsi_dtheta(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        Ok(theta.sin() / (2.0 * psi).sqrt())
     }
 }
 
+impl Bfield3d for Lar {}
+
 #[cfg(test)]
 mod test {
+    use is_close::is_close;
+
     use crate::*;
-    use rsl_interpolation::*;
 
     #[test]
     /// Values cross-tested with gcmotion.
     fn test_lar() {
-        let mut psi_acc = Accelerator::new();
-        let mut theta_acc = Accelerator::new();
         let bfield = bfield::Lar::new().unwrap();
+        let mut cache = bfield.cache();
 
+        assert_eq!(bfield.b(0.01, 1.0, &mut cache).unwrap(), 0.9235897151259821);
         assert_eq!(
-            bfield.b(0.01, 1.0, &mut psi_acc, &mut theta_acc).unwrap(),
-            0.9235897151259821
-        );
-        assert_eq!(
-            bfield
-                .db_dpsi(0.01, 1.0, &mut psi_acc, &mut theta_acc)
-                .unwrap(),
+            bfield.db_dpsi(0.01, 1.0, &mut cache).unwrap(),
             -3.820514243700898
         );
         assert_eq!(
-            bfield
-                .db_dtheta(0.01, 1.0, &mut psi_acc, &mut theta_acc)
-                .unwrap(),
+            bfield.db_dtheta(0.01, 1.0, &mut cache).unwrap(),
             0.11900196790587718
         );
     }
+
+    #[test]
+    /// The second derivatives match central finite differences of the first derivatives.
+    fn test_lar_second_derivatives() {
+        let bfield = bfield::Lar::new().unwrap();
+        let mut cache = bfield.cache();
+        let h = 1e-6;
+
+        for (psi, theta) in [(0.01, 1.0), (0.05, 2.5), (0.1, 4.0)] {
+            let db_dpsi = |psi, cache: &mut _| bfield.db_dpsi(psi, theta, cache).unwrap();
+            let db_dtheta = |theta, cache: &mut _| bfield.db_dtheta(psi, theta, cache).unwrap();
+
+            let d2b_dpsi2 =
+                (db_dpsi(psi + h, &mut cache) - db_dpsi(psi - h, &mut cache)) / (2.0 * h);
+            let d2b_dtheta2 =
+                (db_dtheta(theta + h, &mut cache) - db_dtheta(theta - h, &mut cache)) / (2.0 * h);
+            let d2b_dpsi_dtheta = (bfield.db_dtheta(psi + h, theta, &mut cache).unwrap()
+                - bfield.db_dtheta(psi - h, theta, &mut cache).unwrap())
+                / (2.0 * h);
+
+            assert!(is_close!(
+                bfield.d2b_dpsi2(psi, theta, &mut cache).unwrap(),
+                d2b_dpsi2,
+                rel_tol = 1e-6
+            ));
+            assert!(is_close!(
+                bfield.d2b_dtheta2(psi, theta, &mut cache).unwrap(),
+                d2b_dtheta2,
+                rel_tol = 1e-6
+            ));
+            assert!(is_close!(
+                bfield.d2b_dpsi_dtheta(psi, theta, &mut cache).unwrap(),
+                d2b_dpsi_dtheta,
+                rel_tol = 1e-6
+            ));
+        }
+    }
 }
@@ -6,37 +6,54 @@ use crate::Result;
 
 mod lar;
 mod numerical;
+mod ripple;
 
 pub use lar::*;
 pub use numerical::Numerical;
+pub use ripple::{ConstantRipple, ExponentialRipple, Ripple, RippleProfile};
 
 /// Calculation of magnetic field related quantities.
 pub trait Bfield {
+    /// The evaluation cache of the magnetic field profile.
+    ///
+    /// Analytical profiles use [`NoCache`](crate::cache::NoCache), numerical profiles hold the
+    /// spline accelerators.
+    type Cache: Default;
+
+    /// Creates a new evaluation cache, to be passed to every evaluation method.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bfield = bfield::Numerical::from_dataset(&"./data.nc".into(), Interp2d::Bicubic)?;
+    /// let mut cache = bfield.cache();
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn cache(&self) -> Self::Cache {
+        Self::Cache::default()
+    }
+
     /// Calculates `B(ψ, θ)`,
     ///
     /// # Example
     ///
     /// ```
     /// # use tokamak_equilibria::*;
-    /// # use rsl_interpolation::*;
     /// # use std::f64::consts::PI;
     /// #
     /// # fn main() -> Result<()> {
-    /// let mut psi_acc = Accelerator::new();
-    /// let mut theta_acc = Accelerator::new();
     /// let bfield = bfield::Lar::new()?;
+    /// let mut cache = bfield.cache();
     ///
-    /// let b =  bfield.b(0.015, 2.0*PI, &mut psi_acc, &mut theta_acc)?;
+    /// let b =  bfield.b(0.015, 2.0*PI, &mut cache)?;
     /// # Ok(())
     /// # }
     /// ```
-    fn b(
-        &self,
-        psi: f64,
-        theta: f64,
-        xacc: &mut Accelerator,
-        yacc: &mut Accelerator,
-    ) -> Result<f64>;
+    fn b(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64>;
 
     /// Calculates `𝜕B /𝜕𝜃`.
     ///
@@ -44,25 +61,17 @@ pub trait Bfield {
     ///
     /// ```
     /// # use tokamak_equilibria::*;
-    /// # use rsl_interpolation::*;
     /// # use std::f64::consts::PI;
     /// #
     /// # fn main() -> Result<()> {
-    /// let mut psi_acc = Accelerator::new();
-    /// let mut theta_acc = Accelerator::new();
     /// let bfield = bfield::Lar::new()?;
+    /// let mut cache = bfield.cache();
     ///
-    /// let db_dtheta =  bfield.db_dtheta(0.015, 2.0*PI, &mut psi_acc, &mut theta_acc)?;
+    /// let db_dtheta =  bfield.db_dtheta(0.015, 2.0*PI, &mut cache)?;
     /// # Ok(())
     /// # }
     /// ```
-    fn db_dtheta(
-        &self,
-        psi: f64,
-        theta: f64,
-        xacc: &mut Accelerator,
-        yacc: &mut Accelerator,
-    ) -> Result<f64>;
+    fn db_dtheta(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64>;
 
     /// Calculates `𝜕B /𝜕ψ`.
     ///
@@ -70,25 +79,17 @@ pub trait Bfield {
     ///
     /// ```
     /// # use tokamak_equilibria::*;
-    /// # use rsl_interpolation::*;
     /// # use std::f64::consts::PI;
     /// #
     /// # fn main() -> Result<()> {
-    /// let mut psi_acc = Accelerator::new();
-    /// let mut theta_acc = Accelerator::new();
     /// let bfield = bfield::Lar::new()?;
+    /// let mut cache = bfield.cache();
     ///
-    /// let db_dpsi =  bfield.db_dpsi(0.015, 2.0*PI, &mut psi_acc, &mut theta_acc)?;
+    /// let db_dpsi =  bfield.db_dpsi(0.015, 2.0*PI, &mut cache)?;
     /// # Ok(())
     /// # }
     /// ```
-    fn db_dpsi(
-        &self,
-        psi: f64,
-        theta: f64,
-        xacc: &mut Accelerator,
-        yacc: &mut Accelerator,
-    ) -> Result<f64>;
+    fn db_dpsi(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64>;
 
     /// Calculates `𝜕²B /𝜕𝜓²`.
     ///
@@ -96,23 +97,143 @@ pub trait Bfield {
     ///
     /// ```
     /// # use tokamak_equilibria::*;
-    /// # use rsl_interpolation::*;
     /// # use std::f64::consts::PI;
     /// #
     /// # fn main() -> Result<()> {
-    /// let mut psi_acc = Accelerator::new();
-    /// let mut theta_acc = Accelerator::new();
     /// let bfield = bfield::Lar::new()?;
+    /// let mut cache = bfield.cache();
+    ///
+    /// let d2b_dpsi2 =  bfield.d2b_dpsi2(0.015, 2.0*PI, &mut cache)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn d2b_dpsi2(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64>;
+
+    /// Calculates `𝜕²B /𝜕𝜃²`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// # use std::f64::consts::PI;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bfield = bfield::Lar::new()?;
+    /// let mut cache = bfield.cache();
+    ///
+    /// let d2b_dtheta2 =  bfield.d2b_dtheta2(0.015, 2.0*PI, &mut cache)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn d2b_dtheta2(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64>;
+
+    /// Calculates `𝜕²B /𝜕𝜓𝜕𝜃`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// # use std::f64::consts::PI;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bfield = bfield::Lar::new()?;
+    /// let mut cache = bfield.cache();
+    ///
+    /// let d2b_dpsi_dtheta =  bfield.d2b_dpsi_dtheta(0.015, 2.0*PI, &mut cache)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn d2b_dpsi_dtheta(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64>;
+
+    /// The ψ grid of the magnetic field splines, if the profile is interpolated over ψ.
+    ///
+    /// Profiles over the same ψ grid pass their [`psi_acc`](Self::psi_acc) to one another in
+    /// [`Tokamak::eval`](crate::Tokamak::eval), so that the ψ cell is only located once.
+    fn psi_grid(&self) -> Option<&[f64]> {
+        None
+    }
+
+    /// The ψ-[`Accelerator`] of `cache`, if the profile is interpolated over ψ.
+    #[allow(unused_variables)]
+    fn psi_acc<'a>(&self, cache: &'a mut Self::Cache) -> Option<&'a mut Accelerator> {
+        None
+    }
+}
+
+/// Calculation of magnetic fields which also depend on the toroidal angle ζ.
+///
+/// The [`Bfield`] methods of a 3D field return its axisymmetric part, while the guiding-centre
+/// equations of motion use the full field. The default methods are those of an axisymmetric
+/// field, so an axisymmetric [`Bfield`] implements it with an empty `impl Bfield3d for T {}`.
+/// There is no blanket implementation, since 3D fields override the methods.
+///
+/// The [`gc`](crate::gc), [`particle`](crate::particle), [`poincare`](crate::poincare),
+/// [`orbit`](crate::orbit) and [`frequencies`](crate::frequencies) modules require a `Bfield3d`.
+/// This is a breaking change for magnetic fields defined outside of this crate, which must add
+/// the empty implementation to be used with them.
+pub trait Bfield3d: Bfield {
+    /// Whether the field is independent of ζ, so that its [`Bfield`] methods are the full field.
+    fn is_axisymmetric(&self) -> bool {
+        true
+    }
+
+    /// Calculates `B(ψ, θ, ζ)`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// # use tokamak_equilibria::bfield::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let ripple = Ripple::new(Lar::new()?, ConstantRipple::new(0.01)?, 18)?;
+    /// let mut cache = ripple.cache();
     ///
-    /// let d2b_dpsi2 =  bfield.d2b_dpsi2(0.015, 2.0*PI, &mut psi_acc, &mut theta_acc)?;
+    /// let b = ripple.b_3d(0.015, 1.0, 0.1, &mut cache)?;
     /// # Ok(())
     /// # }
     /// ```
-    fn d2b_dpsi2(
+    #[allow(unused_variables)]
+    fn b_3d(&self, psi: f64, theta: f64, zeta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        self.b(psi, theta, cache)
+    }
+
+    /// Calculates `𝜕B /𝜕ψ` at `(ψ, θ, ζ)`.
+    #[allow(unused_variables)]
+    fn db_dpsi_3d(&self, psi: f64, theta: f64, zeta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        self.db_dpsi(psi, theta, cache)
+    }
+
+    /// Calculates `𝜕B /𝜕𝜃` at `(ψ, θ, ζ)`.
+    #[allow(unused_variables)]
+    fn db_dtheta_3d(
         &self,
         psi: f64,
         theta: f64,
-        xacc: &mut Accelerator,
-        yacc: &mut Accelerator,
-    ) -> Result<f64>;
+        zeta: f64,
+        cache: &mut Self::Cache,
+    ) -> Result<f64> {
+        self.db_dtheta(psi, theta, cache)
+    }
+
+    /// Calculates `𝜕B /𝜕𝜁`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// # use tokamak_equilibria::bfield::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bfield = Lar::new()?;
+    /// let mut cache = bfield.cache();
+    ///
+    /// assert_eq!(bfield.db_dzeta(0.015, 1.0, 0.1, &mut cache)?, 0.0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[allow(unused_variables)]
+    fn db_dzeta(&self, psi: f64, theta: f64, zeta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        Ok(0.0)
+    }
 }
@@ -3,9 +3,14 @@ use std::path::PathBuf;
 use ndarray::concatenate;
 use ndarray::{Array2, Axis};
 use rsl_interpolation::{Accelerator, DynSpline2d};
+use tokamak_netcdf::Equilibrium;
 
 use crate::Bfield;
-use crate::Result;
+use crate::Interp2d;
+use crate::bfield::Bfield3d;
+use crate::cache::PsiThetaCache;
+use crate::dataset;
+use crate::{EqError, Result};
 
 /// Magnetic field reconstructed from a netCDF file.
 #[allow(dead_code)]
@@ -14,6 +19,8 @@ pub struct Numerical {
     b_spline: DynSpline2d<f64>,
     /// The magnetic field data used to construct the spline.
     b_data: Array2<f64>,
+    /// The ψ grid of the spline.
+    psi_data: Box<[f64]>,
 }
 
 impl Numerical {
@@ -27,101 +34,263 @@ impl Numerical {
     /// #
     /// # fn main() -> Result<()> {
     /// let path = PathBuf::from("./data.nc");
-    /// let bfield = bfield::Numerical::from_dataset(&path, "bicubic")?;
+    /// let bfield = bfield::Numerical::from_dataset(&path, Interp2d::Bicubic)?;
     /// # Ok(())
     /// # }
     /// ```
-    pub fn from_dataset(path: &PathBuf, typ: &str) -> Result<Self> {
-        use rsl_interpolation::*;
-        use tokamak_netcdf::variable_names::*;
-        use tokamak_netcdf::*;
+    pub fn from_dataset(path: &PathBuf, typ: Interp2d) -> Result<Self> {
+        Self::from_equilibrium(&Equilibrium::from_file(path)?, typ)
+    }
 
-        let eq = Equilibrium::from_file(path)?;
+    /// Constructs a [`Bfield`] from an already opened netCDF [`Equilibrium`], with spline of
+    /// `typ` interpolation type.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use tokamak_equilibria::*;
+    /// # use std::path::PathBuf;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from("./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let bfield = bfield::Numerical::from_equilibrium(&eq, Interp2d::Bicubic)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_equilibrium(eq: &Equilibrium, typ: Interp2d) -> Result<Self> {
+        let psi_data = dataset::psi_coord(eq)?;
+        let theta_data = dataset::theta_coord(eq)?;
+        let b_data = Self::extract_b_data(eq)?;
 
-        // Add 0.0 manualy, which corresponds to the axis value.
-        let psi_data = extract_var_with_axis_value(&eq.file, PSI_COORD, 0.0)?
-            .as_standard_layout()
-            .to_vec();
-        let theta_data = eq.get_1d(THETA_COORD)?.to_vec();
+        Self::from_arrays(&psi_data, &theta_data, b_data, typ)
+    }
+
+    /// Extracts the magnetic field data, with the axis row `B = B0` prepended, so that it matches
+    /// the ψ grid of [`dataset::psi_coord`].
+    pub(crate) fn extract_b_data(eq: &Equilibrium) -> Result<Array2<f64>> {
+        use tokamak_netcdf::variable_names::*;
 
         let b_data = eq.get_2d(B_FIELD)?;
 
         // Transpose of gcmotion
         let b_axis_values = Array2::from_elem((1, b_data.ncols()), 1.0); // B0 = 1 [NU]
-        let b_data = concatenate![Axis(0), b_axis_values, b_data]; // e.g. [101, 3620]
-        let b_data_flat = b_data.flatten().to_vec();
+        Ok(concatenate![Axis(0), b_axis_values, b_data]) // e.g. [101, 3620]
+    }
 
-        let b_spline = make_spline2d(typ, &psi_data, &theta_data, &b_data_flat)?;
+    /// Constructs a [`Bfield`] from the ψ and θ data arrays, and the magnetic field data, with
+    /// spline of `typ` interpolation type.
+    ///
+    /// `psi_data` and `theta_data` must be strictly increasing, and `b_data` must have the shape
+    /// `[ψ, θ]`. All values must be finite.
+    ///
+    /// # Example
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// # use ndarray::Array2;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let psi_data = [0.0, 0.05, 0.1];
+    /// let theta_data = [0.0, 2.0, 4.0, 6.3];
+    /// let b_data = Array2::from_shape_fn((3, 4), |(i, j)| 1.0 - 0.1 * (i * j) as f64);
+    /// let bfield = bfield::Numerical::from_arrays(&psi_data, &theta_data, b_data, Interp2d::Bicubic)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_arrays(
+        psi_data: &[f64],
+        theta_data: &[f64],
+        b_data: Array2<f64>,
+        typ: Interp2d,
+    ) -> Result<Self> {
+        dataset::validate_grid("ψ", psi_data)?;
+        dataset::validate_grid("θ", theta_data)?;
+        let shape = (psi_data.len(), theta_data.len());
+        if b_data.dim() != shape {
+            return Err(EqError::InvalidData(format!(
+                "B has shape {:?}, expected {shape:?}",
+                b_data.dim()
+            )));
+        }
+        if let Some(b) = b_data.iter().find(|b| !b.is_finite()) {
+            return Err(EqError::InvalidData(format!("B has non-finite value {b}")));
+        }
+
+        use rsl_interpolation::*;
 
-        Ok(Self { b_spline, b_data })
+        let b_data_flat = b_data.flatten().to_vec();
+        let b_spline = make_spline2d(typ.name(), psi_data, theta_data, &b_data_flat)?;
+
+        Ok(Self {
+            b_spline,
+            b_data,
+            psi_data: psi_data.into(),
+        })
     }
 }
 
 impl Bfield for Numerical {
-    fn b(
-        &self,
-        psi: f64,
-        theta: f64,
-        xacc: &mut Accelerator,
-        yacc: &mut Accelerator,
-    ) -> Result<f64> {
-        Ok(self.b_spline.eval(psi, theta, xacc, yacc)?)
+    type Cache = PsiThetaCache;
+
+    fn b(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        Ok(self
+            .b_spline
+            .eval(psi, theta, &mut cache.psi_acc, &mut cache.theta_acc)?)
+    }
+
+    fn db_dtheta(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        // Ok(self.db_dtheta_spline.eval(psi, theta, &mut cache.psi_acc, &mut cache.theta_acc)?)
+        Ok(self
+            .b_spline
+            .eval_deriv_y(psi, theta, &mut cache.psi_acc, &mut cache.theta_acc)?)
+    }
+
+    fn db_dpsi(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        // Ok(self.db_dpsi_spline.eval(psi, theta, &mut cache.psi_acc, &mut cache.theta_acc)?)
+        Ok(self
+            .b_spline
+            .eval_deriv_x(psi, theta, &mut cache.psi_acc, &mut cache.theta_acc)?)
+    }
+
+    fn d2b_dpsi2(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        // Ok(self.d2b_dpsi2_spline.eval(psi, theta, &mut cache.psi_acc, &mut cache.theta_acc)?)
+        Ok(self
+            .b_spline
+            .eval_deriv_xx(psi, theta, &mut cache.psi_acc, &mut cache.theta_acc)?)
     }
 
-    fn db_dtheta(
-        &self,
-        psi: f64,
-        theta: f64,
-        xacc: &mut Accelerator,
-        yacc: &mut Accelerator,
-    ) -> Result<f64> {
-        // Ok(self.db_dtheta_spline.eval(psi, theta, xacc, yacc)?)
-        Ok(self.b_spline.eval_deriv_y(psi, theta, xacc, yacc)?)
+    fn d2b_dtheta2(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        Ok(self
+            .b_spline
+            .eval_deriv_yy(psi, theta, &mut cache.psi_acc, &mut cache.theta_acc)?)
     }
 
-    fn db_dpsi(
-        &self,
-        psi: f64,
-        theta: f64,
-        xacc: &mut Accelerator,
-        yacc: &mut Accelerator,
-    ) -> Result<f64> {
-        // Ok(self.db_dpsi_spline.eval(psi, theta, xacc, yacc)?)
-        Ok(self.b_spline.eval_deriv_x(psi, theta, xacc, yacc)?)
+    fn d2b_dpsi_dtheta(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        Ok(self
+            .b_spline
+            .eval_deriv_xy(psi, theta, &mut cache.psi_acc, &mut cache.theta_acc)?)
     }
 
-    fn d2b_dpsi2(
-        &self,
-        psi: f64,
-        theta: f64,
-        xacc: &mut Accelerator,
-        yacc: &mut Accelerator,
-    ) -> Result<f64> {
-        // Ok(self.d2b_dpsi2_spline.eval(psi, theta, xacc, yacc)?)
-        Ok(self.b_spline.eval_deriv_xx(psi, theta, xacc, yacc)?)
+    fn psi_grid(&self) -> Option<&[f64]> {
+        Some(&self.psi_data)
+    }
+
+    fn psi_acc<'a>(&self, cache: &'a mut Self::Cache) -> Option<&'a mut Accelerator> {
+        Some(&mut cache.psi_acc)
     }
 }
 
+impl Bfield3d for Numerical {}
+
 #[cfg(test)]
 mod test {
-    use std::path::PathBuf;
+    use std::f64::consts::TAU;
+
+    use is_close::is_close;
+    use ndarray::Array2;
 
     use crate::bfield::Numerical;
+    use crate::testing::lar_dataset;
+    use crate::*;
 
     #[test]
-    #[ignore = "needs specific dataset"]
-    /// Specific b-values alues cross-tested with gcmotion.
-    fn test_numeric_bfield_indices() {
-        let path = PathBuf::from("./reconstructed/smart_positive.nc");
+    /// Bilinear splines over bilinear data are exact.
+    fn test_from_arrays() {
+        let psi_data = [0.0, 0.05, 0.1];
+        let theta_data = [0.0, 2.0, 4.0, 6.0];
+        let b = |psi: f64, theta: f64| 1.0 + psi * (1.0 - theta / 6.0);
+        let b_data = Array2::from_shape_fn((3, 4), |(i, j)| b(psi_data[i], theta_data[j]));
+        let bf =
+            Numerical::from_arrays(&psi_data, &theta_data, b_data, Interp2d::Bilinear).unwrap();
+        let mut cache = bf.cache();
+
+        assert!(is_close!(
+            bf.b(0.07, 3.0, &mut cache).unwrap(),
+            b(0.07, 3.0)
+        ));
+        assert!(is_close!(bf.db_dpsi(0.07, 3.0, &mut cache).unwrap(), 0.5));
+        assert!(is_close!(
+            bf.db_dtheta(0.07, 3.0, &mut cache).unwrap(),
+            -0.07 / 6.0
+        ));
+
+        let wrong_shape = Array2::ones((4, 3));
+        let err = Numerical::from_arrays(&psi_data, &theta_data, wrong_shape, Interp2d::Bilinear);
+        assert!(matches!(err, Err(EqError::InvalidData(_))));
+        let mut infinite = Array2::ones((3, 4));
+        infinite[[1, 2]] = f64::INFINITY;
+        let err = Numerical::from_arrays(&psi_data, &theta_data, infinite, Interp2d::Bilinear);
+        assert!(matches!(err, Err(EqError::InvalidData(_))));
+    }
 
-        let bf = Numerical::from_dataset(&path, "Bicubic").unwrap();
-        let b = bf.b_data;
+    #[test]
+    /// The spline's second derivatives match central finite differences of its first
+    /// derivatives, away from the grid nodes.
+    fn test_second_derivatives() {
+        let psi_data: Vec<f64> = (0..=20).map(|i| 0.005 * i as f64).collect();
+        let theta_data: Vec<f64> = (0..=40)
+            .map(|j| std::f64::consts::TAU * j as f64 / 40.0)
+            .collect();
+        let b_data = Array2::from_shape_fn((21, 41), |(i, j)| {
+            1.0 - (2.0 * psi_data[i]).sqrt() * theta_data[j].cos()
+        });
+        let bf = Numerical::from_arrays(&psi_data, &theta_data, b_data, Interp2d::Bicubic).unwrap();
+        let mut cache = bf.cache();
+        let h = 1e-6;
+
+        for (psi, theta) in [(0.0312, 1.03), (0.0777, 4.21)] {
+            let d2b_dpsi2 = (bf.db_dpsi(psi + h, theta, &mut cache).unwrap()
+                - bf.db_dpsi(psi - h, theta, &mut cache).unwrap())
+                / (2.0 * h);
+            let d2b_dtheta2 = (bf.db_dtheta(psi, theta + h, &mut cache).unwrap()
+                - bf.db_dtheta(psi, theta - h, &mut cache).unwrap())
+                / (2.0 * h);
+            let d2b_dpsi_dtheta = (bf.db_dtheta(psi + h, theta, &mut cache).unwrap()
+                - bf.db_dtheta(psi - h, theta, &mut cache).unwrap())
+                / (2.0 * h);
+
+            assert!(is_close!(
+                bf.d2b_dpsi2(psi, theta, &mut cache).unwrap(),
+                d2b_dpsi2,
+                rel_tol = 1e-5
+            ));
+            assert!(is_close!(
+                bf.d2b_dtheta2(psi, theta, &mut cache).unwrap(),
+                d2b_dtheta2,
+                rel_tol = 1e-5
+            ));
+            assert!(is_close!(
+                bf.d2b_dpsi_dtheta(psi, theta, &mut cache).unwrap(),
+                d2b_dpsi_dtheta,
+                rel_tol = 1e-5
+            ));
+        }
+    }
+
+    #[test]
+    fn test_dataset_indices() {
+        let lar = bfield::Lar::new().unwrap();
+        let grid = SampleGrid {
+            psi_wall: 0.125,
+            psi: 50,
+            theta: 64,
+        };
 
-        assert_eq!(b.shape(), [101, 3620]);
+        let file = lar_dataset("bfield_indices", &grid);
+        let bf = Numerical::from_dataset(&file.path, Interp2d::Bicubic).unwrap();
+        let b = &bf.b_data;
+        let mut cache = lar.cache();
 
+        assert_eq!(b.shape(), [51, 65]);
         assert!(b.row(0).iter().all(|x| *x == 1.0));
-        assert_eq!(b[[100, 3619]], 0.6756920402998402);
-        assert_eq!(b[[50, 2000]], 1.342405803626943);
-        assert_eq!(b[[80, 20]], 0.707178368383484);
+        for (k, n) in [(50, 64), (25, 40), (40, 2)] {
+            let psi = 0.125 * k as f64 / 50.0;
+            let theta = TAU * n as f64 / 64.0;
+            assert_eq!(b[[k, n]], lar.b(psi, theta, &mut cache).unwrap());
+        }
+        assert!(is_close!(
+            bf.b(0.0771, 1.3, &mut bf.cache()).unwrap(),
+            lar.b(0.0771, 1.3, &mut cache).unwrap(),
+            rel_tol = 1e-4
+        ));
     }
 }
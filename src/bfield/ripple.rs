@@ -0,0 +1,334 @@
+use rsl_interpolation::Accelerator;
+
+use crate::bfield::{Bfield, Bfield3d};
+use crate::{EqError, Result};
+
+/// Calculation of the relative ripple amplitude `δ(ψ, θ)`.
+pub trait RippleProfile {
+    /// Calculates `δ(ψ, θ)`.
+    fn delta(&self, psi: f64, theta: f64) -> Result<f64>;
+
+    /// Calculates `𝜕δ/𝜕ψ`.
+    fn ddelta_dpsi(&self, psi: f64, theta: f64) -> Result<f64>;
+
+    /// Calculates `𝜕δ/𝜕𝜃`.
+    fn ddelta_dtheta(&self, psi: f64, theta: f64) -> Result<f64>;
+}
+
+/// Toroidal field ripple of `N` coils, superimposed on an axisymmetric [`Bfield`].
+///
+/// The field is `B(ψ, θ, ζ) = B₀(ψ, θ)⋅(1 + δ(ψ, θ)⋅cos(Nζ))`, where `B₀` is the wrapped field.
+/// Its [`Bfield`] methods return the toroidal average `B₀`, and its [`Bfield3d`] methods the full
+/// field, which drives the guiding-centre motion.
+///
+/// # Example
+///
+/// ```
+/// # use tokamak_equilibria::*;
+/// # use tokamak_equilibria::bfield::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let delta = ExponentialRipple::new(0.01, 0.125, 0.1)?;
+/// let ripple = Ripple::new(Lar::new()?, delta, 18)?;
+/// let mut cache = ripple.cache();
+///
+/// let b = ripple.b_3d(0.1, 0.0, 0.0, &mut cache)?;
+/// let db_dzeta = ripple.db_dzeta(0.1, 0.0, 0.1, &mut cache)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Ripple<B: Bfield, D: RippleProfile> {
+    /// The axisymmetric field `B₀`.
+    pub bfield: B,
+    /// The ripple amplitude profile `δ`.
+    pub delta: D,
+    /// The number of toroidal field coils `N`.
+    pub coils: u32,
+}
+
+impl<B: Bfield, D: RippleProfile> Ripple<B, D> {
+    /// Creates a new ripple of `coils` toroidal field coils with amplitude profile `delta`, on
+    /// the axisymmetric field `bfield`.
+    pub fn new(bfield: B, delta: D, coils: u32) -> Result<Self> {
+        if coils == 0 {
+            return Err(EqError::InvalidEquilibrium(
+                "the ripple needs at least one coil".into(),
+            ));
+        }
+        Ok(Self {
+            bfield,
+            delta,
+            coils,
+        })
+    }
+
+    /// Returns `cos(Nζ)`.
+    fn cos(&self, zeta: f64) -> f64 {
+        (f64::from(self.coils) * zeta).cos()
+    }
+}
+
+impl<B: Bfield, D: RippleProfile> Bfield for Ripple<B, D> {
+    type Cache = B::Cache;
+
+    fn b(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        self.bfield.b(psi, theta, cache)
+    }
+
+    fn db_dtheta(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        self.bfield.db_dtheta(psi, theta, cache)
+    }
+
+    fn db_dpsi(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        self.bfield.db_dpsi(psi, theta, cache)
+    }
+
+    fn d2b_dpsi2(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        self.bfield.d2b_dpsi2(psi, theta, cache)
+    }
+
+    fn d2b_dtheta2(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        self.bfield.d2b_dtheta2(psi, theta, cache)
+    }
+
+    fn d2b_dpsi_dtheta(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        self.bfield.d2b_dpsi_dtheta(psi, theta, cache)
+    }
+
+    fn psi_grid(&self) -> Option<&[f64]> {
+        self.bfield.psi_grid()
+    }
+
+    fn psi_acc<'a>(&self, cache: &'a mut Self::Cache) -> Option<&'a mut Accelerator> {
+        self.bfield.psi_acc(cache)
+    }
+}
+
+impl<B: Bfield, D: RippleProfile> Bfield3d for Ripple<B, D> {
+    fn is_axisymmetric(&self) -> bool {
+        false
+    }
+
+    /// Returns `B₀⋅(1 + δ⋅cos(Nζ))`
+    fn b_3d(&self, psi: f64, theta: f64, zeta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        let modulation = self.delta.delta(psi, theta)? * self.cos(zeta);
+        Ok(self.bfield.b(psi, theta, cache)? * (1.0 + modulation))
+    }
+
+    /// Returns `𝜕B₀/𝜕ψ⋅(1 + δ⋅cos(Nζ)) + B₀⋅𝜕δ/𝜕ψ⋅cos(Nζ)`
+    fn db_dpsi_3d(&self, psi: f64, theta: f64, zeta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        let cos = self.cos(zeta);
+        let delta = self.delta.delta(psi, theta)?;
+        let ddelta = self.delta.ddelta_dpsi(psi, theta)?;
+        let b = self.bfield.b(psi, theta, cache)?;
+        let db = self.bfield.db_dpsi(psi, theta, cache)?;
+        Ok(db * (1.0 + delta * cos) + b * ddelta * cos)
+    }
+
+    /// Returns `𝜕B₀/𝜕𝜃⋅(1 + δ⋅cos(Nζ)) + B₀⋅𝜕δ/𝜕𝜃⋅cos(Nζ)`
+    fn db_dtheta_3d(
+        &self,
+        psi: f64,
+        theta: f64,
+        zeta: f64,
+        cache: &mut Self::Cache,
+    ) -> Result<f64> {
+        let cos = self.cos(zeta);
+        let delta = self.delta.delta(psi, theta)?;
+        let ddelta = self.delta.ddelta_dtheta(psi, theta)?;
+        let b = self.bfield.b(psi, theta, cache)?;
+        let db = self.bfield.db_dtheta(psi, theta, cache)?;
+        Ok(db * (1.0 + delta * cos) + b * ddelta * cos)
+    }
+
+    /// Returns `−N⋅B₀⋅δ⋅sin(Nζ)`
+    fn db_dzeta(&self, psi: f64, theta: f64, zeta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        let n = f64::from(self.coils);
+        let delta = self.delta.delta(psi, theta)?;
+        Ok(-n * self.bfield.b(psi, theta, cache)? * delta * (n * zeta).sin())
+    }
+}
+
+/// Ripple of constant amplitude `δ`.
+pub struct ConstantRipple {
+    /// The ripple amplitude.
+    pub delta: f64,
+}
+
+impl ConstantRipple {
+    /// Creates a new ripple profile of constant amplitude `delta`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let delta = bfield::ConstantRipple::new(0.01)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(delta: f64) -> Result<Self> {
+        validate_amplitude(delta)?;
+        Ok(Self { delta })
+    }
+}
+
+impl RippleProfile for ConstantRipple {
+    #[allow(unused_variables)]
+    fn delta(&self, psi: f64, theta: f64) -> Result<f64> {
+        Ok(self.delta)
+    }
+
+    #[allow(unused_variables)]
+    fn ddelta_dpsi(&self, psi: f64, theta: f64) -> Result<f64> {
+        Ok(0.0)
+    }
+
+    #[allow(unused_variables)]
+    fn ddelta_dtheta(&self, psi: f64, theta: f64) -> Result<f64> {
+        Ok(0.0)
+    }
+}
+
+/// Ripple whose amplitude grows exponentially with the minor radius `r = √(2ψ)`,
+/// `δ = δ_w⋅exp((r − r_w)/w)`.
+pub struct ExponentialRipple {
+    /// The ripple amplitude `δ_w` at the wall.
+    pub delta_wall: f64,
+    /// The toroidal flux `ψ_w` of the wall.
+    pub psi_wall: f64,
+    /// The radial decay length `w`.
+    pub width: f64,
+}
+
+impl ExponentialRipple {
+    /// Creates a new ripple profile with amplitude `delta_wall` at the wall `psi_wall`, decaying
+    /// inwards over the minor radius `width`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let delta = bfield::ExponentialRipple::new(0.01, 0.125, 0.1)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(delta_wall: f64, psi_wall: f64, width: f64) -> Result<Self> {
+        validate_amplitude(delta_wall)?;
+        if !(psi_wall > 0.0 && width > 0.0) {
+            return Err(EqError::InvalidEquilibrium(format!(
+                "ψ_w = {psi_wall} and w = {width} must be positive"
+            )));
+        }
+        Ok(Self {
+            delta_wall,
+            psi_wall,
+            width,
+        })
+    }
+}
+
+impl RippleProfile for ExponentialRipple {
+    /// Returns `δ_w⋅exp((r − r_w)/w)`
+    #[allow(unused_variables)]
+    fn delta(&self, psi: f64, theta: f64) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        let r = (2.0 * psi).sqrt();
+        let r_wall = (2.0 * self.psi_wall).sqrt();
+        Ok(self.delta_wall * ((r - r_wall) / self.width).exp())
+    }
+
+    /// Returns `δ/(w⋅r)`
+    fn ddelta_dpsi(&self, psi: f64, theta: f64) -> Result<f64> {
+        let r = (2.0 * psi).sqrt();
+        Ok(self.delta(psi, theta)? / (self.width * r))
+    }
+
+    #[allow(unused_variables)]
+    fn ddelta_dtheta(&self, psi: f64, theta: f64) -> Result<f64> {
+        Ok(0.0)
+    }
+}
+
+/// Checks that the ripple amplitude keeps `B` positive.
+fn validate_amplitude(delta: f64) -> Result<()> {
+    if delta.abs() >= 1.0 || delta.is_nan() {
+        return Err(EqError::InvalidEquilibrium(format!(
+            "ripple amplitude δ = {delta} must be in (-1, 1)"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use is_close::is_close;
+
+    use crate::bfield::*;
+
+    #[test]
+    /// Without ripple, the field is exactly the wrapped field.
+    fn test_axisymmetric_limit() {
+        let ripple =
+            Ripple::new(Lar::new().unwrap(), ConstantRipple::new(0.0).unwrap(), 18).unwrap();
+        let lar = Lar::new().unwrap();
+        let mut cache = ripple.cache();
+
+        for (psi, theta, zeta) in [(0.01, 1.0, 0.3), (0.05, 2.5, 4.0), (0.1, 4.0, 6.0)] {
+            assert_eq!(
+                ripple.b_3d(psi, theta, zeta, &mut cache).unwrap(),
+                lar.b(psi, theta, &mut cache).unwrap()
+            );
+            assert_eq!(
+                ripple.db_dpsi_3d(psi, theta, zeta, &mut cache).unwrap(),
+                lar.db_dpsi(psi, theta, &mut cache).unwrap()
+            );
+            assert_eq!(
+                ripple.db_dtheta_3d(psi, theta, zeta, &mut cache).unwrap(),
+                lar.db_dtheta(psi, theta, &mut cache).unwrap()
+            );
+            assert_eq!(ripple.db_dzeta(psi, theta, zeta, &mut cache).unwrap(), 0.0);
+        }
+        assert!(Ripple::new(Lar::new().unwrap(), ConstantRipple::new(0.0).unwrap(), 0).is_err());
+        assert!(ConstantRipple::new(1.0).is_err());
+    }
+
+    #[test]
+    /// The derivatives match central finite differences of `B(ψ, θ, ζ)`.
+    fn test_ripple_derivatives() {
+        let delta = ExponentialRipple::new(0.02, 0.125, 0.1).unwrap();
+        let ripple = Ripple::new(Lar::new().unwrap(), delta, 16).unwrap();
+        let mut cache = ripple.cache();
+        let h = 1e-6;
+
+        for (psi, theta, zeta) in [(0.01, 1.0, 0.03), (0.05, 2.5, 0.2), (0.1, 4.0, 6.0)] {
+            let mut b = |psi, theta, zeta| ripple.b_3d(psi, theta, zeta, &mut cache).unwrap();
+            let db_dpsi = (b(psi + h, theta, zeta) - b(psi - h, theta, zeta)) / (2.0 * h);
+            let db_dtheta = (b(psi, theta + h, zeta) - b(psi, theta - h, zeta)) / (2.0 * h);
+            let db_dzeta = (b(psi, theta, zeta + h) - b(psi, theta, zeta - h)) / (2.0 * h);
+
+            let expected = [
+                ripple.db_dpsi_3d(psi, theta, zeta, &mut cache).unwrap(),
+                ripple.db_dtheta_3d(psi, theta, zeta, &mut cache).unwrap(),
+                ripple.db_dzeta(psi, theta, zeta, &mut cache).unwrap(),
+            ];
+            for (fd, exact) in [db_dpsi, db_dtheta, db_dzeta].into_iter().zip(expected) {
+                assert!(is_close!(fd, exact, rel_tol = 1e-6, abs_tol = 1e-9));
+            }
+        }
+    }
+
+    #[test]
+    fn test_exponential_profile() {
+        let delta = ExponentialRipple::new(0.02, 0.125, 0.1).unwrap();
+        assert!(is_close!(delta.delta(0.125, 1.0).unwrap(), 0.02));
+        // One decay length inside the wall, at r = 0.4.
+        assert!(is_close!(
+            delta.delta(0.08, 1.0).unwrap(),
+            0.02 * (-1.0f64).exp()
+        ));
+    }
+}
@@ -0,0 +1,295 @@
+//! Mapping of an axisymmetric equilibrium in cylindrical coordinates `(R, Z)` to the flux
+//! functions and the Boozer-angle magnetic field used by the numerical profiles.
+//!
+//! The equilibrium is given by its poloidal flux per radian `Ψ(R, Z)`, so that
+//! `B_p = |∇Ψ|/R`, and the toroidal field function `F(Ψ) = RB_φ`. Every flux surface is traced
+//! by rays from the magnetic axis, parametrized by their geometric angle `ω`, along which
+//!
+//! ```text
+//! q = (F/2π)∮ dl/(R²B_p)
+//! I = (1/2π)∮ B_p dl
+//! g = F
+//! dθ/dl = B²/((gq + I)B_p)
+//! ```
+//!
+//! where `θ` is the Boozer poloidal angle, starting from the outboard midplane. The results are
+//! normalized by the major radius `R_axis` and the field `B_axis` of the magnetic axis, and the
+//! toroidal flux is recovered from `dψ/d𝜓ₚ = q`.
+
+use std::f64::consts::TAU;
+
+use ndarray::Array2;
+use rsl_interpolation::{Accelerator, make_spline};
+
+use crate::numerics::bisect;
+use crate::{EqError, Interp1d, Result};
+
+/// An axisymmetric equilibrium in cylindrical coordinates.
+pub(crate) trait FluxFunction {
+    /// The poloidal flux per radian `Ψ(R, Z)`.
+    fn psi(&self, r: f64, z: f64) -> Result<f64>;
+
+    /// The gradient `(𝜕Ψ/𝜕R, 𝜕Ψ/𝜕Z)`.
+    fn grad_psi(&self, r: f64, z: f64) -> Result<(f64, f64)>;
+
+    /// The toroidal field function `F(Ψ) = RB_φ`.
+    fn f(&self, psi: f64) -> Result<f64>;
+}
+
+/// Resolution of the Boozer mapping.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BoozerGrid {
+    /// Number of flux surfaces, excluding the magnetic axis.
+    pub surfaces: usize,
+    /// Number of intervals of the uniform θ grid.
+    pub theta: usize,
+    /// Number of rays used to trace each flux surface.
+    pub rays: usize,
+}
+
+impl Default for BoozerGrid {
+    fn default() -> Self {
+        Self {
+            surfaces: 100,
+            theta: 256,
+            rays: 512,
+        }
+    }
+}
+
+/// The normalized profiles of a mapped equilibrium, including the axis values.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BoozerData {
+    /// The toroidal flux `ψ`.
+    pub psi: Vec<f64>,
+    /// The safety factor.
+    pub q: Vec<f64>,
+    /// The toroidal current function `I`.
+    pub i: Vec<f64>,
+    /// The poloidal current function `g`.
+    pub g: Vec<f64>,
+    /// The uniform Boozer θ grid over `[0, 2π]`.
+    pub theta: Vec<f64>,
+    /// The magnetic field `B(ψ, θ)`.
+    pub b: Array2<f64>,
+    /// The major radius `R_axis` of the magnetic axis, in the units of the equilibrium.
+    pub r_axis: f64,
+    /// The magnetic field `B_axis` on the magnetic axis, in the units of the equilibrium.
+    pub b_axis: f64,
+}
+
+/// Maps the equilibrium `eq`, with its magnetic axis at `axis = (R, Z)`, up to the flux surface
+/// `Ψ = psi_edge`.
+///
+/// `r_max` must exceed the largest distance between the axis and the edge surface, and the grid
+/// must have at least two surfaces, from which q is extrapolated to the axis.
+pub(crate) fn boozer_map<F: FluxFunction>(
+    eq: &F,
+    axis: (f64, f64),
+    psi_edge: f64,
+    r_max: f64,
+    grid: &BoozerGrid,
+) -> Result<BoozerData> {
+    if grid.surfaces < 2 {
+        return Err(EqError::InvalidEquilibrium(format!(
+            "the Boozer mapping needs at least 2 flux surfaces, got {}",
+            grid.surfaces
+        )));
+    }
+    let (r_axis, z_axis) = axis;
+    let psi_axis = eq.psi(r_axis, z_axis)?;
+    let f_axis = eq.f(psi_axis)?.abs();
+    let b_axis = f_axis / r_axis;
+    let flux_norm = b_axis * r_axis.powi(2);
+    let current_norm = b_axis * r_axis;
+
+    // The normalized flux, which increases from 0 at the axis to 1 at the edge.
+    let level =
+        |r: f64, z: f64| -> Result<f64> { Ok((eq.psi(r, z)? - psi_axis) / (psi_edge - psi_axis)) };
+    let rays: Vec<(f64, f64, f64)> = (0..grid.rays)
+        .map(|j| {
+            let omega = TAU * j as f64 / grid.rays as f64;
+            let (sin, cos) = omega.sin_cos();
+            let on_ray = |r: f64| level(r_axis + r * cos, z_axis + r * sin);
+            Ok((cos, sin, edge_radius(on_ray, r_max)?))
+        })
+        .collect::<Result<_>>()?;
+
+    let theta: Vec<f64> = (0..=grid.theta)
+        .map(|k| TAU * k as f64 / grid.theta as f64)
+        .collect();
+
+    let mut psip = vec![0.0];
+    let mut q = vec![f64::NAN];
+    let mut i = vec![0.0];
+    let mut g = vec![1.0];
+    let mut b = Array2::from_elem((grid.surfaces + 1, theta.len()), 1.0);
+
+    for k in 1..=grid.surfaces {
+        let target = (k as f64 / grid.surfaces as f64).powi(2);
+        let psi_k = psi_axis + target * (psi_edge - psi_axis);
+        let f_k = eq.f(psi_k)?.abs();
+
+        // Position, poloidal field, total field and arc length element of every ray.
+        let mut points = Vec::with_capacity(grid.rays);
+        for (cos, sin, r_edge) in rays.iter() {
+            let on_ray = |r: f64| Ok(level(r_axis + r * cos, z_axis + r * sin)? - target);
+            let r = if k == grid.surfaces {
+                *r_edge
+            } else {
+                bisect(on_ray, 0.0, *r_edge)?
+            };
+            let (big_r, z) = (r_axis + r * cos, z_axis + r * sin);
+            let (psi_r, psi_z) = eq.grad_psi(big_r, z)?;
+            // dr/dω, from Ψ being constant along the surface.
+            let dr = r * (psi_r * sin - psi_z * cos) / (psi_r * cos + psi_z * sin);
+            let dl = (r.powi(2) + dr.powi(2)).sqrt();
+            let bp = psi_r.hypot(psi_z) / big_r;
+            let b2 = bp.powi(2) + (f_k / big_r).powi(2);
+            points.push((big_r, bp, b2, dl));
+        }
+
+        // Periodic trapezoidal rule, where ∮ ... dω is 2π times the mean over the rays.
+        let rays_len = grid.rays as f64;
+        let q_k = f_k
+            * points
+                .iter()
+                .map(|(r, bp, _, dl)| dl / (r * r * bp))
+                .sum::<f64>()
+            / rays_len;
+        let i_k = points.iter().map(|(_, bp, _, dl)| bp * dl).sum::<f64>() / rays_len;
+
+        // θ(ω), normalized to exactly 2π over the surface.
+        let dtheta: Vec<f64> = points.iter().map(|(_, bp, b2, dl)| b2 / bp * dl).collect();
+        let mut theta_rays = Vec::with_capacity(grid.rays + 1);
+        let mut b_rays = Vec::with_capacity(grid.rays + 1);
+        let mut sum = 0.0;
+        for j in 0..=grid.rays {
+            theta_rays.push(sum);
+            b_rays.push(points[j % grid.rays].2.sqrt() / b_axis);
+            sum += 0.5 * (dtheta[j % grid.rays] + dtheta[(j + 1) % grid.rays]);
+        }
+        let total = theta_rays[grid.rays];
+        theta_rays.iter_mut().for_each(|t| *t *= TAU / total);
+        theta_rays[grid.rays] = TAU;
+
+        let spline = make_spline(Interp1d::Cubic.name(), &theta_rays, &b_rays)?;
+        let mut acc = Accelerator::new();
+        for (n, t) in theta.iter().enumerate() {
+            b[[k, n]] = spline.eval(*t, &mut acc)?;
+        }
+
+        psip.push(target * (psi_edge - psi_axis).abs() / flux_norm);
+        q.push(q_k);
+        i.push(i_k / current_norm);
+        g.push(f_k / current_norm);
+    }
+    // The axis value of q, extrapolated linearly in 𝜓ₚ.
+    q[0] = q[1] - (q[2] - q[1]) * psip[1] / (psip[2] - psip[1]);
+
+    let q_spline = make_spline(Interp1d::Cubic.name(), &psip, &q)?;
+    let mut acc = Accelerator::new();
+    let psi = psip
+        .iter()
+        .map(|p| q_spline.eval_integ(0.0, *p, &mut acc))
+        .collect::<std::result::Result<_, _>>()?;
+
+    Ok(BoozerData {
+        psi,
+        q,
+        i,
+        g,
+        theta,
+        b,
+        r_axis,
+        b_axis,
+    })
+}
+
+/// Finds the distance from the axis at which the normalized flux `level` reaches 1.
+fn edge_radius<L>(mut level: L, r_max: f64) -> Result<f64>
+where
+    L: FnMut(f64) -> Result<f64>,
+{
+    const STEPS: usize = 256;
+
+    let mut prev = 0.0;
+    for n in 1..=STEPS {
+        let r = r_max * n as f64 / STEPS as f64;
+        if level(r)? >= 1.0 {
+            return bisect(|r| Ok(level(r)? - 1.0), prev, r);
+        }
+        prev = r;
+    }
+    Err(EqError::RootFindingError(format!(
+        "edge flux surface not found within {r_max} of the magnetic axis"
+    )))
+}
+
+#[cfg(test)]
+mod test {
+    use is_close::is_close;
+
+    use crate::boozer::*;
+
+    /// Large aspect ratio circular equilibrium, with `Ψ = B₀r²/2q₀` and constant `F`.
+    struct Circular {
+        q0: f64,
+    }
+
+    impl FluxFunction for Circular {
+        fn psi(&self, r: f64, z: f64) -> Result<f64> {
+            Ok(((r - 1.0).powi(2) + z.powi(2)) / (2.0 * self.q0))
+        }
+
+        fn grad_psi(&self, r: f64, z: f64) -> Result<(f64, f64)> {
+            Ok(((r - 1.0) / self.q0, z / self.q0))
+        }
+
+        fn f(&self, _: f64) -> Result<f64> {
+            Ok(1.0)
+        }
+    }
+
+    #[test]
+    /// On the circular surfaces of `Ψ = r²/2q₀`, `q = q₀/√(1 − r²)` and `I = r²/(q₀√(1 − r²))`.
+    fn test_circular() {
+        let eq = Circular { q0: 2.0 };
+        let a: f64 = 0.1;
+        let grid = BoozerGrid {
+            surfaces: 20,
+            theta: 64,
+            rays: 256,
+        };
+        let data = boozer_map(&eq, (1.0, 0.0), eq.psi(1.0 + a, 0.0).unwrap(), 0.2, &grid).unwrap();
+
+        let last = grid.surfaces;
+        assert!(is_close!(
+            data.q[last],
+            2.0 / (1.0 - a.powi(2)).sqrt(),
+            rel_tol = 1e-10
+        ));
+        let i = a.powi(2) / (2.0 * (1.0 - a.powi(2)).sqrt());
+        assert!(is_close!(data.i[last], i, rel_tol = 1e-10));
+        let psi = 1.0 - (1.0 - a.powi(2)).sqrt();
+        assert!(is_close!(data.psi[last], psi, rel_tol = 1e-6));
+        assert!(is_close!(data.q[0], 2.0, rel_tol = 1e-4));
+        assert!(data.g.iter().all(|g| *g == 1.0));
+
+        // Up-down symmetry, and the field minimum at the outboard midplane.
+        let b = data.b.row(last);
+        for n in 1..grid.theta {
+            assert!(is_close!(b[n], b[grid.theta - n], rel_tol = 1e-8));
+            assert!(b[n] > b[0]);
+        }
+        let b0 = (1.0 + (a / 2.0).powi(2)).sqrt() / (1.0 + a);
+        assert!(is_close!(b[0], b0, rel_tol = 1e-10));
+
+        let grid = BoozerGrid {
+            surfaces: 1,
+            ..grid
+        };
+        let err = boozer_map(&eq, (1.0, 0.0), eq.psi(1.0 + a, 0.0).unwrap(), 0.2, &grid);
+        assert!(matches!(err, Err(EqError::InvalidEquilibrium(_))));
+    }
+}
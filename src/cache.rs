@@ -0,0 +1,46 @@
+//! Evaluation caches of the profiles.
+//!
+//! Every profile declares the state it needs during evaluation through its associated `Cache`
+//! type. Numerical profiles keep their spline [`Accelerator`]s there, while analytical profiles
+//! use the zero-sized [`NoCache`], so they carry no state at all.
+//!
+//! Caches are cheap to create, but must not be shared between threads. Each thread should create
+//! its own with the profile's `cache()` method, or [`Tokamak::cache()`](crate::Tokamak::cache)
+//! for a whole equilibrium.
+
+use rsl_interpolation::Accelerator;
+
+/// Cache of profiles that need no evaluation state.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoCache;
+
+/// Cache of profiles defined over ψ.
+pub struct PsiCache {
+    /// The ψ-[`Accelerator`].
+    pub psi_acc: Accelerator,
+}
+
+impl Default for PsiCache {
+    fn default() -> Self {
+        Self {
+            psi_acc: Accelerator::new(),
+        }
+    }
+}
+
+/// Cache of profiles defined over (ψ, θ).
+pub struct PsiThetaCache {
+    /// The ψ-[`Accelerator`].
+    pub psi_acc: Accelerator,
+    /// The θ-[`Accelerator`].
+    pub theta_acc: Accelerator,
+}
+
+impl Default for PsiThetaCache {
+    fn default() -> Self {
+        Self {
+            psi_acc: Accelerator::new(),
+            theta_acc: Accelerator::new(),
+        }
+    }
+}
@@ -1,6 +1,5 @@
-use rsl_interpolation::Accelerator;
-
 use crate::Result;
+use crate::cache::NoCache;
 use crate::current::Current;
 
 /// Representation of Large Aspect Ratio plasma currents.
@@ -27,45 +26,45 @@ impl Lar {
 }
 
 impl Current for Lar {
+    type Cache = NoCache;
+
     /// Always returns `0.0`.
     #[allow(unused_variables)]
-    fn i(&self, psi: f64, acc: &mut Accelerator) -> Result<f64> {
+    fn i(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
         Ok(0.0)
     }
 
     /// Always returns `1.0`.
     #[allow(unused_variables)]
-    fn g(&self, psi: f64, acc: &mut Accelerator) -> Result<f64> {
+    fn g(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
         Ok(1.0)
     }
 
     /// Always returns `0.0`.
     #[allow(unused_variables)]
-    fn i_der(&self, psi: f64, acc: &mut Accelerator) -> Result<f64> {
+    fn i_der(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
         Ok(0.0)
     }
 
     /// Always returns `0.0`.
     #[allow(unused_variables)]
-    fn g_der(&self, psi: f64, acc: &mut Accelerator) -> Result<f64> {
+    fn g_der(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
         Ok(0.0)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use rsl_interpolation::Accelerator;
-
     use crate::*;
 
     #[test]
     fn test_lar_current() {
-        let mut acc = Accelerator::new();
         let current = current::Lar::new().unwrap();
+        let mut cache = current.cache();
 
-        assert_eq!(current.i(0.0, &mut acc).unwrap(), 0.0);
-        assert_eq!(current.g(0.0, &mut acc).unwrap(), 1.0);
-        assert_eq!(current.i_der(0.0, &mut acc).unwrap(), 0.0);
-        assert_eq!(current.g_der(0.0, &mut acc).unwrap(), 0.0);
+        assert_eq!(current.i(0.0, &mut cache).unwrap(), 0.0);
+        assert_eq!(current.g(0.0, &mut cache).unwrap(), 1.0);
+        assert_eq!(current.i_der(0.0, &mut cache).unwrap(), 0.0);
+        assert_eq!(current.g_der(0.0, &mut cache).unwrap(), 0.0);
     }
 }
@@ -12,23 +12,45 @@ pub use numerical::Numerical;
 
 /// Calculation of plasma current related quantities.
 pub trait Current {
+    /// The evaluation cache of the current profile.
+    ///
+    /// Analytical profiles use [`NoCache`](crate::cache::NoCache), numerical profiles hold the
+    /// spline accelerators.
+    type Cache: Default;
+
+    /// Creates a new evaluation cache, to be passed to every evaluation method.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let cur = current::Numerical::from_dataset(&"./data.nc".into(), Interp1d::Cubic)?;
+    /// let mut cache = cur.cache();
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn cache(&self) -> Self::Cache {
+        Self::Cache::default()
+    }
+
     /// Calculates `I(ψ, θ)`
     ///
     /// # Example
     ///
     /// ```
     /// # use tokamak_equilibria::*;
-    /// # use rsl_interpolation::*;
     /// #
     /// # fn main() -> Result<()> {
-    /// let mut acc = Accelerator::new();
     /// let cur = current::Lar::new()?;
+    /// let mut cache = cur.cache();
     ///
-    /// let i = cur.i(0.015, &mut acc)?;
+    /// let i = cur.i(0.015, &mut cache)?;
     /// # Ok(())
     /// # }
     /// ```
-    fn i(&self, psi: f64, acc: &mut Accelerator) -> Result<f64>;
+    fn i(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64>;
 
     /// Calculates `g(ψ, θ)`
     ///
@@ -36,17 +58,16 @@ pub trait Current {
     ///
     /// ```
     /// # use tokamak_equilibria::*;
-    /// # use rsl_interpolation::*;
     /// #
     /// # fn main() -> Result<()> {
-    /// let mut acc = Accelerator::new();
     /// let cur = current::Lar::new()?;
+    /// let mut cache = cur.cache();
     ///
-    /// let g = cur.g(0.015, &mut acc)?;
+    /// let g = cur.g(0.015, &mut cache)?;
     /// # Ok(())
     /// # }
     /// ```
-    fn g(&self, psi: f64, acc: &mut Accelerator) -> Result<f64>;
+    fn g(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64>;
 
     /// Calculates `𝜕I(ψ, θ)/𝜕ψ`
     ///
@@ -54,13 +75,12 @@ pub trait Current {
     ///
     /// ```
     /// # use tokamak_equilibria::*;
-    /// # use rsl_interpolation::*;
     /// #
     /// # fn main() -> Result<()> {
-    /// let mut acc = Accelerator::new();
     /// let cur = current::Lar::new()?;
+    /// let mut cache = cur.cache();
     ///
-    /// let i_der = cur.i_der(0.015, &mut acc)?;
+    /// let i_der = cur.i_der(0.015, &mut cache)?;
     /// # Ok(())
     /// # }
     /// ```
@@ -70,7 +90,7 @@ pub trait Current {
     /// Current derivatives are calculated with respect to `ψ`, and not `𝜓ₚ`, which appears in the
     /// guiding center equations of motion. To get the derivatives with respect to `𝜓ₚ`, we can
     /// simply multiply with `q(ψ)`.
-    fn i_der(&self, psi: f64, acc: &mut Accelerator) -> Result<f64>;
+    fn i_der(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64>;
 
     /// Calculates `𝜕g(ψ, θ)/𝜕ψ`
     ///
@@ -78,13 +98,12 @@ pub trait Current {
     ///
     /// ```
     /// # use tokamak_equilibria::*;
-    /// # use rsl_interpolation::*;
     /// #
     /// # fn main() -> Result<()> {
-    /// let mut acc = Accelerator::new();
     /// let cur = current::Lar::new()?;
+    /// let mut cache = cur.cache();
     ///
-    /// let g_der = cur.g_der(0.015, &mut acc)?;
+    /// let g_der = cur.g_der(0.015, &mut cache)?;
     /// # Ok(())
     /// # }
     /// ```
@@ -94,5 +113,19 @@ pub trait Current {
     /// Current derivatives are calculated with respect to `ψ`, and not `𝜓ₚ`, which appears in the
     /// guiding center equations of motion. To get the derivatives with respect to `𝜓ₚ`, we can
     /// simply multiply with `q(ψ)`.
-    fn g_der(&self, psi: f64, acc: &mut Accelerator) -> Result<f64>;
+    fn g_der(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64>;
+
+    /// The ψ grid of the current splines, if the profile is interpolated over ψ.
+    ///
+    /// Profiles over the same ψ grid pass their [`psi_acc`](Self::psi_acc) to one another in
+    /// [`Tokamak::eval`](crate::Tokamak::eval), so that the ψ cell is only located once.
+    fn psi_grid(&self) -> Option<&[f64]> {
+        None
+    }
+
+    /// The ψ-[`Accelerator`] of `cache`, if the profile is interpolated over ψ.
+    #[allow(unused_variables)]
+    fn psi_acc<'a>(&self, cache: &'a mut Self::Cache) -> Option<&'a mut Accelerator> {
+        None
+    }
 }
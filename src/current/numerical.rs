@@ -1,9 +1,13 @@
 use std::path::PathBuf;
 
 use rsl_interpolation::{Accelerator, DynSpline};
+use tokamak_netcdf::Equilibrium;
 
 use crate::Current;
+use crate::Interp1d;
 use crate::Result;
+use crate::cache::PsiCache;
+use crate::dataset;
 
 /// Plasma current reconstructed from a netCDF file.
 pub struct Numerical {
@@ -28,95 +32,160 @@ impl Numerical {
     /// #
     /// # fn main() -> Result<()> {
     /// let path = PathBuf::from("./data.nc");
-    /// let cur = current::Numerical::from_dataset(&path, "cubic")?;
+    /// let cur = current::Numerical::from_dataset(&path, Interp1d::Cubic)?;
     /// # Ok(())
     /// # }
     /// ```
-    pub fn from_dataset(path: &PathBuf, typ: &str) -> Result<Self> {
-        use rsl_interpolation::*;
+    pub fn from_dataset(path: &PathBuf, typ: Interp1d) -> Result<Self> {
+        Self::from_equilibrium(&Equilibrium::from_file(path)?, typ)
+    }
+
+    /// Constructs a [`Current`] from an already opened netCDF [`Equilibrium`], with spline of
+    /// `typ` interpolation type.
+    ///
+    /// The axis values are treated exactly as in [`Numerical::from_dataset`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use tokamak_equilibria::*;
+    /// # use std::path::PathBuf;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from("./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let cur = current::Numerical::from_equilibrium(&eq, Interp1d::Cubic)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_equilibrium(eq: &Equilibrium, typ: Interp1d) -> Result<Self> {
         use tokamak_netcdf::variable_names::*;
-        use tokamak_netcdf::*;
 
-        let eq = Equilibrium::from_file(path)?;
+        let psi_data = dataset::psi_coord(eq)?;
+        let i_data = dataset::flux_function(eq, CURRENT_I)?;
+        let g_data = dataset::flux_function(eq, CURRENT_G)?;
+
+        Self::from_arrays(&psi_data, &i_data, &g_data, typ)
+    }
+
+    /// Constructs a [`Current`] from the ψ, I and g data arrays, with splines of `typ`
+    /// interpolation type.
+    ///
+    /// `psi_data` must be strictly increasing, and `i_data` and `g_data` must have the same
+    /// length. All values must be finite.
+    ///
+    /// # Example
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let psi_data = [0.0, 0.025, 0.05, 0.075, 0.1];
+    /// let i_data = [0.0, 0.01, 0.03, 0.05, 0.06];
+    /// let g_data = [1.0, 0.99, 0.98, 0.97, 0.96];
+    /// let cur = current::Numerical::from_arrays(&psi_data, &i_data, &g_data, Interp1d::Cubic)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_arrays(
+        psi_data: &[f64],
+        i_data: &[f64],
+        g_data: &[f64],
+        typ: Interp1d,
+    ) -> Result<Self> {
+        dataset::validate_grid("ψ", psi_data)?;
+        dataset::validate_values("I", i_data, psi_data.len())?;
+        dataset::validate_values("g", g_data, psi_data.len())?;
 
-        // Add 0.0 manualy, which corresponds to q0.
-        let psi_data = extract_var_with_axis_value(&eq.file, PSI_COORD, 0.0)?
-            .as_standard_layout()
-            .to_vec();
-        // Manually add q0 to the array.
-        let i_data = extract_var_with_first_axis_value(&eq.file, CURRENT_I)?
-            .as_standard_layout()
-            .to_vec();
-        let g_data = extract_var_with_first_axis_value(&eq.file, CURRENT_G)?
-            .as_standard_layout()
-            .to_vec();
+        use rsl_interpolation::*;
 
-        let i_spline = make_spline(typ, &psi_data, &i_data)?;
-        let g_spline = make_spline(typ, &psi_data, &g_data)?;
+        let i_spline = make_spline(typ.name(), psi_data, i_data)?;
+        let g_spline = make_spline(typ.name(), psi_data, g_data)?;
 
         Ok(Self { i_spline, g_spline })
     }
 }
 
 impl Current for Numerical {
-    fn i(&self, psi: f64, acc: &mut Accelerator) -> Result<f64> {
-        Ok(self.i_spline.eval(psi, acc)?)
+    type Cache = PsiCache;
+
+    fn i(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
+        Ok(self.i_spline.eval(psi, &mut cache.psi_acc)?)
+    }
+
+    fn g(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
+        Ok(self.g_spline.eval(psi, &mut cache.psi_acc)?)
+    }
+
+    fn i_der(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
+        Ok(self.i_spline.eval_deriv(psi, &mut cache.psi_acc)?)
     }
 
-    fn g(&self, psi: f64, acc: &mut Accelerator) -> Result<f64> {
-        Ok(self.g_spline.eval(psi, acc)?)
+    fn g_der(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
+        Ok(self.g_spline.eval_deriv(psi, &mut cache.psi_acc)?)
     }
 
-    fn i_der(&self, psi: f64, acc: &mut Accelerator) -> Result<f64> {
-        Ok(self.i_spline.eval_deriv(psi, acc)?)
+    fn psi_grid(&self) -> Option<&[f64]> {
+        Some(&self.i_spline.xa)
     }
 
-    fn g_der(&self, psi: f64, acc: &mut Accelerator) -> Result<f64> {
-        Ok(self.g_spline.eval_deriv(psi, acc)?)
+    fn psi_acc<'a>(&self, cache: &'a mut Self::Cache) -> Option<&'a mut Accelerator> {
+        Some(&mut cache.psi_acc)
     }
 }
 
 #[cfg(test)]
 mod test {
     use is_close::is_close;
-    use std::path::PathBuf;
-
-    use rsl_interpolation::Accelerator;
 
     use crate::current::Numerical;
+    use crate::testing::{TempFile, parabolic};
     use crate::*;
 
-    /// Values cross-tested with gcmotion.
     #[test]
-    #[ignore = "needs specific dataset"]
-    fn test_numeric_current_values() {
-        let path = PathBuf::from("./reconstructed/smart_positive.nc");
-
-        let mut acc = Accelerator::new();
-        let cur = Numerical::from_dataset(&path, "Akima").unwrap();
-
-        assert_eq!(cur.i(0.0, &mut acc).unwrap(), 0.0012294990364400897); // inserted value
-        assert_eq!(cur.g(0.0, &mut acc).unwrap(), 0.9985398705655125); // inserted value
-        // Use a relatively high relative tolerance, since the splines are not exactly the same.
-        assert!(is_close!(
-            cur.i(0.1, &mut acc).unwrap(),
-            0.1433092088696332,
-            rel_tol = 1e-4
-        ));
-        assert!(is_close!(
-            cur.g(0.1, &mut acc).unwrap(),
-            0.8575838128118375,
-            rel_tol = 1e-4
-        ));
-        assert!(is_close!(
-            cur.i(0.19889475414290547, &mut acc).unwrap(),
-            0.17214836970426942,
-            rel_tol = 1e-4
-        ));
-        assert!(is_close!(
-            cur.g(0.19889475414290547, &mut acc).unwrap(),
-            0.8416486417160426,
-            rel_tol = 1e-4
-        ));
+    /// Splines over linear data are exact.
+    fn test_from_arrays() {
+        let psi_data: Vec<f64> = (0..=20).map(|k| 0.005 * k as f64).collect();
+        let i_data: Vec<f64> = psi_data.iter().map(|psi| 0.5 * psi).collect();
+        let g_data: Vec<f64> = psi_data.iter().map(|psi| 1.0 - psi).collect();
+        let cur = Numerical::from_arrays(&psi_data, &i_data, &g_data, Interp1d::Cubic).unwrap();
+        let mut cache = cur.cache();
+
+        assert!(is_close!(cur.i(0.0333, &mut cache).unwrap(), 0.01665));
+        assert!(is_close!(cur.g(0.0333, &mut cache).unwrap(), 0.9667));
+        assert!(is_close!(cur.i_der(0.0333, &mut cache).unwrap(), 0.5));
+        assert!(is_close!(cur.g_der(0.0333, &mut cache).unwrap(), -1.0));
+
+        let err = Numerical::from_arrays(&psi_data, &i_data, &g_data[1..], Interp1d::Cubic);
+        assert!(matches!(err, Err(EqError::InvalidData(_))));
+        let mut reversed = psi_data.clone();
+        reversed.reverse();
+        let err = Numerical::from_arrays(&reversed, &i_data, &g_data, Interp1d::Cubic);
+        assert!(matches!(err, Err(EqError::InvalidData(_))));
+    }
+
+    #[test]
+    /// Splines over the linear data written by [`Tokamak::to_dataset`] are exact.
+    fn test_dataset_values() {
+        let psi_data: Vec<f64> = (0..=20).map(|k| 0.00625 * k as f64).collect();
+        let i_data: Vec<f64> = psi_data.iter().map(|psi| 0.5 * psi).collect();
+        let g_data: Vec<f64> = psi_data.iter().map(|psi| 1.0 - psi).collect();
+        let current = Numerical::from_arrays(&psi_data, &i_data, &g_data, Interp1d::Cubic).unwrap();
+        let bfield = bfield::Lar::new().unwrap();
+        let efield = efield::NoEfield::new().unwrap();
+        let eq = Tokamak::build(parabolic(), bfield, current, efield).unwrap();
+
+        let file = TempFile::new("current_values");
+        eq.to_dataset(&file.path, &SampleGrid::new(0.125)).unwrap();
+        let cur = Numerical::from_dataset(&file.path, Interp1d::Akima).unwrap();
+        let mut cache = cur.cache();
+
+        // The axis values duplicate the first sampled ones.
+        assert!(is_close!(cur.i(0.0, &mut cache).unwrap(), 0.5 * 0.00125));
+        assert!(is_close!(cur.g(0.0, &mut cache).unwrap(), 1.0 - 0.00125));
+        for psi in [0.0333, 0.1, 0.125] {
+            assert!(is_close!(cur.i(psi, &mut cache).unwrap(), 0.5 * psi));
+            assert!(is_close!(cur.g(psi, &mut cache).unwrap(), 1.0 - psi));
+            assert!(is_close!(cur.i_der(psi, &mut cache).unwrap(), 0.5));
+            assert!(is_close!(cur.g_der(psi, &mut cache).unwrap(), -1.0));
+        }
     }
 }
@@ -0,0 +1,129 @@
+//! Extraction of the data shared by all numerical profiles from a netCDF [`Equilibrium`],
+//! validation of the data arrays they are constructed from, and writing of the same layout.
+//!
+//! All numerical profiles must be constructed over the same ψ grid, with the same treatment of
+//! the magnetic axis, so the extraction logic lives here instead of each profile.
+
+use std::path::Path;
+
+use ndarray::Array2;
+use tokamak_netcdf::variable_names::*;
+use tokamak_netcdf::*;
+
+use crate::units::Units;
+use crate::{EqError, Result};
+
+/// Extracts the ψ coordinate, with the axis value `ψ = 0.0` prepended.
+pub(crate) fn psi_coord(eq: &Equilibrium) -> Result<Vec<f64>> {
+    Ok(extract_var_with_axis_value(&eq.file, PSI_COORD, 0.0)?
+        .as_standard_layout()
+        .to_vec())
+}
+
+/// Extracts the θ coordinate.
+pub(crate) fn theta_coord(eq: &Equilibrium) -> Result<Vec<f64>> {
+    Ok(eq.get_1d(THETA_COORD)?.to_vec())
+}
+
+/// Extracts the 1D flux function `name`, with its first value prepended (duplicated), so that it
+/// matches the grid returned by [`psi_coord`].
+pub(crate) fn flux_function(eq: &Equilibrium, name: &str) -> Result<Vec<f64>> {
+    Ok(extract_var_with_first_axis_value(&eq.file, name)?
+        .as_standard_layout()
+        .to_vec())
+}
+
+/// The names of the scalar variables holding the [`Units`] of the dataset.
+pub(crate) const B_AXIS: &str = "baxis";
+pub(crate) const R_AXIS: &str = "raxis";
+
+/// The sampled profiles of an equilibrium, without the axis values.
+pub(crate) struct Profiles {
+    pub psi: Vec<f64>,
+    pub theta: Vec<f64>,
+    pub q: Vec<f64>,
+    pub i: Vec<f64>,
+    pub g: Vec<f64>,
+    pub b: Array2<f64>,
+}
+
+/// Writes the `profiles`, and the `units` if known, in the layout read by [`Equilibrium`].
+pub(crate) fn write(path: &Path, profiles: &Profiles, units: Option<&Units>) -> Result<()> {
+    let Profiles {
+        psi,
+        theta,
+        q,
+        i,
+        g,
+        b,
+    } = profiles;
+    let mut file = netcdf::create(path)?;
+    file.add_dimension(PSI_COORD, psi.len())?;
+    file.add_dimension(THETA_COORD, theta.len())?;
+
+    file.add_variable::<f64>(PSI_COORD, &[PSI_COORD])?
+        .put_values(psi, ..)?;
+    file.add_variable::<f64>(THETA_COORD, &[THETA_COORD])?
+        .put_values(theta, ..)?;
+    for (name, data) in [(Q_FACTOR, q), (CURRENT_I, i), (CURRENT_G, g)] {
+        file.add_variable::<f64>(name, &[PSI_COORD])?
+            .put_values(data, ..)?;
+    }
+    file.add_variable::<f64>(B_FIELD, &[PSI_COORD, THETA_COORD])?
+        .put_values(&b.flatten().to_vec(), ..)?;
+
+    if let Some(units) = units {
+        file.add_variable::<f64>(B_AXIS, &[])?
+            .put_value(units.b0, ..)?;
+        file.add_variable::<f64>(R_AXIS, &[])?
+            .put_value(units.r0, ..)?;
+    }
+    Ok(())
+}
+
+/// Reads the [`Units`] of the dataset, if it contains both `B_AXIS` and `R_AXIS`.
+pub(crate) fn units(eq: &Equilibrium) -> Result<Option<Units>> {
+    let scalar = |name| {
+        eq.file
+            .variable(name)
+            .map(|var| var.get_value::<f64, _>(..))
+            .transpose()
+    };
+    match (scalar(B_AXIS)?, scalar(R_AXIS)?) {
+        (Some(b0), Some(r0)) => Ok(Some(Units::new(b0, r0)?)),
+        _ => Ok(None),
+    }
+}
+
+/// Checks that the grid `data` is finite and strictly increasing.
+pub(crate) fn validate_grid(name: &str, data: &[f64]) -> Result<()> {
+    validate_finite(name, data)?;
+    if let Some(k) = data.windows(2).position(|w| w[1] <= w[0]) {
+        return Err(EqError::InvalidData(format!(
+            "{name} is not strictly increasing at index {}",
+            k + 1
+        )));
+    }
+    Ok(())
+}
+
+/// Checks that the values `data` are finite and match the length `len` of their grid.
+pub(crate) fn validate_values(name: &str, data: &[f64], len: usize) -> Result<()> {
+    if data.len() != len {
+        return Err(EqError::InvalidData(format!(
+            "{name} has length {}, expected {len}",
+            data.len()
+        )));
+    }
+    validate_finite(name, data)
+}
+
+fn validate_finite(name: &str, data: &[f64]) -> Result<()> {
+    match data.iter().position(|x| !x.is_finite()) {
+        Some(k) => Err(EqError::InvalidData(format!(
+            "{name} has non-finite value {} at index {k}",
+            data[k]
+        ))),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,91 @@
+use crate::Result;
+use crate::cache::NoCache;
+use crate::efield::{Efield, radial_field, validate_potential};
+
+/// Electric potential linear in ψ.
+///
+/// `Φ` is given by the equation `Φ(ψ) = Φ₀ + (Φ_w − Φ₀)ψ/ψ_w`, so that the radial field
+/// `E ∝ √ψ` grows linearly with the minor radius.
+pub struct Linear {
+    /// The potential at the magnetic axis.
+    pub phi0: f64,
+    /// The potential at the wall.
+    pub phi_wall: f64,
+    /// The toroidal flux value at the wall.
+    pub psi_wall: f64,
+}
+
+impl Linear {
+    /// Creates a new linear electric potential.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let efield = efield::Linear::new(1e-4, 0.0, 0.125)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(phi0: f64, phi_wall: f64, psi_wall: f64) -> Result<Self> {
+        validate_potential(&[phi0, phi_wall], psi_wall)?;
+        Ok(Self {
+            phi0,
+            phi_wall,
+            psi_wall,
+        })
+    }
+}
+
+impl Efield for Linear {
+    type Cache = NoCache;
+
+    #[allow(unused_variables)]
+    fn phi(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        Ok(self.phi0 + (self.phi_wall - self.phi0) * psi / self.psi_wall)
+    }
+
+    fn e(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        Ok(radial_field(psi, self.dphi_dpsi(psi, theta, cache)?))
+    }
+
+    #[allow(unused_variables)]
+    fn dphi_dpsi(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        Ok((self.phi_wall - self.phi0) / self.psi_wall)
+    }
+
+    /// Always returns `0.0`.
+    #[allow(unused_variables)]
+    fn dphi_dtheta(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        Ok(0.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use is_close::is_close;
+
+    use crate::efield::test::check_derivatives;
+    use crate::*;
+
+    #[test]
+    fn test_linear() {
+        let efield = efield::Linear::new(1e-4, 0.0, 0.125).unwrap();
+        let mut cache = efield.cache();
+
+        assert!(is_close!(
+            efield.phi(0.0625, 0.0, &mut cache).unwrap(),
+            5e-5
+        ));
+        // E = −r⋅𝜕Φ/𝜕ψ, at r = 0.5.
+        assert!(is_close!(efield.e(0.125, 0.0, &mut cache).unwrap(), 4e-4));
+        for psi in [0.01, 0.05, 0.1] {
+            check_derivatives(&efield, psi);
+        }
+        assert!(efield::Linear::new(f64::NAN, 0.0, 0.125).is_err());
+    }
+}
@@ -1,50 +1,137 @@
 //! Various electric field profiles.
+//!
+//! The analytical profiles are electrostatic potentials `Φ(ψ)` which are flux functions, so that
+//! the electric field is purely radial. With the minor radius `r = √(2ψ)` of the large aspect
+//! ratio limit, the radial field is `E = −𝜕Φ/𝜕r = −√(2ψ)⋅𝜕Φ/𝜕ψ`.
 
-use rsl_interpolation::Accelerator;
-
-use crate::Result;
+use crate::{EqError, Result};
 
+mod linear;
 mod nofield;
+mod parabolic;
+mod pedestal;
 
+pub use linear::Linear;
 pub use nofield::*;
+pub use parabolic::Parabolic;
+pub use pedestal::{Pedestal, PedestalShape};
 
 /// Calculation of electric field related quantities.
 pub trait Efield {
-    // TODO: add examples.
+    /// The evaluation cache of the electric field profile.
+    ///
+    /// Analytical profiles use [`NoCache`](crate::cache::NoCache).
+    type Cache: Default;
+
+    /// Creates a new evaluation cache, to be passed to every evaluation method.
+    fn cache(&self) -> Self::Cache {
+        Self::Cache::default()
+    }
 
     /// Calculates `Φ(ψ, θ)`.
-    fn phi(
-        &self,
-        psi: f64,
-        theta: f64,
-        xacc: &mut Accelerator,
-        yacc: &mut Accelerator,
-    ) -> Result<f64>;
-
-    /// Calculates `E(ψ, θ)`.
-    fn e(
-        &self,
-        psi: f64,
-        theta: f64,
-        xacc: &mut Accelerator,
-        yacc: &mut Accelerator,
-    ) -> Result<f64>;
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let efield = efield::Parabolic::new(1e-4, 0.0, 0.125)?;
+    /// let mut cache = efield.cache();
+    ///
+    /// let phi = efield.phi(0.05, 1.0, &mut cache)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn phi(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64>;
+
+    /// Calculates the radial electric field `E(ψ, θ) = −𝜕Φ/𝜕r`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let efield = efield::Parabolic::new(1e-4, 0.0, 0.125)?;
+    /// let mut cache = efield.cache();
+    ///
+    /// let e = efield.e(0.05, 1.0, &mut cache)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn e(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64>;
 
     /// Calculates `𝜕𝛷 /𝜕𝜓`.
-    fn dphi_dpsi(
-        &self,
-        psi: f64,
-        theta: f64,
-        xacc: &mut Accelerator,
-        yacc: &mut Accelerator,
-    ) -> Result<f64>;
-
-    /// Calculates `𝜕²𝛷 /𝜕𝜓²`.
-    fn dphi_dtheta(
-        &self,
-        psi: f64,
-        theta: f64,
-        xacc: &mut Accelerator,
-        yacc: &mut Accelerator,
-    ) -> Result<f64>;
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let efield = efield::Parabolic::new(1e-4, 0.0, 0.125)?;
+    /// let mut cache = efield.cache();
+    ///
+    /// let dphi_dpsi = efield.dphi_dpsi(0.05, 1.0, &mut cache)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn dphi_dpsi(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64>;
+
+    /// Calculates `𝜕𝛷 /𝜕𝜃`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let efield = efield::Parabolic::new(1e-4, 0.0, 0.125)?;
+    /// let mut cache = efield.cache();
+    ///
+    /// let dphi_dtheta = efield.dphi_dtheta(0.05, 1.0, &mut cache)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn dphi_dtheta(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64>;
+}
+
+/// Returns the radial field `E = −√(2ψ)⋅𝜕Φ/𝜕ψ`.
+fn radial_field(psi: f64, dphi_dpsi: f64) -> f64 {
+    -(2.0 * psi).sqrt() * dphi_dpsi
+}
+
+/// Checks that the potential is defined up to a positive `psi_wall`.
+fn validate_potential(values: &[f64], psi_wall: f64) -> Result<()> {
+    if !(psi_wall > 0.0 && values.iter().all(|v| v.is_finite())) {
+        return Err(EqError::InvalidEquilibrium(format!(
+            "invalid potential {values:?} with ψ_w = {psi_wall}"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use is_close::is_close;
+
+    use crate::efield::*;
+
+    /// Checks `𝜕Φ/𝜕ψ` and `E` against central finite differences of `Φ`, in ψ and in `r = √(2ψ)`.
+    pub(crate) fn check_derivatives<E: Efield>(efield: &E, psi: f64) {
+        let mut cache = efield.cache();
+        let mut phi = |psi| efield.phi(psi, 1.0, &mut cache).unwrap();
+        let h = 1e-6;
+        let dphi = (phi(psi + h) - phi(psi - h)) / (2.0 * h);
+        let r = (2.0 * psi).sqrt();
+        let e = -(phi((r + h).powi(2) / 2.0) - phi((r - h).powi(2) / 2.0)) / (2.0 * h);
+
+        let mut cache = efield.cache();
+        let exact = efield.dphi_dpsi(psi, 1.0, &mut cache).unwrap();
+        assert!(is_close!(exact, dphi, rel_tol = 1e-6, abs_tol = 1e-12));
+        let exact = efield.e(psi, 1.0, &mut cache).unwrap();
+        assert!(is_close!(exact, e, rel_tol = 1e-6, abs_tol = 1e-12));
+        assert_eq!(efield.dphi_dtheta(psi, 1.0, &mut cache).unwrap(), 0.0);
+    }
 }
@@ -0,0 +1,90 @@
+use crate::Result;
+use crate::cache::NoCache;
+use crate::efield::{Efield, radial_field, validate_potential};
+
+/// Parabolic electric potential.
+///
+/// `Φ` is given by the equation `Φ(ψ) = Φ₀ + (Φ_w − Φ₀)(ψ/ψ_w)²`.
+pub struct Parabolic {
+    /// The potential at the magnetic axis.
+    pub phi0: f64,
+    /// The potential at the wall.
+    pub phi_wall: f64,
+    /// The toroidal flux value at the wall.
+    pub psi_wall: f64,
+}
+
+impl Parabolic {
+    /// Creates a new parabolic electric potential.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let efield = efield::Parabolic::new(1e-4, 0.0, 0.125)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(phi0: f64, phi_wall: f64, psi_wall: f64) -> Result<Self> {
+        validate_potential(&[phi0, phi_wall], psi_wall)?;
+        Ok(Self {
+            phi0,
+            phi_wall,
+            psi_wall,
+        })
+    }
+}
+
+impl Efield for Parabolic {
+    type Cache = NoCache;
+
+    #[allow(unused_variables)]
+    fn phi(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        Ok(self.phi0 + (self.phi_wall - self.phi0) * (psi / self.psi_wall).powi(2))
+    }
+
+    fn e(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        Ok(radial_field(psi, self.dphi_dpsi(psi, theta, cache)?))
+    }
+
+    #[allow(unused_variables)]
+    fn dphi_dpsi(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        Ok(2.0 * (self.phi_wall - self.phi0) * psi / self.psi_wall.powi(2))
+    }
+
+    /// Always returns `0.0`.
+    #[allow(unused_variables)]
+    fn dphi_dtheta(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        Ok(0.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use is_close::is_close;
+
+    use crate::efield::test::check_derivatives;
+    use crate::*;
+
+    #[test]
+    fn test_parabolic() {
+        let efield = efield::Parabolic::new(1e-4, -2e-4, 0.125).unwrap();
+        let mut cache = efield.cache();
+
+        assert_eq!(efield.phi(0.0, 0.0, &mut cache).unwrap(), 1e-4);
+        assert!(is_close!(
+            efield.phi(0.125, 0.0, &mut cache).unwrap(),
+            -2e-4
+        ));
+        assert_eq!(efield.e(0.0, 0.0, &mut cache).unwrap(), 0.0);
+        for psi in [0.01, 0.05, 0.1] {
+            check_derivatives(&efield, psi);
+        }
+        assert!(efield::Parabolic::new(1e-4, 0.0, 0.0).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use std::f64::consts::PI;
+
+use crate::cache::NoCache;
+use crate::efield::{Efield, radial_field};
+use crate::numerics::erf;
+use crate::{EqError, Result};
+
+/// The shape of the `𝜕Φ/𝜕ψ` peak of a [`Pedestal`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PedestalShape {
+    /// `𝜕Φ/𝜕ψ ∝ sech²((ψ − ψ_p)/w)`.
+    Tanh,
+    /// `𝜕Φ/𝜕ψ ∝ exp(−((ψ − ψ_p)/w)²)`.
+    Gaussian,
+}
+
+/// Electric potential of an H-mode pedestal, with a radial field well at ψ_p.
+///
+/// With `x = (ψ − ψ_p)/w`, `𝜕Φ/𝜕ψ = D⋅s(x)` for the [`PedestalShape`] `s`, where
+/// `D = d/√(2ψ_p)`, so that the radial field at the bottom of the well is `E(ψ_p) = −d`. The
+/// potential vanishes at the magnetic axis:
+///
+/// ```text
+/// Tanh:      Φ = Dw⋅(tanh(x) − tanh(x₀))
+/// Gaussian:  Φ = Dw⋅(√π/2)⋅(erf(x) − erf(x₀)),   x₀ = −ψ_p/w
+/// ```
+pub struct Pedestal {
+    /// The shape of the well.
+    pub shape: PedestalShape,
+    /// The location ψ_p of the well.
+    pub psi_ped: f64,
+    /// The width w of the well, in ψ.
+    pub width: f64,
+    /// The depth d of the well, the opposite of the radial field at ψ_p.
+    pub depth: f64,
+    /// Intermediate quantity to avoid recalculation. Is equal to `D`.
+    slope: f64,
+}
+
+impl Pedestal {
+    /// Creates a new pedestal potential with a well of `shape`, at `psi_ped`, of `width` and
+    /// `depth`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// use efield::PedestalShape;
+    ///
+    /// let efield = efield::Pedestal::new(PedestalShape::Tanh, 0.11, 0.005, 1e-3)?;
+    /// let e = efield.e(0.11, 0.0, &mut efield.cache())?;
+    /// assert!((e + 1e-3).abs() < 1e-15);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(shape: PedestalShape, psi_ped: f64, width: f64, depth: f64) -> Result<Self> {
+        if !(psi_ped > 0.0 && width > 0.0 && depth.is_finite() && psi_ped.is_finite()) {
+            return Err(EqError::InvalidEquilibrium(format!(
+                "invalid pedestal with ψ_p = {psi_ped}, w = {width} and d = {depth}"
+            )));
+        }
+        Ok(Self {
+            shape,
+            psi_ped,
+            width,
+            depth,
+            slope: depth / (2.0 * psi_ped).sqrt(),
+        })
+    }
+
+    /// The antiderivative of the shape `s(x)`.
+    fn integral(&self, x: f64) -> f64 {
+        match self.shape {
+            PedestalShape::Tanh => x.tanh(),
+            PedestalShape::Gaussian => 0.5 * PI.sqrt() * erf(x),
+        }
+    }
+}
+
+impl Efield for Pedestal {
+    type Cache = NoCache;
+
+    #[allow(unused_variables)]
+    fn phi(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        let x = (psi - self.psi_ped) / self.width;
+        let x0 = -self.psi_ped / self.width;
+        Ok(self.slope * self.width * (self.integral(x) - self.integral(x0)))
+    }
+
+    fn e(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        Ok(radial_field(psi, self.dphi_dpsi(psi, theta, cache)?))
+    }
+
+    #[allow(unused_variables)]
+    fn dphi_dpsi(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        let x = (psi - self.psi_ped) / self.width;
+        let shape = match self.shape {
+            PedestalShape::Tanh => 1.0 - x.tanh().powi(2),
+            PedestalShape::Gaussian => (-x * x).exp(),
+        };
+        Ok(self.slope * shape)
+    }
+
+    /// Always returns `0.0`.
+    #[allow(unused_variables)]
+    fn dphi_dtheta(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        Ok(0.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use is_close::is_close;
+
+    use crate::efield::test::check_derivatives;
+    use crate::efield::*;
+
+    #[test]
+    fn test_pedestal() {
+        for shape in [PedestalShape::Tanh, PedestalShape::Gaussian] {
+            let efield = Pedestal::new(shape, 0.11, 0.005, 1e-3).unwrap();
+            let mut cache = efield.cache();
+
+            assert_eq!(efield.phi(0.0, 0.0, &mut cache).unwrap(), 0.0);
+            assert!(is_close!(efield.e(0.11, 0.0, &mut cache).unwrap(), -1e-3));
+            // The well is localized around ψ_p.
+            let e_core = efield.e(0.05, 0.0, &mut cache).unwrap();
+            let e_edge = efield.e(0.125, 0.0, &mut cache).unwrap();
+            assert!(e_core.abs() < 1e-8 && e_edge.abs() < 1e-3 / 2.0);
+
+            for psi in [0.05, 0.105, 0.11, 0.1153] {
+                check_derivatives(&efield, psi);
+            }
+        }
+        assert!(Pedestal::new(PedestalShape::Tanh, 0.0, 0.005, 1e-3).is_err());
+        assert!(Pedestal::new(PedestalShape::Gaussian, 0.11, -0.005, 1e-3).is_err());
+    }
+}
@@ -8,10 +8,42 @@ pub enum EqError {
     #[error("netCDF error: {0}")]
     NcError(#[from] tokamak_netcdf::NcError),
 
+    /// Error writing a netCDF file.
+    #[error("netCDF writing error: {0}")]
+    NcWriteError(#[from] netcdf::Error),
+
     /// Error creating Spline.
     #[error("Error creating Spline: {0}")]
     SplineError(#[from] rsl_interpolation::InterpolationError),
 
+    /// Invalid interpolation type.
+    #[error("Invalid interpolation type: {0}")]
+    InterpTypeError(String),
+
+    /// Root finding failed.
+    #[error("Root finding error: {0}")]
+    RootFindingError(String),
+
+    /// Error reading a file.
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    /// Malformed equilibrium file.
+    #[error("Parse error: {0}")]
+    ParseError(String),
+
+    /// Invalid profile data arrays.
+    #[error("Invalid data: {0}")]
+    InvalidData(String),
+
+    /// Orbit integration failed.
+    #[error("Integration error: {0}")]
+    IntegrationError(String),
+
+    /// Invalid equilibrium parameters.
+    #[error("Invalid equilibrium: {0}")]
+    InvalidEquilibrium(String),
+
     /// Spline evaluation called without Accelerator.
     #[error("Spline evaluation called without Accelerator.")]
     AccError,
@@ -0,0 +1,288 @@
+//! Poloidal and toroidal orbit frequencies.
+//!
+//! The orbit with invariants `(E, μ, P_ζ)` is integrated from its starting point on `θ = θ₀` until
+//! it first returns to it, in the direction of its initial poloidal motion. The return is located
+//! exactly with Hénon's method, see [`poincare`](crate::poincare). Over this period `T`, a passing
+//! orbit completes a poloidal turn and a trapped orbit a full bounce, so that
+//!
+//! ```text
+//! ω_θ = Δθ/T  (passing),  ω_θ = 2π/T  (trapped)
+//! ω_ζ = Δζ/T
+//! q_kinetic = ω_ζ/ω_θ
+//! ```
+
+use std::f64::consts::{PI, TAU};
+
+use crate::bfield::Bfield3d;
+use crate::current::Current;
+use crate::efield::Efield;
+use crate::gc::Constants;
+use crate::orbit::{self, ClassifyConfig, Direction};
+use crate::particle::{IntegrationConfig, Particle, Termination};
+use crate::perturbation::Perturbation;
+use crate::poincare::{self, PoincareConfig, Section};
+use crate::qfactor::Qfactor;
+use crate::{Result, Tokamak};
+
+/// The frequencies of a guiding-centre orbit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frequencies {
+    /// The poloidal period `T`.
+    pub period: f64,
+    /// The poloidal transit or bounce frequency `ω_θ`.
+    pub omega_theta: f64,
+    /// The toroidal frequency `ω_ζ`.
+    pub omega_zeta: f64,
+    /// The kinetic safety factor `ω_ζ/ω_θ`.
+    pub qkinetic: f64,
+}
+
+/// Configuration of the frequency calculation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrequencyConfig {
+    /// The poloidal angle `θ₀` of the starting point. Defaults to the low field side, `θ₀ = 0`.
+    pub theta0: f64,
+    /// The sign of `𝜌∥` at the starting point. Defaults to [`Direction::Co`].
+    pub direction: Direction,
+    /// The plasma boundary, in toroidal flux `ψ`.
+    pub psi_max: f64,
+    /// The number of `𝜓ₚ` samples used to find the starting point.
+    pub psip_samples: usize,
+    /// The configuration of the orbit integration. Its `psi_wall` and `max_transits` are ignored.
+    pub integration: IntegrationConfig,
+}
+
+impl FrequencyConfig {
+    /// Creates a new configuration with plasma boundary `psi_max`.
+    pub fn new(psi_max: f64) -> Self {
+        Self {
+            theta0: 0.0,
+            direction: Direction::Co,
+            psi_max,
+            psip_samples: 200,
+            integration: IntegrationConfig::default(),
+        }
+    }
+}
+
+/// Calculates the frequencies of the orbit with energy `energy` and canonical toroidal momentum
+/// `pzeta`.
+///
+/// The orbit is selected as in [`orbit::classify`]. Returns `None` if there is no such orbit, or
+/// if the integration stopped before the orbit returned to its starting point.
+///
+/// # Example
+///
+/// ```
+/// # use tokamak_equilibria::*;
+/// # use tokamak_equilibria::frequencies::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125)?;
+/// let bfield = bfield::Lar::new()?;
+/// let current = current::Lar::new()?;
+/// let efield = efield::NoEfield::new()?;
+/// let eq = Tokamak::build(qfactor, bfield, current, efield)?;
+/// let mut cache = eq.cache();
+///
+/// let consts = gc::Constants::new(1e-7, 1.0, 1.0);
+/// let state = [0.0, 0.02, 1e-3, 0.0];
+/// let energy = gc::energy(&eq, &state, &consts, &mut cache)?;
+/// let pzeta = gc::pzeta(&eq, 0.0, &state, &mut cache)?;
+///
+/// let freqs = frequencies(&eq, energy, pzeta, &consts, &FrequencyConfig::new(0.03))?;
+/// let Frequencies { omega_theta, omega_zeta, qkinetic, .. } = freqs.unwrap();
+/// # Ok(())
+/// # }
+/// ```
+pub fn frequencies<Q, B, C, E, P>(
+    tokamak: &Tokamak<Q, B, C, E, P>,
+    energy: f64,
+    pzeta: f64,
+    consts: &Constants,
+    config: &FrequencyConfig,
+) -> Result<Option<Frequencies>>
+where
+    Q: Qfactor,
+    B: Bfield3d,
+    C: Current,
+    E: Efield,
+    P: Perturbation,
+{
+    let start = ClassifyConfig {
+        theta0: config.theta0,
+        direction: config.direction,
+        psip_samples: config.psip_samples,
+        ..ClassifyConfig::new(config.psi_max)
+    };
+    let Some(initial) = orbit::initial_state(tokamak, energy, pzeta, consts, &start)? else {
+        return Ok(None);
+    };
+
+    let section = PoincareConfig {
+        section: Section::Theta(config.theta0),
+        intersections: 1,
+        integration: IntegrationConfig {
+            psi_wall: Some(config.psi_max),
+            ..config.integration.clone()
+        },
+    };
+    let mut particle = Particle::new(initial, *consts);
+    poincare::intersections(tokamak, &mut particle, &section)?;
+    if particle.termination != Some(Termination::Intersections) {
+        return Ok(None);
+    }
+
+    let period = particle.t;
+    let dtheta = particle.state[0] - initial[0];
+    let dzeta = particle.state[3] - initial[3];
+    let omega_theta = if dtheta.abs() > PI {
+        dtheta / period
+    } else {
+        TAU / period
+    };
+    let omega_zeta = dzeta / period;
+    Ok(Some(Frequencies {
+        period,
+        omega_theta,
+        omega_zeta,
+        qkinetic: omega_zeta / omega_theta,
+    }))
+}
+
+/// Calculates the frequencies of the orbits with canonical toroidal momentum `pzeta`, for every
+/// energy in `energies`.
+///
+/// See [`frequencies`].
+pub fn scan_energy<Q, B, C, E, P>(
+    tokamak: &Tokamak<Q, B, C, E, P>,
+    energies: &[f64],
+    pzeta: f64,
+    consts: &Constants,
+    config: &FrequencyConfig,
+) -> Result<Vec<Option<Frequencies>>>
+where
+    Q: Qfactor,
+    B: Bfield3d,
+    C: Current,
+    E: Efield,
+    P: Perturbation,
+{
+    energies
+        .iter()
+        .map(|energy| frequencies(tokamak, *energy, pzeta, consts, config))
+        .collect()
+}
+
+/// Calculates the frequencies of the orbits with energy `energy`, for every canonical toroidal
+/// momentum in `pzetas`.
+///
+/// See [`frequencies`].
+pub fn scan_pzeta<Q, B, C, E, P>(
+    tokamak: &Tokamak<Q, B, C, E, P>,
+    energy: f64,
+    pzetas: &[f64],
+    consts: &Constants,
+    config: &FrequencyConfig,
+) -> Result<Vec<Option<Frequencies>>>
+where
+    Q: Qfactor,
+    B: Bfield3d,
+    C: Current,
+    E: Efield,
+    P: Perturbation,
+{
+    pzetas
+        .iter()
+        .map(|pzeta| frequencies(tokamak, energy, *pzeta, consts, config))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use is_close::is_close;
+
+    use crate::frequencies::*;
+    use crate::testing::lar_tokamak;
+    use crate::*;
+
+    /// The frequencies of the orbit through `state`.
+    fn state_frequencies(state: gc::State, mu: f64, direction: Direction) -> Frequencies {
+        let eq = lar_tokamak();
+        let mut cache = eq.cache();
+        let consts = gc::Constants::new(mu, 1.0, 1.0);
+        let energy = gc::energy(&eq, &state, &consts, &mut cache).unwrap();
+        let pzeta = gc::pzeta(&eq, 0.0, &state, &mut cache).unwrap();
+        let mut config = FrequencyConfig::new(0.03);
+        config.direction = direction;
+        frequencies(&eq, energy, pzeta, &consts, &config)
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    /// A thin passing orbit with `μ = 0` follows the field lines with constant `v∥ = 𝜌∥B`, so that
+    /// `q_kinetic ≈ q` and `ω_θ ≈ v∥√(1 − ε²)/q`.
+    fn test_passing() {
+        let eq = lar_tokamak();
+        let psi = eq
+            .qfactor
+            .psi_from_psip(0.02, &mut eq.qfactor.cache())
+            .unwrap();
+        let q = eq.qfactor.q(psi, &mut eq.qfactor.cache()).unwrap();
+        let epsilon = (2.0 * psi).sqrt();
+        let v_par = 1e-3 * (1.0 - epsilon);
+        let omega_theta = v_par * (1.0 - epsilon.powi(2)).sqrt() / q;
+
+        let freqs = state_frequencies([0.0, 0.02, 1e-3, 0.0], 0.0, Direction::Co);
+        assert!(is_close!(freqs.qkinetic, q, rel_tol = 1e-2));
+        assert!(is_close!(freqs.omega_theta, omega_theta, rel_tol = 5e-2));
+        assert!(freqs.omega_zeta > 0.0);
+
+        let freqs = state_frequencies([0.0, 0.02, -1e-3, 0.0], 0.0, Direction::Counter);
+        assert!(is_close!(freqs.qkinetic, q, rel_tol = 1e-2));
+        assert!(is_close!(freqs.omega_theta, -omega_theta, rel_tol = 5e-2));
+        assert!(freqs.omega_zeta < 0.0);
+    }
+
+    #[test]
+    /// A deeply trapped orbit oscillates with the bounce frequency `ω_θ ≈ B_min√(με)/q`.
+    fn test_trapped() {
+        let eq = lar_tokamak();
+        let psi = eq
+            .qfactor
+            .psi_from_psip(0.02, &mut eq.qfactor.cache())
+            .unwrap();
+        let q = eq.qfactor.q(psi, &mut eq.qfactor.cache()).unwrap();
+        let mu = 1e-5;
+        let epsilon = (2.0 * psi).sqrt();
+        let omega_bounce = (1.0 - epsilon) * (mu * epsilon).sqrt() / q;
+
+        let freqs = state_frequencies([0.0, 0.02, 1e-5, 0.0], mu, Direction::Co);
+        assert!(is_close!(freqs.omega_theta, omega_bounce, rel_tol = 5e-2));
+        assert!(freqs.qkinetic.abs() < 1.0);
+    }
+
+    #[test]
+    fn test_scans() {
+        let eq = lar_tokamak();
+        let mut cache = eq.cache();
+        let consts = gc::Constants::new(1e-8, 1.0, 1.0);
+        let pzeta = gc::pzeta(&eq, 0.0, &[0.0, 0.02, 1e-3, 0.0], &mut cache).unwrap();
+        let config = FrequencyConfig::new(0.03);
+
+        let energies = [2e-7, 5e-7, 1e-6];
+        let freqs = scan_energy(&eq, &energies, pzeta, &consts, &config).unwrap();
+        assert_eq!(freqs.len(), 3);
+        let omegas: Vec<f64> = freqs.iter().map(|f| f.unwrap().omega_theta).collect();
+        assert!(omegas.is_sorted());
+
+        let pzetas = [pzeta, pzeta - 1e-3];
+        let freqs = scan_pzeta(&eq, 5e-7, &pzetas, &consts, &config).unwrap();
+        assert!(freqs.iter().all(Option::is_some));
+
+        // Below μB everywhere.
+        let freqs = scan_energy(&eq, &[1e-9], pzeta, &consts, &config).unwrap();
+        assert_eq!(freqs, vec![None]);
+    }
+}
@@ -0,0 +1,371 @@
+//! Guiding-centre equations of motion.
+//!
+//! The equations are derived from White's guiding-centre Lagrangian in Boozer coordinates,
+//!
+//! `L = Z[(ψ + 𝜌꜀I)θ̇ + (𝜌꜀g − 𝜓ₚ)ζ̇] − H`, with `H = Z²𝜌∥²B²/2m + μB + ZΦ`,
+//!
+//! where `B` is the full [`Bfield3d`] field `B(ψ, θ, ζ)`, and `𝜌꜀ = 𝜌∥ + α` includes the magnetic
+//! [`Perturbation`] `α(ψ, θ, ζ, t)`. Setting `K = H/Z`, and denoting derivatives with respect to
+//! `𝜓ₚ` with a prime, the canonical equations of motion are
+//!
+//! ```text
+//! D   = gq + I + 𝜌꜀(gI′ − Ig′)
+//! Θ   = 1 − 𝜌꜀g′ − gα′
+//! Z   = q + 𝜌꜀I′ + Iα′
+//! A   = g𝜕α/𝜕θ − I𝜕α/𝜕ζ
+//! θ̇   = [Θ𝜕K/𝜕𝜌∥ + g𝜕K/𝜕𝜓ₚ] / D
+//! 𝜓̇ₚ  = [−g𝜕K/𝜕θ + I𝜕K/𝜕ζ + A𝜕K/𝜕𝜌∥] / D
+//! 𝜌̇∥  = [−Θ𝜕K/𝜕θ − Z𝜕K/𝜕ζ − A𝜕K/𝜕𝜓ₚ] / D − 𝜕α/𝜕t
+//! ζ̇   = [Z𝜕K/𝜕𝜌∥ − I𝜕K/𝜕𝜓ₚ] / D
+//! ```
+//!
+//! The energy `H` is a constant of the motion if the perturbation is static. The canonical
+//! toroidal momentum [`P_ζ = 𝜌꜀g − 𝜓ₚ`](pzeta) is only conserved if neither the field nor the perturbation
+//! depend on ζ, and otherwise evolves as `−𝜕K/𝜕ζ`.
+//!
+//! All quantities are in **Normalized Units**, so that a proton has `charge = 1` and
+//! `mass = 1`.
+
+use std::f64::consts::TAU;
+
+use crate::bfield::{Bfield, Bfield3d};
+use crate::current::Current;
+use crate::efield::Efield;
+use crate::perturbation::Perturbation;
+use crate::qfactor::Qfactor;
+use crate::{FieldState, Result, Tokamak, TokamakCache};
+
+/// The guiding-centre state `[θ, 𝜓ₚ, 𝜌∥, ζ]`.
+pub type State = [f64; 4];
+
+/// The constants of a guiding-centre particle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Constants {
+    /// The magnetic moment `μ`.
+    pub mu: f64,
+    /// The particle's charge `Z`.
+    pub charge: f64,
+    /// The particle's mass `m`.
+    pub mass: f64,
+}
+
+impl Constants {
+    /// Creates a new set of particle constants.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// // A proton with magnetic moment μ = 1e-5.
+    /// let consts = gc::Constants::new(1e-5, 1.0, 1.0);
+    /// ```
+    pub fn new(mu: f64, charge: f64, mass: f64) -> Self {
+        Self { mu, charge, mass }
+    }
+}
+
+/// Calculates the time derivatives `[θ̇, 𝜓̇ₚ, 𝜌̇∥, ζ̇]` of the guiding-centre `state` at time `t`.
+///
+/// # Example
+///
+/// ```
+/// # use tokamak_equilibria::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125)?;
+/// let bfield = bfield::Lar::new()?;
+/// let current = current::Lar::new()?;
+/// let efield = efield::NoEfield::new()?;
+/// let eq = Tokamak::build(qfactor, bfield, current, efield)?;
+/// let mut cache = eq.cache();
+///
+/// let consts = gc::Constants::new(1e-5, 1.0, 1.0);
+/// let state = [0.0, 0.02, 1e-3, 0.0];
+/// let [theta_dot, psip_dot, rho_dot, zeta_dot] =
+///     gc::rhs(&eq, 0.0, &state, &consts, &mut cache)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn rhs<Q, B, C, E, P>(
+    tokamak: &Tokamak<Q, B, C, E, P>,
+    t: f64,
+    state: &State,
+    consts: &Constants,
+    cache: &mut TokamakCache<Q, B, C, E, P>,
+) -> Result<State>
+where
+    Q: Qfactor,
+    B: Bfield3d,
+    C: Current,
+    E: Efield,
+    P: Perturbation,
+{
+    let [theta, psip, rho, zeta] = *state;
+    let (fs, db_dzeta) = field_state(tokamak, theta, psip, zeta, cache)?;
+    let alpha = alpha_state(&tokamak.perturbation, &fs, zeta, t, &mut cache.perturbation)?;
+    Ok(rhs_from_field_state(&fs, db_dzeta, &alpha, rho, consts))
+}
+
+/// Calculates the energy `H = Z²𝜌∥²B²/2m + μB + ZΦ` of the guiding-centre `state`.
+pub fn energy<Q, B, C, E, P>(
+    tokamak: &Tokamak<Q, B, C, E, P>,
+    state: &State,
+    consts: &Constants,
+    cache: &mut TokamakCache<Q, B, C, E, P>,
+) -> Result<f64>
+where
+    Q: Qfactor,
+    B: Bfield3d,
+    C: Current,
+    E: Efield,
+    P: Perturbation,
+{
+    let [theta, psip, rho, zeta] = *state;
+    let (fs, _) = field_state(tokamak, theta, psip, zeta, cache)?;
+    Ok(energy_from_field_state(&fs, rho, consts))
+}
+
+/// Calculates the canonical toroidal momentum `P_ζ = (𝜌∥ + α)g − 𝜓ₚ` of the guiding-centre
+/// `state` at time `t`, where `α` is the [`Perturbation`].
+pub fn pzeta<Q, B, C, E, P>(
+    tokamak: &Tokamak<Q, B, C, E, P>,
+    t: f64,
+    state: &State,
+    cache: &mut TokamakCache<Q, B, C, E, P>,
+) -> Result<f64>
+where
+    Q: Qfactor,
+    B: Bfield,
+    C: Current,
+    E: Efield,
+    P: Perturbation,
+{
+    let [theta, psip, rho, zeta] = *state;
+    let psi = tokamak.qfactor.psi_from_psip(psip, &mut cache.qfactor)?;
+    let g = tokamak.current.g(psi, &mut cache.current)?;
+    let theta = theta.rem_euclid(TAU);
+    let alpha = tokamak
+        .perturbation
+        .alpha(psi, theta, zeta, t, &mut cache.perturbation)?;
+    Ok((rho + alpha) * g - psip)
+}
+
+/// Evaluates the [`FieldState`] at `(θ, 𝜓ₚ)`, with θ wrapped in `[0, 2π)`, and `𝜕B/𝜕ζ`.
+///
+/// The magnetic field and its derivatives are those of the full field at `ζ`.
+pub(crate) fn field_state<Q, B, C, E, P>(
+    tokamak: &Tokamak<Q, B, C, E, P>,
+    theta: f64,
+    psip: f64,
+    zeta: f64,
+    cache: &mut TokamakCache<Q, B, C, E, P>,
+) -> Result<(FieldState, f64)>
+where
+    Q: Qfactor,
+    B: Bfield3d,
+    C: Current,
+    E: Efield,
+    P: Perturbation,
+{
+    let psi = tokamak.qfactor.psi_from_psip(psip, &mut cache.qfactor)?;
+    tokamak.eval_3d(psi, theta.rem_euclid(TAU), zeta, cache)
+}
+
+/// The perturbation `α` and its derivatives at a point.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct AlphaState {
+    pub alpha: f64,
+    pub dalpha_dpsi: f64,
+    pub dalpha_dtheta: f64,
+    pub dalpha_dzeta: f64,
+    pub dalpha_dt: f64,
+}
+
+/// Evaluates the [`AlphaState`] at the `(ψ, θ)` of a [`FieldState`], and at `(ζ, t)`.
+pub(crate) fn alpha_state<P: Perturbation>(
+    perturbation: &P,
+    fs: &FieldState,
+    zeta: f64,
+    t: f64,
+    cache: &mut P::Cache,
+) -> Result<AlphaState> {
+    let (psi, theta) = (fs.psi, fs.theta);
+    Ok(AlphaState {
+        alpha: perturbation.alpha(psi, theta, zeta, t, cache)?,
+        dalpha_dpsi: perturbation.dalpha_dpsi(psi, theta, zeta, t, cache)?,
+        dalpha_dtheta: perturbation.dalpha_dtheta(psi, theta, zeta, t, cache)?,
+        dalpha_dzeta: perturbation.dalpha_dzeta(psi, theta, zeta, t, cache)?,
+        dalpha_dt: perturbation.dalpha_dt(psi, theta, zeta, t, cache)?,
+    })
+}
+
+/// The energy of a particle with parallel gyroradius `rho`, at a [`FieldState`].
+pub(crate) fn energy_from_field_state(fs: &FieldState, rho: f64, consts: &Constants) -> f64 {
+    let Constants { mu, charge, mass } = *consts;
+    (charge * rho * fs.b).powi(2) / (2.0 * mass) + mu * fs.b + charge * fs.phi
+}
+
+/// The equations of motion of a particle with parallel gyroradius `rho`, at a [`FieldState`] where
+/// the field varies toroidally as `db_dzeta`, and the perturbation is `alpha`.
+pub(crate) fn rhs_from_field_state(
+    fs: &FieldState,
+    db_dzeta: f64,
+    alpha: &AlphaState,
+    rho: f64,
+    consts: &Constants,
+) -> State {
+    let Constants { mu, charge, mass } = *consts;
+    let FieldState { q, b, i, g, .. } = *fs;
+
+    // Derivatives with respect to 𝜓ₚ.
+    let i_der = q * fs.i_der;
+    let g_der = q * fs.g_der;
+    let db_dpsip = q * fs.db_dpsi;
+    let dphi_dpsip = q * fs.dphi_dpsi;
+    let dalpha_dpsip = q * alpha.dalpha_dpsi;
+
+    // Derivatives of K = H/Z.
+    let par = charge * rho.powi(2) * b / mass + mu / charge;
+    let dk_drho = charge * rho * b.powi(2) / mass;
+    let dk_dtheta = par * fs.db_dtheta + fs.dphi_dtheta;
+    let dk_dpsip = par * db_dpsip + dphi_dpsip;
+    let dk_dzeta = par * db_dzeta;
+
+    let rho_c = rho + alpha.alpha;
+    let d = g * q + i + rho_c * (g * i_der - i * g_der);
+    let theta_fac = 1.0 - rho_c * g_der - g * dalpha_dpsip;
+    let zeta_fac = q + rho_c * i_der + i * dalpha_dpsip;
+    let alpha_fac = g * alpha.dalpha_dtheta - i * alpha.dalpha_dzeta;
+
+    [
+        (theta_fac * dk_drho + g * dk_dpsip) / d,
+        (-g * dk_dtheta + i * dk_dzeta + alpha_fac * dk_drho) / d,
+        (-theta_fac * dk_dtheta - zeta_fac * dk_dzeta - alpha_fac * dk_dpsip) / d - alpha.dalpha_dt,
+        (zeta_fac * dk_drho - i * dk_dpsip) / d,
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use is_close::is_close;
+
+    use crate::testing::{lar_tokamak, ripple_tokamak, unity_tokamak};
+    use crate::*;
+
+    #[test]
+    /// With q = 1, I = 0, g = 1 and B = 1 − √(2ψ)cosθ, the equations reduce to
+    /// θ̇ = 𝜌∥B² + (𝜌∥²B + μ)𝜕B/𝜕ψ, 𝜓̇ₚ = 𝜌̇∥ = −(𝜌∥²B + μ)𝜕B/𝜕θ and ζ̇ = 𝜌∥B².
+    fn test_lar_limit() {
+        let eq = unity_tokamak();
+        let mut cache = eq.cache();
+        let consts = gc::Constants::new(1e-4, 1.0, 1.0);
+
+        let (theta, psip, rho): (f64, f64, f64) = (0.7, 0.02, 2e-3);
+        let r = (2.0 * psip).sqrt();
+        let b = 1.0 - r * theta.cos();
+        let db_dpsi = -theta.cos() / r;
+        let db_dtheta = r * theta.sin();
+        let par = rho.powi(2) * b + consts.mu;
+
+        let [theta_dot, psip_dot, rho_dot, zeta_dot] =
+            gc::rhs(&eq, 0.0, &[theta, psip, rho, 0.0], &consts, &mut cache).unwrap();
+
+        assert!(is_close!(theta_dot, rho * b.powi(2) + par * db_dpsi));
+        assert!(is_close!(psip_dot, -par * db_dtheta));
+        assert!(is_close!(rho_dot, -par * db_dtheta));
+        assert!(is_close!(zeta_dot, rho * b.powi(2)));
+    }
+
+    #[test]
+    /// The energy and P_ζ must be constants of the motion: dH/dt = dP_ζ/dt = 0.
+    fn test_constants_of_motion() {
+        let eq = lar_tokamak();
+        let mut cache = eq.cache();
+        let consts = gc::Constants::new(3e-5, 2.0, 4.0);
+
+        let state = [2.1, 0.03, -4e-3, 1.0];
+        let dot = gc::rhs(&eq, 0.0, &state, &consts, &mut cache).unwrap();
+
+        // Central differences along the flow.
+        let h = 1e-6;
+        let shifted =
+            |sign: f64| -> gc::State { std::array::from_fn(|k| state[k] + sign * h * dot[k]) };
+        let (fwd, bwd) = (shifted(1.0), shifted(-1.0));
+
+        let de = gc::energy(&eq, &fwd, &consts, &mut cache).unwrap()
+            - gc::energy(&eq, &bwd, &consts, &mut cache).unwrap();
+        let dpz = gc::pzeta(&eq, 0.0, &fwd, &mut cache).unwrap()
+            - gc::pzeta(&eq, 0.0, &bwd, &mut cache).unwrap();
+        let e = gc::energy(&eq, &state, &consts, &mut cache).unwrap();
+
+        assert!(is_close!(de / (2.0 * h), 0.0, abs_tol = 1e-9 * e));
+        assert!(is_close!(dpz / (2.0 * h), 0.0, abs_tol = 1e-9));
+    }
+
+    #[test]
+    /// In a rippled field, the energy is a constant of the motion, while P_ζ evolves as
+    /// dP_ζ/dt = −𝜕K/𝜕ζ.
+    fn test_ripple() {
+        let eq = ripple_tokamak(0.02);
+        let mut cache = eq.cache();
+        let consts = gc::Constants::new(3e-5, 2.0, 4.0);
+
+        let state = [2.1, 0.03, -4e-3, 0.1];
+        let dot = gc::rhs(&eq, 0.0, &state, &consts, &mut cache).unwrap();
+
+        let h = 1e-6;
+        let shifted =
+            |sign: f64| -> gc::State { std::array::from_fn(|k| state[k] + sign * h * dot[k]) };
+        let (fwd, bwd) = (shifted(1.0), shifted(-1.0));
+        let mut energy = |state: &gc::State| gc::energy(&eq, state, &consts, &mut cache).unwrap();
+        let de = energy(&fwd) - energy(&bwd);
+        let e = energy(&state);
+        let (zeta_fwd, zeta_bwd) = ([2.1, 0.03, -4e-3, 0.1 + h], [2.1, 0.03, -4e-3, 0.1 - h]);
+        let dk_dzeta = (energy(&zeta_fwd) - energy(&zeta_bwd)) / (2.0 * h * consts.charge);
+        let dpz = gc::pzeta(&eq, 0.0, &fwd, &mut cache).unwrap()
+            - gc::pzeta(&eq, 0.0, &bwd, &mut cache).unwrap();
+
+        assert!(is_close!(de / (2.0 * h), 0.0, abs_tol = 1e-9 * e));
+        assert!(dk_dzeta.abs() > 1e-6);
+        assert!(is_close!(dpz / (2.0 * h), -dk_dzeta, rel_tol = 1e-6));
+    }
+
+    #[test]
+    /// With a static, axisymmetric perturbation, the energy and the canonical momentum
+    /// P_ζ = (𝜌∥ + α)g − 𝜓ₚ are constants of the motion.
+    fn test_perturbation() {
+        use crate::perturbation::{Envelope, Harmonic, Harmonics};
+
+        let envelope = Envelope::gaussian(0.03, 0.01).unwrap();
+        let perturbation = Harmonics::new(vec![Harmonic::new(2, 0, 1e-4, envelope)]).unwrap();
+        let eq = lar_tokamak().with_perturbation(perturbation);
+        let mut cache = eq.cache();
+        let consts = gc::Constants::new(3e-5, 2.0, 4.0);
+
+        let state = [2.1, 0.03, -4e-3, 1.0];
+        let dot = gc::rhs(&eq, 0.0, &state, &consts, &mut cache).unwrap();
+
+        let h = 1e-6;
+        let shifted =
+            |sign: f64| -> gc::State { std::array::from_fn(|k| state[k] + sign * h * dot[k]) };
+        let (fwd, bwd) = (shifted(1.0), shifted(-1.0));
+        let dpz = gc::pzeta(&eq, 0.0, &fwd, &mut cache).unwrap()
+            - gc::pzeta(&eq, 0.0, &bwd, &mut cache).unwrap();
+        let de = gc::energy(&eq, &fwd, &consts, &mut cache).unwrap()
+            - gc::energy(&eq, &bwd, &consts, &mut cache).unwrap();
+        let e = gc::energy(&eq, &state, &consts, &mut cache).unwrap();
+        let psi = eq
+            .qfactor
+            .psi_from_psip(state[1], &mut cache.qfactor)
+            .unwrap();
+        let alpha = eq
+            .perturbation
+            .alpha(psi, state[0], state[3], 0.0, &mut cache.perturbation)
+            .unwrap();
+
+        assert!(alpha.abs() > 1e-6);
+        assert!(is_close!(de / (2.0 * h), 0.0, abs_tol = 1e-9 * e));
+        assert!(is_close!(dpz / (2.0 * h), 0.0, abs_tol = 1e-9));
+    }
+}
@@ -0,0 +1,589 @@
+//! G-EQDSK equilibrium files.
+//!
+//! The G-EQDSK format of EFIT, also written by CHEASE and most other equilibrium codes, stores the
+//! poloidal flux per radian `Ψ(R, Z)` on a uniform cylindrical grid, and the flux functions
+//! `F = RB_φ`, `p`, `FF′`, `p′` and `q` on a uniform `Ψ` grid from the magnetic axis to the plasma
+//! boundary. The file is read in the order
+//!
+//! ```text
+//! description (48 characters), idum, nw, nh
+//! rdim, zdim, rcentr, rleft, zmid
+//! rmaxis, zmaxis, simag, sibry, bcentr
+//! current, simag, xdum, rmaxis, xdum
+//! zmaxis, xdum, sibry, xdum, xdum
+//! fpol[nw], pres[nw], ffprim[nw], pprime[nw], psirz[nh][nw], qpsi[nw]
+//! nbbbs, limitr
+//! (rbbbs, zbbbs)[nbbbs], (rlim, zlim)[limitr]
+//! ```
+//!
+//! where the real numbers are written in fixed width fields that may run into each other.
+//!
+//! `Ψ(R, Z)` and `F(Ψ)` are interpolated with splines, and the equilibrium is mapped to Boozer
+//! coordinates and normalized by the major radius and the magnetic field of the magnetic axis,
+//! consistently with the [`Solov'ev`](crate::solovev) equilibrium.
+
+use std::path::Path;
+
+use ndarray::Array2;
+use rsl_interpolation::{Accelerator, DynSpline, DynSpline2d, make_spline, make_spline2d};
+
+use crate::boozer::{BoozerData, BoozerGrid, FluxFunction, boozer_map};
+use crate::efield::NoEfield;
+use crate::numerics::solve_linear;
+use crate::units::Units;
+use crate::{EqError, Interp1d, Interp2d, InterpOptions, Result, Tokamak};
+use crate::{bfield, current, qfactor};
+
+/// An equilibrium read from a G-EQDSK file.
+///
+/// All quantities are in the units of the file, usually SI.
+///
+/// # Example
+///
+/// ```no_run
+/// # use tokamak_equilibria::*;
+/// # use std::path::Path;
+/// #
+/// # fn main() -> Result<()> {
+/// let geqdsk = geqdsk::Geqdsk::from_file(Path::new("./g012345.01000"), 0.99)?;
+/// let tokamak = geqdsk.tokamak(InterpOptions::default())?;
+///
+/// let mut cache = tokamak.cache();
+/// let q = tokamak.qfactor.q(0.5 * geqdsk.psi_wall(), &mut cache.qfactor)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Geqdsk {
+    /// The description at the start of the file.
+    pub description: String,
+    /// The width of the `(R, Z)` grid.
+    pub rdim: f64,
+    /// The height of the `(R, Z)` grid.
+    pub zdim: f64,
+    /// The reference major radius of `bcentr`.
+    pub rcentr: f64,
+    /// The smallest `R` of the grid.
+    pub rleft: f64,
+    /// The `Z` of the middle of the grid.
+    pub zmid: f64,
+    /// The major radius of the magnetic axis.
+    pub rmaxis: f64,
+    /// The height of the magnetic axis.
+    pub zmaxis: f64,
+    /// The poloidal flux per radian on the magnetic axis.
+    pub simag: f64,
+    /// The poloidal flux per radian on the plasma boundary.
+    pub sibry: f64,
+    /// The vacuum toroidal field at `rcentr`.
+    pub bcentr: f64,
+    /// The plasma current.
+    pub current: f64,
+    /// The toroidal field function `F = RB_φ` on the uniform `Ψ` grid.
+    pub fpol: Vec<f64>,
+    /// The pressure on the uniform `Ψ` grid.
+    pub pres: Vec<f64>,
+    /// `FF′(Ψ)` on the uniform `Ψ` grid.
+    pub ffprim: Vec<f64>,
+    /// `p′(Ψ)` on the uniform `Ψ` grid.
+    pub pprime: Vec<f64>,
+    /// The poloidal flux per radian `Ψ[R, Z]`.
+    pub psirz: Array2<f64>,
+    /// The safety factor on the uniform `Ψ` grid.
+    pub qpsi: Vec<f64>,
+    /// The `R` of the plasma boundary.
+    pub rbbbs: Vec<f64>,
+    /// The `Z` of the plasma boundary.
+    pub zbbbs: Vec<f64>,
+    /// The `R` of the limiter.
+    pub rlim: Vec<f64>,
+    /// The `Z` of the limiter.
+    pub zlim: Vec<f64>,
+    /// The Boozer-mapped profiles.
+    data: BoozerData,
+}
+
+impl Geqdsk {
+    /// Reads the G-EQDSK file at `path`, and maps the equilibrium up to the flux surface with
+    /// normalized poloidal flux `boundary`.
+    ///
+    /// `boundary` must lie in `(0, 1]`. Values slightly below 1, such as 0.99, avoid the
+    /// separatrix of diverted equilibria, where `q` diverges.
+    pub fn from_file(path: &Path, boundary: f64) -> Result<Self> {
+        Self::from_text(&std::fs::read_to_string(path)?, boundary)
+    }
+
+    /// Parses the contents `text` of a G-EQDSK file, and maps the equilibrium up to the flux
+    /// surface with normalized poloidal flux `boundary`.
+    ///
+    /// See [`Geqdsk::from_file`].
+    pub fn from_text(text: &str, boundary: f64) -> Result<Self> {
+        Self::with_grid(text, boundary, &BoozerGrid::default())
+    }
+
+    pub(crate) fn with_grid(text: &str, boundary: f64, grid: &BoozerGrid) -> Result<Self> {
+        if !(boundary > 0.0 && boundary <= 1.0) {
+            return Err(EqError::InvalidEquilibrium(format!(
+                "boundary = {boundary} must lie in (0, 1]"
+            )));
+        }
+        let mut geqdsk = parse(text)?;
+        let splines = Splines::new(&geqdsk)?;
+
+        let axis = splines.magnetic_axis((geqdsk.rmaxis, geqdsk.zmaxis))?;
+        let psi_edge = geqdsk.simag + boundary * (geqdsk.sibry - geqdsk.simag);
+        let r_max = [splines.r[0], splines.r[splines.r.len() - 1]]
+            .iter()
+            .flat_map(|r| {
+                [splines.z[0], splines.z[splines.z.len() - 1]]
+                    .map(|z| (r - axis.0).hypot(z - axis.1))
+            })
+            .fold(0.0, f64::max);
+        geqdsk.data = boozer_map(&splines, axis, psi_edge, r_max, grid)?;
+        Ok(geqdsk)
+    }
+
+    /// The toroidal flux `ψ` of the outermost mapped surface.
+    pub fn psi_wall(&self) -> f64 {
+        *self.data.psi.last().expect("non-empty grid")
+    }
+
+    /// Returns the [`qfactor::Numerical`] of the equilibrium, with spline of `typ` interpolation
+    /// type.
+    pub fn qfactor(&self, typ: Interp1d) -> Result<qfactor::Numerical> {
+        qfactor::Numerical::from_arrays(&self.data.psi, &self.data.q, typ)
+    }
+
+    /// Returns the [`current::Numerical`] of the equilibrium, with splines of `typ` interpolation
+    /// type.
+    pub fn current(&self, typ: Interp1d) -> Result<current::Numerical> {
+        current::Numerical::from_arrays(&self.data.psi, &self.data.i, &self.data.g, typ)
+    }
+
+    /// Returns the [`bfield::Numerical`] of the equilibrium, with spline of `typ` interpolation
+    /// type.
+    pub fn bfield(&self, typ: Interp2d) -> Result<bfield::Numerical> {
+        let b_data = self.data.b.clone();
+        bfield::Numerical::from_arrays(&self.data.psi, &self.data.theta, b_data, typ)
+    }
+
+    /// The [`Units`] of the equilibrium, defined by the magnetic axis located by the mapping,
+    /// rather than the one of the file header.
+    pub fn units(&self) -> Result<Units> {
+        Units::new(self.data.b_axis, self.data.r_axis)
+    }
+
+    /// Returns the [`Tokamak`] of the equilibrium, with its [`Units`].
+    pub fn tokamak(
+        &self,
+        opts: InterpOptions,
+    ) -> Result<Tokamak<qfactor::Numerical, bfield::Numerical, current::Numerical, NoEfield>> {
+        let tokamak = Tokamak::build(
+            self.qfactor(opts.typ1d)?,
+            self.bfield(opts.typ2d)?,
+            self.current(opts.typ1d)?,
+            NoEfield::new()?,
+        )?;
+        Ok(tokamak.with_units(self.units()?))
+    }
+}
+
+/// Parses the contents of a G-EQDSK file, without mapping it.
+fn parse(text: &str) -> Result<Geqdsk> {
+    let (header, body) = text.split_once('\n').unwrap_or((text, ""));
+    let description = header.get(..48).unwrap_or_default().trim().to_string();
+    let dims: Vec<usize> = header
+        .get(48..)
+        .unwrap_or(header)
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<std::result::Result<_, _>>()
+        .map_err(|_| EqError::ParseError(format!("invalid header `{header}`")))?;
+    let [.., nw, nh] = dims[..] else {
+        return Err(EqError::ParseError(format!(
+            "missing grid dimensions in header `{header}`"
+        )));
+    };
+    if nw < 4 || nh < 4 {
+        return Err(EqError::ParseError(format!("grid of {nw}×{nh} points")));
+    }
+
+    let mut numbers = Numbers::new(body);
+    let [rdim, zdim, rcentr, rleft, zmid] = numbers.array()?;
+    let [rmaxis, zmaxis, simag, sibry, bcentr] = numbers.array()?;
+    let [current, ..] = numbers.array::<5>()?;
+    numbers.array::<5>()?;
+    let fpol = numbers.vec(nw)?;
+    let pres = numbers.vec(nw)?;
+    let ffprim = numbers.vec(nw)?;
+    let pprime = numbers.vec(nw)?;
+    // Stored with R varying fastest.
+    let psirz = Array2::from_shape_vec((nh, nw), numbers.vec(nw * nh)?)
+        .expect("matching shape")
+        .reversed_axes()
+        .as_standard_layout()
+        .into_owned();
+    let qpsi = numbers.vec(nw)?;
+
+    // The boundary and limiter are missing from some files.
+    let nbbbs = numbers.next().map_or(Ok(0.0), |n| n)? as usize;
+    let limitr = numbers.next().map_or(Ok(0.0), |n| n)? as usize;
+    let boundary = numbers.vec(2 * nbbbs)?;
+    let limiter = numbers.vec(2 * limitr)?;
+
+    Ok(Geqdsk {
+        description,
+        rdim,
+        zdim,
+        rcentr,
+        rleft,
+        zmid,
+        rmaxis,
+        zmaxis,
+        simag,
+        sibry,
+        bcentr,
+        current,
+        fpol,
+        pres,
+        ffprim,
+        pprime,
+        psirz,
+        qpsi,
+        rbbbs: boundary.iter().step_by(2).copied().collect(),
+        zbbbs: boundary.iter().skip(1).step_by(2).copied().collect(),
+        rlim: limiter.iter().step_by(2).copied().collect(),
+        zlim: limiter.iter().skip(1).step_by(2).copied().collect(),
+        data: BoozerData::default(),
+    })
+}
+
+/// The numbers of a G-EQDSK file, in order.
+struct Numbers<'a> {
+    tokens: Box<dyn Iterator<Item = &'a str> + 'a>,
+}
+
+impl<'a> Numbers<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            tokens: Box::new(text.split_whitespace().flat_map(split_fields)),
+        }
+    }
+
+    fn next(&mut self) -> Option<Result<f64>> {
+        self.tokens.next().map(|token| {
+            token
+                .parse()
+                .map_err(|_| EqError::ParseError(format!("invalid number `{token}`")))
+        })
+    }
+
+    fn vec(&mut self, len: usize) -> Result<Vec<f64>> {
+        (0..len)
+            .map(|_| {
+                self.next()
+                    .unwrap_or_else(|| Err(EqError::ParseError("unexpected end of file".into())))
+            })
+            .collect()
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[f64; N]> {
+        Ok(self.vec(N)?.try_into().expect("N numbers"))
+    }
+}
+
+/// Splits the numbers of fixed width fields that run into each other, such as
+/// `1.0E+00-2.0E+00`, at the signs that do not follow an exponent.
+fn split_fields(token: &str) -> impl Iterator<Item = &str> {
+    let bytes = token.as_bytes();
+    let mut starts: Vec<usize> = (1..bytes.len())
+        .filter(|k| {
+            matches!(bytes[*k], b'+' | b'-') && !matches!(bytes[k - 1], b'e' | b'E' | b'd' | b'D')
+        })
+        .collect();
+    starts.insert(0, 0);
+    starts.push(bytes.len());
+    (0..starts.len() - 1).map(move |k| &token[starts[k]..starts[k + 1]])
+}
+
+/// The splines of `Ψ(R, Z)` and `F(Ψ)`.
+struct Splines {
+    r: Vec<f64>,
+    z: Vec<f64>,
+    psi: DynSpline2d<f64>,
+    /// `F` over the normalized flux `(Ψ − Ψ_axis)/(Ψ_boundary − Ψ_axis)`.
+    f: DynSpline<f64>,
+    simag: f64,
+    sibry: f64,
+}
+
+impl Splines {
+    fn new(geqdsk: &Geqdsk) -> Result<Self> {
+        let (nw, nh) = geqdsk.psirz.dim();
+        let r: Vec<f64> = (0..nw)
+            .map(|k| geqdsk.rleft + geqdsk.rdim * k as f64 / (nw - 1) as f64)
+            .collect();
+        let z: Vec<f64> = (0..nh)
+            .map(|k| geqdsk.zmid + geqdsk.zdim * (k as f64 / (nh - 1) as f64 - 0.5))
+            .collect();
+        let psi_data = geqdsk.psirz.flatten().to_vec();
+        let psi = make_spline2d(Interp2d::Bicubic.name(), &r, &z, &psi_data)?;
+
+        let s: Vec<f64> = (0..nw).map(|k| k as f64 / (nw - 1) as f64).collect();
+        let f = make_spline(Interp1d::Cubic.name(), &s, &geqdsk.fpol)?;
+
+        Ok(Self {
+            r,
+            z,
+            psi,
+            f,
+            simag: geqdsk.simag,
+            sibry: geqdsk.sibry,
+        })
+    }
+
+    /// Refines the position of the magnetic axis from `guess`, with Newton's method on `∇Ψ = 0`.
+    fn magnetic_axis(&self, guess: (f64, f64)) -> Result<(f64, f64)> {
+        const MAX_ITER: usize = 50;
+        const TOL: f64 = 1e-12;
+
+        let (mut r, mut z) = guess;
+        for _ in 0..MAX_ITER {
+            let (acc_r, acc_z) = (&mut Accelerator::new(), &mut Accelerator::new());
+            let (psi_r, psi_z) = self.grad_psi(r, z)?;
+            let psi_rr = self.psi.eval_deriv_xx(r, z, acc_r, acc_z)?;
+            let psi_rz = self.psi.eval_deriv_xy(r, z, acc_r, acc_z)?;
+            let psi_zz = self.psi.eval_deriv_yy(r, z, acc_r, acc_z)?;
+            let [dr, dz] = solve_linear([[psi_rr, psi_rz], [psi_rz, psi_zz]], [psi_r, psi_z])
+                .ok_or_else(|| EqError::RootFindingError("singular flux Hessian".into()))?;
+            r -= dr;
+            z -= dz;
+            if dr.hypot(dz) <= TOL * r {
+                return Ok((r, z));
+            }
+        }
+        Err(EqError::RootFindingError(format!(
+            "magnetic axis not found near {guess:?}"
+        )))
+    }
+}
+
+impl FluxFunction for Splines {
+    fn psi(&self, r: f64, z: f64) -> Result<f64> {
+        let (acc_r, acc_z) = (&mut Accelerator::new(), &mut Accelerator::new());
+        Ok(self.psi.eval(r, z, acc_r, acc_z)?)
+    }
+
+    fn grad_psi(&self, r: f64, z: f64) -> Result<(f64, f64)> {
+        let (acc_r, acc_z) = (&mut Accelerator::new(), &mut Accelerator::new());
+        Ok((
+            self.psi.eval_deriv_x(r, z, acc_r, acc_z)?,
+            self.psi.eval_deriv_y(r, z, acc_r, acc_z)?,
+        ))
+    }
+
+    fn f(&self, psi: f64) -> Result<f64> {
+        let s = ((psi - self.simag) / (self.sibry - self.simag)).clamp(0.0, 1.0);
+        Ok(self.f.eval(s, &mut Accelerator::new())?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fmt::Write;
+
+    use is_close::is_close;
+
+    use crate::boozer::FluxFunction;
+    use crate::geqdsk::*;
+    use crate::*;
+
+    /// Writes a G-EQDSK file of `eq`, on a `n×n` grid of half-sizes `width` and `height` around
+    /// `(1, 0)`, with lengths scaled by `units.r0`, `Ψ` by `B₀R₀²` and `F` by `B₀R₀`.
+    ///
+    /// The header holds the magnetic axis rounded to a hundredth, as the coarse axis of a grid
+    /// based solver, so that it has to be refined by the mapping.
+    fn write_geqdsk<F: FluxFunction>(
+        eq: &F,
+        axis: (f64, f64),
+        n: usize,
+        size: (f64, f64),
+        units: &Units,
+    ) -> String {
+        let (width, height) = size;
+        let r0 = units.r0;
+        let flux = units.b0 * r0.powi(2);
+        let simag = eq.psi(axis.0, axis.1).unwrap();
+        let sibry = 0.0;
+        let rmaxis = (100.0 * axis.0).round() / 100.0 * r0;
+        let zmaxis = (100.0 * axis.1).round() / 100.0 * r0;
+        let mut numbers = vec![
+            2.0 * width * r0,
+            2.0 * height * r0,
+            r0,
+            (1.0 - width) * r0,
+            0.0,
+            rmaxis,
+            zmaxis,
+            simag * flux,
+            sibry * flux,
+            units.b0,
+            1.0,
+            simag * flux,
+            0.0,
+            rmaxis,
+            0.0,
+            zmaxis,
+            0.0,
+            sibry * flux,
+            0.0,
+            0.0,
+        ];
+        let psi_grid: Vec<f64> = (0..n)
+            .map(|k| simag + (sibry - simag) * k as f64 / (n - 1) as f64)
+            .collect();
+        numbers.extend(
+            psi_grid
+                .iter()
+                .map(|psi| eq.f(*psi).unwrap() * units.b0 * r0),
+        );
+        numbers.extend([0.0].repeat(3 * n));
+        for j in 0..n {
+            let z = height * (2.0 * j as f64 / (n - 1) as f64 - 1.0);
+            for i in 0..n {
+                let r = 1.0 - width + 2.0 * width * i as f64 / (n - 1) as f64;
+                numbers.push(eq.psi(r, z).unwrap() * flux);
+            }
+        }
+        numbers.extend([1.0].repeat(n));
+
+        let mut text = format!("{:<48}{:>4}{:>4}{:>4}\n", "Solov'ev", 3, n, n);
+        for line in numbers.chunks(5) {
+            for x in line {
+                write!(text, "{x:>16.9E}").unwrap();
+            }
+            text.push('\n');
+        }
+        text.push_str("   0   0\n");
+        text
+    }
+
+    #[test]
+    fn test_split_fields() {
+        let fields: Vec<&str> = split_fields("1.5E+00-2.0E-01+3.0e-02").collect();
+        assert_eq!(fields, ["1.5E+00", "-2.0E-01", "+3.0e-02"]);
+        let fields: Vec<&str> = split_fields("-1.0E+00").collect();
+        assert_eq!(fields, ["-1.0E+00"]);
+    }
+
+    #[test]
+    fn test_parse() {
+        let solovev = solovev::Solovev::new(0.32, 1.7, 0.33, -0.155, 1.1).unwrap();
+        let text = write_geqdsk(
+            &solovev.flux_function(),
+            (solovev.r_axis, 0.0),
+            9,
+            (0.5, 0.7),
+            &Units::new(1.0, 1.0).unwrap(),
+        );
+        let geqdsk = parse(&text).unwrap();
+
+        assert_eq!(geqdsk.description, "Solov'ev");
+        assert_eq!(geqdsk.psirz.dim(), (9, 9));
+        assert_eq!(geqdsk.fpol.len(), 9);
+        assert_eq!(geqdsk.qpsi, vec![1.0; 9]);
+        assert!(geqdsk.rbbbs.is_empty());
+        assert!(is_close!(geqdsk.rleft, 0.5));
+        assert!(is_close!(geqdsk.sibry, 0.0));
+        // Ψ[R, Z] at R = 1, Z = 0.7.
+        let psi = solovev.flux_function().psi(1.0, 0.7).unwrap();
+        assert!(is_close!(geqdsk.psirz[[4, 8]], psi, rel_tol = 1e-8));
+
+        assert!(matches!(
+            parse(&text[..text.len() / 2]),
+            Err(EqError::ParseError(_))
+        ));
+        assert!(matches!(parse("header"), Err(EqError::ParseError(_))));
+    }
+
+    /// Maps the G-EQDSK file of a Solov'ev equilibrium written in `units`, and checks that it
+    /// reproduces the normalized profiles.
+    fn check_solovev(units: &Units) {
+        let solovev = solovev::Solovev::new(0.32, 1.7, 0.33, -0.155, 1.1).unwrap();
+        let text = write_geqdsk(
+            &solovev.flux_function(),
+            (solovev.r_axis, 0.0),
+            65,
+            (0.4, 0.6),
+            units,
+        );
+        let grid = BoozerGrid {
+            surfaces: 16,
+            theta: 64,
+            rays: 48,
+        };
+        let geqdsk = Geqdsk::with_grid(&text, 1.0, &grid).unwrap();
+        assert!(is_close!(
+            geqdsk.psi_wall(),
+            solovev.psi_wall(),
+            rel_tol = 1e-3
+        ));
+
+        let q = geqdsk.qfactor(Interp1d::Cubic).unwrap();
+        let q_exact = solovev.qfactor(Interp1d::Cubic).unwrap();
+        let i = geqdsk.current(Interp1d::Cubic).unwrap();
+        let i_exact = solovev.current(Interp1d::Cubic).unwrap();
+        for fraction in [0.25, 0.5, 0.9] {
+            let psi = fraction * solovev.psi_wall();
+            assert!(is_close!(
+                q.q(psi, &mut q.cache()).unwrap(),
+                q_exact.q(psi, &mut q_exact.cache()).unwrap(),
+                rel_tol = 1e-3
+            ));
+            assert!(is_close!(
+                i.i(psi, &mut i.cache()).unwrap(),
+                i_exact.i(psi, &mut i_exact.cache()).unwrap(),
+                rel_tol = 1e-2
+            ));
+            assert!(is_close!(
+                i.g(psi, &mut i.cache()).unwrap(),
+                i_exact.g(psi, &mut i_exact.cache()).unwrap(),
+                rel_tol = 1e-5
+            ));
+        }
+        // The units of the refined axis, rather than the rounded one of the header.
+        assert!(!is_close!(
+            geqdsk.rmaxis,
+            units.r0 * solovev.r_axis,
+            rel_tol = 1e-4
+        ));
+        let flux_function = solovev.flux_function();
+        let f_axis = flux_function
+            .f(flux_function.psi(solovev.r_axis, 0.0).unwrap())
+            .unwrap();
+        let geqdsk_units = geqdsk.units().unwrap();
+        assert!(is_close!(
+            geqdsk_units.r0,
+            units.r0 * solovev.r_axis,
+            rel_tol = 1e-6
+        ));
+        assert!(is_close!(
+            geqdsk_units.b0,
+            units.b0 * f_axis.abs() / solovev.r_axis,
+            rel_tol = 1e-6
+        ));
+        assert!(Geqdsk::with_grid(&text, 1.5, &grid).is_err());
+    }
+
+    #[test]
+    /// The mapped G-EQDSK file of a Solov'ev equilibrium reproduces its profiles.
+    fn test_solovev() {
+        check_solovev(&Units::new(1.0, 1.0).unwrap());
+    }
+
+    #[test]
+    /// In SI units, the normalization by the magnetic axis recovers the same profiles, with the
+    /// currents in units of `B₀R₀` and the fluxes in units of `B₀R₀²`.
+    fn test_solovev_si() {
+        check_solovev(&Units::new(2.5, 1.65).unwrap());
+    }
+}
@@ -0,0 +1,189 @@
+//! Interpolation types of the numerical profiles.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::EqError;
+
+/// Interpolation type of 1D splines over ψ.
+///
+/// # Example
+///
+/// ```
+/// # use tokamak_equilibria::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let typ: Interp1d = "cubic".parse()?;
+/// assert_eq!(typ, Interp1d::Cubic);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Interp1d {
+    /// Linear interpolation.
+    Linear,
+    /// Cubic spline with natural boundary conditions.
+    Cubic,
+    /// Cubic spline with periodic boundary conditions.
+    CubicPeriodic,
+    /// Non-rounded Akima spline with natural boundary conditions.
+    Akima,
+    /// Non-rounded Akima spline with periodic boundary conditions.
+    AkimaPeriodic,
+    /// Steffen's method, which guarantees the monotonicity of the interpolating function.
+    Steffen,
+}
+
+/// Interpolation type of 2D splines over (ψ, θ).
+///
+/// # Example
+///
+/// ```
+/// # use tokamak_equilibria::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let typ: Interp2d = "Bicubic".parse()?;
+/// assert_eq!(typ, Interp2d::Bicubic);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Interp2d {
+    /// Bilinear interpolation.
+    Bilinear,
+    /// Bicubic interpolation.
+    Bicubic,
+}
+
+impl Interp1d {
+    const EXPECTED: &str = "Linear, Cubic, CubicPeriodic, Akima, AkimaPeriodic, Steffen";
+
+    /// The name of the interpolation type, as expected by [`rsl_interpolation::make_spline`].
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Linear => "Linear",
+            Self::Cubic => "Cubic",
+            Self::CubicPeriodic => "CubicPeriodic",
+            Self::Akima => "Akima",
+            Self::AkimaPeriodic => "AkimaPeriodic",
+            Self::Steffen => "Steffen",
+        }
+    }
+}
+
+impl Interp2d {
+    const EXPECTED: &str = "Bilinear, Bicubic";
+
+    /// The name of the interpolation type, as expected by [`rsl_interpolation::make_spline2d`].
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Bilinear => "Bilinear",
+            Self::Bicubic => "Bicubic",
+        }
+    }
+}
+
+/// Lowercases `s` and removes any separators, so that e.g. "Cubic Periodic", "cubic-periodic"
+/// and "cubic_periodic" are all parsed the same.
+fn normalize(s: &str) -> String {
+    s.chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '_'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+impl FromStr for Interp1d {
+    type Err = EqError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match normalize(s).as_str() {
+            "linear" => Ok(Self::Linear),
+            "cubic" => Ok(Self::Cubic),
+            "cubicperiodic" => Ok(Self::CubicPeriodic),
+            "akima" => Ok(Self::Akima),
+            "akimaperiodic" => Ok(Self::AkimaPeriodic),
+            "steffen" => Ok(Self::Steffen),
+            "bilinear" | "bicubic" => Err(EqError::InterpTypeError(format!(
+                "'{s}' is a 2D interpolation type, expected one of {}",
+                Self::EXPECTED
+            ))),
+            _ => Err(EqError::InterpTypeError(format!(
+                "unknown 1D interpolation type '{s}', expected one of {}",
+                Self::EXPECTED
+            ))),
+        }
+    }
+}
+
+impl FromStr for Interp2d {
+    type Err = EqError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match normalize(s).as_str() {
+            "bilinear" => Ok(Self::Bilinear),
+            "bicubic" => Ok(Self::Bicubic),
+            "linear" | "cubic" | "cubicperiodic" | "akima" | "akimaperiodic" | "steffen" => {
+                Err(EqError::InterpTypeError(format!(
+                    "'{s}' is a 1D interpolation type, expected one of {}",
+                    Self::EXPECTED
+                )))
+            }
+            _ => Err(EqError::InterpTypeError(format!(
+                "unknown 2D interpolation type '{s}', expected one of {}",
+                Self::EXPECTED
+            ))),
+        }
+    }
+}
+
+impl Display for Interp1d {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Display for Interp2d {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    #[test]
+    fn test_interp1d_parsing() {
+        assert_eq!("cubic".parse::<Interp1d>().unwrap(), Interp1d::Cubic);
+        assert_eq!("Akima".parse::<Interp1d>().unwrap(), Interp1d::Akima);
+        assert_eq!(
+            "cubic periodic".parse::<Interp1d>().unwrap(),
+            Interp1d::CubicPeriodic
+        );
+        assert_eq!(
+            "Akima_Periodic".parse::<Interp1d>().unwrap(),
+            Interp1d::AkimaPeriodic
+        );
+        assert!("Bicubic".parse::<Interp1d>().is_err());
+        assert!("cubci".parse::<Interp1d>().is_err());
+    }
+
+    #[test]
+    fn test_interp2d_parsing() {
+        assert_eq!("bicubic".parse::<Interp2d>().unwrap(), Interp2d::Bicubic);
+        assert_eq!("Bilinear".parse::<Interp2d>().unwrap(), Interp2d::Bilinear);
+        assert!("Cubic".parse::<Interp2d>().is_err());
+        assert!("bicubci".parse::<Interp2d>().is_err());
+    }
+
+    #[test]
+    fn test_display_roundtrip() {
+        use Interp1d::*;
+        for typ in [Linear, Cubic, CubicPeriodic, Akima, AkimaPeriodic, Steffen] {
+            assert_eq!(typ.to_string().parse::<Interp1d>().unwrap(), typ);
+        }
+        for typ in [Interp2d::Bilinear, Interp2d::Bicubic] {
+            assert_eq!(typ.to_string().parse::<Interp2d>().unwrap(), typ);
+        }
+    }
+}
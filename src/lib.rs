@@ -3,7 +3,6 @@
 //!
 //! ```
 //! # use tokamak_equilibria::*;
-//! # use rsl_interpolation::*;
 //! # use std::f64::consts::PI;
 //! #
 //! # fn main() -> Result<()> {
@@ -15,70 +14,87 @@
 //! let tokamak = Tokamak::build(qfactor, bfield, current, efield)?;
 //!
 //! // Evaluation of electromagnetic field and q-factor inside the tokamak.
-//! let mut psi_acc = Accelerator::new();
-//! let mut theta_acc = Accelerator::new();
+//! let mut cache = tokamak.cache();
 //!
-//! let q = tokamak.qfactor.q(0.01, &mut psi_acc)?;
-//! let b = tokamak.bfield.b(0.01, PI, &mut psi_acc, &mut theta_acc)?;
-//! let i = tokamak.current.i(0.01, &mut psi_acc)?;
-//! let phi = tokamak.efield.phi(0.01, PI, &mut psi_acc, &mut theta_acc)?;
+//! let q = tokamak.qfactor.q(0.01, &mut cache.qfactor)?;
+//! let b = tokamak.bfield.b(0.01, PI, &mut cache.bfield)?;
+//! let i = tokamak.current.i(0.01, &mut cache.current)?;
+//! let phi = tokamak.efield.phi(0.01, PI, &mut cache.efield)?;
 //! # Ok(())
 //! # }
 //! ```
 //!
 //! # Note
 //!
-//! Even in analytical equilibria objects, the accelerators must be passed as parameters as well,
-//! even though they are not used. Until I find a better solution...
+//! Every profile declares its own evaluation [`cache`] type. Analytical profiles use the
+//! zero-sized [`cache::NoCache`], so they carry no evaluation state, while numerical profiles hold
+//! their spline accelerators in it.
 //!
 //! # Example - Numerical Equilibrium
 //!
 //! ```no_run
 //! # use tokamak_equilibria::*;
-//! # use rsl_interpolation::*;
 //! # use std::f64::consts::PI;
 //! # use std::path::PathBuf;
 //! #
 //! # fn main() -> Result<()> {
 //! let path = PathBuf::from("./data.nc");
-//! let qfactor = qfactor::Numerical::from_dataset(&path, "cubic")?;
-//! let current = current::Numerical::from_dataset(&path, "cubic")?;
-//! let bfield = bfield::Numerical::from_dataset(&path, "bicubic")?;
-//! let efield = efield::NoEfield::new()?;
-//!
-//! let tokamak = Tokamak::build(qfactor, bfield, current, efield)?;
+//! let tokamak = Tokamak::from_dataset(&path, InterpOptions::new(Interp1d::Cubic, Interp2d::Bicubic))?;
 //!
 //! // Evaluation of electromagnetic field and q-factor inside the tokamak.
-//! let mut psi_acc = Accelerator::new();
-//! let mut theta_acc = Accelerator::new();
+//! let mut cache = tokamak.cache();
 //!
-//! let q = tokamak.qfactor.q(0.01, &mut psi_acc)?;
-//! let b = tokamak.bfield.b(0.01, PI, &mut psi_acc, &mut theta_acc)?;
-//! let i = tokamak.current.i(0.01, &mut psi_acc)?;
-//! let phi = tokamak.efield.phi(0.01, PI, &mut psi_acc, &mut theta_acc)?;
+//! let q = tokamak.qfactor.q(0.01, &mut cache.qfactor)?;
+//! let b = tokamak.bfield.b(0.01, PI, &mut cache.bfield)?;
+//! let i = tokamak.current.i(0.01, &mut cache.current)?;
+//! let phi = tokamak.efield.phi(0.01, PI, &mut cache.efield)?;
 //! # Ok(())
 //! # }
 //! ```
+mod boozer;
+mod dataset;
 mod error;
+mod interp;
+mod numerics;
+mod solver;
+#[cfg(test)]
+mod testing;
 mod tokamak;
 
 pub mod bfield;
+pub mod cache;
 pub mod current;
 pub mod efield;
+pub mod frequencies;
+pub mod gc;
+pub mod geqdsk;
+pub mod orbit;
+pub mod particle;
+pub mod perturbation;
+pub mod poincare;
 pub mod qfactor;
+pub mod solovev;
+pub mod surface;
+pub mod units;
 
 pub use error::EqError;
+#[doc(inline)]
+pub use interp::{Interp1d, Interp2d};
 
 #[doc(inline)]
-pub use tokamak::Tokamak;
+pub use tokamak::{FieldState, InterpOptions, SampleGrid, Tokamak, TokamakCache};
+#[doc(inline)]
+pub use tokamak_netcdf::Equilibrium;
 
 #[doc(inline)]
-pub use bfield::Bfield;
+pub use bfield::{Bfield, Bfield3d};
 #[doc(inline)]
 pub use current::Current;
 #[doc(inline)]
 pub use efield::Efield;
 #[doc(inline)]
+pub use perturbation::Perturbation;
+#[doc(inline)]
 pub use qfactor::Qfactor;
 
 pub type Result<T> = std::result::Result<T, EqError>;
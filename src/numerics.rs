@@ -0,0 +1,202 @@
+//! Small numerical routines shared by the equilibrium constructors and profiles.
+
+use crate::{EqError, Result};
+
+/// Solves the linear system `a⋅x = b` with Gaussian elimination and partial pivoting.
+///
+/// Returns `None` if the matrix is singular.
+pub(crate) fn solve_linear<const N: usize>(
+    mut a: [[f64; N]; N],
+    mut b: [f64; N],
+) -> Option<[f64; N]> {
+    for col in 0..N {
+        let pivot = (col..N).max_by(|i, j| a[*i][col].abs().total_cmp(&a[*j][col].abs()))?;
+        if a[pivot][col] == 0.0 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        for row in col + 1..N {
+            let factor = a[row][col] / a[col][col];
+            let pivot_row = a[col];
+            for (k, value) in a[row].iter_mut().enumerate().skip(col) {
+                *value -= factor * pivot_row[k];
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+
+    let mut x = [0.0; N];
+    for row in (0..N).rev() {
+        let sum: f64 = (row + 1..N).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+    x.iter().all(|xi| xi.is_finite()).then_some(x)
+}
+
+/// Finds a root of `f` in `[a, b]` with bisection, to a relative tolerance of `1e-14`.
+///
+/// `f(a)` and `f(b)` must have opposite signs.
+pub(crate) fn bisect<F>(mut f: F, mut a: f64, mut b: f64) -> Result<f64>
+where
+    F: FnMut(f64) -> Result<f64>,
+{
+    const MAX_ITER: usize = 200;
+    const TOL: f64 = 1e-14;
+
+    let fa = f(a)?;
+    if fa == 0.0 {
+        return Ok(a);
+    }
+    if fa.signum() == f(b)?.signum() {
+        return Err(EqError::RootFindingError(format!(
+            "no sign change in [{a}, {b}]"
+        )));
+    }
+    for _ in 0..MAX_ITER {
+        let mid = 0.5 * (a + b);
+        let fmid = f(mid)?;
+        if fmid.signum() == fa.signum() {
+            a = mid;
+        } else {
+            b = mid;
+        }
+        if (b - a).abs() <= TOL * a.abs().max(b.abs()).max(TOL) {
+            break;
+        }
+    }
+    Ok(0.5 * (a + b))
+}
+
+/// The nodes and weights of the `N`-point Gauss–Legendre rule on `[-1, 1]`.
+pub(crate) fn gauss_legendre<const N: usize>() -> ([f64; N], [f64; N]) {
+    use std::f64::consts::PI;
+
+    let mut nodes = [0.0; N];
+    let mut weights = [0.0; N];
+    for i in 0..N.div_ceil(2) {
+        // Newton iteration on P_N, starting from the Chebyshev-like estimate.
+        let mut x = (PI * (i as f64 + 0.75) / (N as f64 + 0.5)).cos();
+        let mut dp = 0.0;
+        for _ in 0..100 {
+            let (mut p0, mut p1) = (1.0, x);
+            for k in 2..=N {
+                let k = k as f64;
+                (p0, p1) = (p1, ((2.0 * k - 1.0) * x * p1 - (k - 1.0) * p0) / k);
+            }
+            dp = N as f64 * (x * p1 - p0) / (x * x - 1.0);
+            let dx = p1 / dp;
+            x -= dx;
+            if dx.abs() <= 1e-16 {
+                break;
+            }
+        }
+        let w = 2.0 / ((1.0 - x * x) * dp * dp);
+        nodes[i] = -x;
+        nodes[N - 1 - i] = x;
+        weights[i] = w;
+        weights[N - 1 - i] = w;
+    }
+    (nodes, weights)
+}
+
+/// Integrates `f` over `[a, b]` with the Gauss–Legendre rule of `nodes` and `weights`.
+pub(crate) fn integrate<F, const N: usize>(
+    mut f: F,
+    a: f64,
+    b: f64,
+    (nodes, weights): &([f64; N], [f64; N]),
+) -> f64
+where
+    F: FnMut(f64) -> f64,
+{
+    let half = 0.5 * (b - a);
+    let mid = 0.5 * (a + b);
+    half * nodes
+        .iter()
+        .zip(weights)
+        .map(|(x, w)| w * f(mid + half * x))
+        .sum::<f64>()
+}
+
+/// The error function `erf(x)`, to a relative accuracy of about `1e-14`.
+///
+/// Uses its Maclaurin series for `|x| < 3`, and the continued fraction of `erfc(x)` otherwise.
+pub(crate) fn erf(x: f64) -> f64 {
+    const TWO_OVER_SQRT_PI: f64 = std::f64::consts::FRAC_2_SQRT_PI;
+
+    if x.abs() < 3.0 {
+        let x2 = x * x;
+        let mut power = x;
+        let mut sum = x;
+        for n in 1..200 {
+            power *= -x2 / n as f64;
+            let term = power / (2 * n + 1) as f64;
+            sum += term;
+            if term.abs() <= 1e-17 * sum.abs() {
+                break;
+            }
+        }
+        TWO_OVER_SQRT_PI * sum
+    } else {
+        let ax = x.abs();
+        let mut fraction = ax;
+        for k in (1..=60).rev() {
+            fraction = ax + 0.5 * k as f64 / fraction;
+        }
+        let erfc = 0.5 * TWO_OVER_SQRT_PI * (-ax * ax).exp() / fraction;
+        (1.0 - erfc).copysign(x)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use is_close::is_close;
+
+    use crate::numerics::*;
+
+    #[test]
+    fn test_solve_linear() {
+        let a = [[0.0, 2.0, 1.0], [1.0, 1.0, 0.0], [3.0, 0.0, 1.0]];
+        let x = solve_linear(a, [7.0, 3.0, 6.0]).unwrap();
+        for (xi, expected) in x.iter().zip([1.0, 2.0, 3.0]) {
+            assert!(is_close!(*xi, expected));
+        }
+        assert!(solve_linear([[1.0, 2.0], [2.0, 4.0]], [1.0, 2.0]).is_none());
+    }
+
+    #[test]
+    fn test_bisect() {
+        let root = bisect(|x| Ok(x * x - 2.0), 0.0, 2.0).unwrap();
+        assert!(is_close!(root, 2f64.sqrt()));
+        assert!(bisect(|x| Ok(x * x + 1.0), 0.0, 2.0).is_err());
+    }
+
+    #[test]
+    fn test_gauss_legendre() {
+        let rule = gauss_legendre::<8>();
+        assert!(is_close!(rule.1.iter().sum::<f64>(), 2.0));
+        // Exact for polynomials up to degree 15.
+        let integral = integrate(|x| x.powi(15) + x.powi(14), 0.0, 1.0, &rule);
+        assert!(is_close!(integral, 1.0 / 16.0 + 1.0 / 15.0));
+        let integral = integrate(f64::exp, 0.0, 1.0, &gauss_legendre::<16>());
+        assert!(is_close!(integral, 1f64.exp() - 1.0));
+    }
+
+    #[test]
+    fn test_erf() {
+        let values = [
+            (0.0, 0.0),
+            (0.5, 0.5204998778130465),
+            (1.0, 0.8427007929497149),
+            (2.0, 0.9953222650189527),
+            (2.9, 0.9999589021219005),
+            (3.5, 0.9999992569016276),
+        ];
+        for (x, expected) in values {
+            assert!(is_close!(erf(x), expected, rel_tol = 1e-13));
+            assert!(is_close!(erf(-x), -expected, rel_tol = 1e-13));
+        }
+        assert_eq!(erf(10.0), 1.0);
+    }
+}
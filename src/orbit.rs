@@ -0,0 +1,473 @@
+//! Classification of guiding-centre orbits.
+//!
+//! An orbit is fully determined by the particle's invariants: the energy `E`, the magnetic moment
+//! `μ` and the canonical toroidal momentum `P_ζ = (𝜌∥ + α)g − 𝜓ₚ`, where `α` is the
+//! [`Perturbation`]. Its starting point is found on the surface `θ = θ₀`, where
+//! `𝜌∥ = (P_ζ + 𝜓ₚ)/g − α` and the energy condition only leave `𝜓ₚ` free. Since a co-passing
+//! and a counter-passing orbit may share the same invariants, the sign of `𝜌∥` at the starting
+//! point is also given. In a field or a perturbation which depends on ζ or t, such as a
+//! [`Ripple`](crate::bfield::Ripple), an invariant is not conserved, and only holds at the starting
+//! point `ζ = 0`, `t = 0`.
+//!
+//! Thin orbits are classified from the constants of motion alone: on the flux surface of the
+//! starting point, the particle is passing if `E − μB − ZΦ > 0` at the maximum of B, and trapped
+//! otherwise. The extrema of B are found exactly with [`field_extrema`]. If the orbit is too wide
+//! for this to hold, or it lies too close to the trapped-passing boundary, the orbit is integrated
+//! for one poloidal transit instead.
+
+use std::f64::consts::PI;
+
+use crate::bfield::Bfield3d;
+use crate::current::Current;
+use crate::efield::Efield;
+use crate::gc::{self, Constants, State};
+use crate::particle::{IntegrationConfig, Particle, Termination};
+use crate::perturbation::Perturbation;
+use crate::qfactor::Qfactor;
+use crate::surface::field_extrema;
+use crate::{Result, Tokamak, TokamakCache};
+
+/// The type of a guiding-centre orbit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OrbitType {
+    /// Passing orbit, moving parallel to the magnetic field (`𝜌∥ > 0`).
+    CoPassing,
+    /// Passing orbit, moving anti-parallel to the magnetic field (`𝜌∥ < 0`).
+    CounterPassing,
+    /// Trapped (banana) orbit, bouncing between two values of θ.
+    Trapped,
+    /// Trapped orbit that encircles the magnetic axis, so that θ covers every value.
+    Potato,
+    /// Orbit with no parallel velocity reversal, that nevertheless does not encircle the axis.
+    Stagnation,
+    /// Orbit that leaves the plasma.
+    Lost,
+    /// No orbit with the given invariants crosses `θ = θ₀` inside the plasma.
+    Inaccessible,
+    /// The integration stopped before the orbit could be classified.
+    Undetermined,
+}
+
+/// The sign of the parallel velocity at the starting point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+    /// Parallel to the magnetic field, `𝜌∥ > 0`.
+    #[default]
+    Co,
+    /// Anti-parallel to the magnetic field, `𝜌∥ < 0`.
+    Counter,
+}
+
+/// Configuration of the orbit classification.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassifyConfig {
+    /// The poloidal angle `θ₀` of the starting point. Defaults to the low field side, `θ₀ = 0`.
+    pub theta0: f64,
+    /// The sign of `𝜌∥` at the starting point. Defaults to [`Direction::Co`].
+    pub direction: Direction,
+    /// The plasma boundary, in toroidal flux `ψ`. Orbits that cross it are lost.
+    pub psi_max: f64,
+    /// The number of `𝜓ₚ` samples used to find the starting point.
+    pub psip_samples: usize,
+    /// Orbits are thin if their estimated width is smaller than this fraction of their `𝜓ₚ`.
+    pub thin_orbit_ratio: f64,
+    /// Thin orbits are integrated anyway if the extrema of `E − μB − ZΦ` are closer to zero than
+    /// this fraction of its maximum.
+    pub boundary_margin: f64,
+    /// The configuration of the fallback integration. Its `psi_wall` and `max_transits` are
+    /// ignored.
+    pub integration: IntegrationConfig,
+}
+
+impl ClassifyConfig {
+    /// Creates a new configuration with plasma boundary `psi_max`.
+    pub fn new(psi_max: f64) -> Self {
+        Self {
+            theta0: 0.0,
+            direction: Direction::Co,
+            psi_max,
+            psip_samples: 200,
+            thin_orbit_ratio: 0.1,
+            boundary_margin: 0.05,
+            integration: IntegrationConfig::default(),
+        }
+    }
+}
+
+/// Classifies the orbit with energy `energy` and canonical toroidal momentum `pzeta`.
+///
+/// Different orbits may share the same invariants, so the classified orbit is the one through the
+/// innermost starting point in `config.direction`, as returned by [`initial_state`].
+///
+/// # Example
+///
+/// ```
+/// # use tokamak_equilibria::*;
+/// # use tokamak_equilibria::orbit::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125)?;
+/// let bfield = bfield::Lar::new()?;
+/// let current = current::Lar::new()?;
+/// let efield = efield::NoEfield::new()?;
+/// let eq = Tokamak::build(qfactor, bfield, current, efield)?;
+/// let mut cache = eq.cache();
+///
+/// let consts = gc::Constants::new(1e-5, 1.0, 1.0);
+/// let state = [0.0, 0.02, 1e-3, 0.0];
+/// let energy = gc::energy(&eq, &state, &consts, &mut cache)?;
+/// let pzeta = gc::pzeta(&eq, 0.0, &state, &mut cache)?;
+///
+/// let orbit_type = classify(&eq, energy, pzeta, &consts, &ClassifyConfig::new(0.03))?;
+/// assert_eq!(orbit_type, OrbitType::Trapped);
+/// # Ok(())
+/// # }
+/// ```
+pub fn classify<Q, B, C, E, P>(
+    tokamak: &Tokamak<Q, B, C, E, P>,
+    energy: f64,
+    pzeta: f64,
+    consts: &Constants,
+    config: &ClassifyConfig,
+) -> Result<OrbitType>
+where
+    Q: Qfactor,
+    B: Bfield3d,
+    C: Current,
+    E: Efield,
+    P: Perturbation,
+{
+    let mut cache = tokamak.cache();
+    let Some(initial) = find_initial_state(tokamak, energy, pzeta, consts, config, &mut cache)?
+    else {
+        return Ok(OrbitType::Inaccessible);
+    };
+    match thin_orbit_type(tokamak, energy, &initial, consts, config, &mut cache)? {
+        Some(orbit_type) => Ok(orbit_type),
+        None => integrated_orbit_type(tokamak, &initial, consts, config),
+    }
+}
+
+/// Finds the innermost state `[θ₀, 𝜓ₚ, 𝜌∥, 0]` at `t = 0` with energy `energy`, canonical
+/// toroidal momentum `pzeta` and the sign of `𝜌∥` given by `config.direction`, inside the plasma
+/// boundary.
+///
+/// Returns `None` if there is no such state.
+pub fn initial_state<Q, B, C, E, P>(
+    tokamak: &Tokamak<Q, B, C, E, P>,
+    energy: f64,
+    pzeta: f64,
+    consts: &Constants,
+    config: &ClassifyConfig,
+) -> Result<Option<State>>
+where
+    Q: Qfactor,
+    B: Bfield3d,
+    C: Current,
+    E: Efield,
+    P: Perturbation,
+{
+    let mut cache = tokamak.cache();
+    find_initial_state(tokamak, energy, pzeta, consts, config, &mut cache)
+}
+
+pub(crate) fn find_initial_state<Q, B, C, E, P>(
+    tokamak: &Tokamak<Q, B, C, E, P>,
+    energy: f64,
+    pzeta: f64,
+    consts: &Constants,
+    config: &ClassifyConfig,
+    cache: &mut TokamakCache<Q, B, C, E, P>,
+) -> Result<Option<State>>
+where
+    Q: Qfactor,
+    B: Bfield3d,
+    C: Current,
+    E: Efield,
+    P: Perturbation,
+{
+    const MAX_ITER: usize = 100;
+    const TOL: f64 = 1e-14;
+
+    let theta0 = config.theta0;
+    let sign = match config.direction {
+        Direction::Co => 1.0,
+        Direction::Counter => -1.0,
+    };
+    let psip_max = tokamak.qfactor.psip(config.psi_max, &mut cache.qfactor)?;
+    // The energy error and parallel gyroradius of the state at 𝜓ₚ on θ = θ₀.
+    let mut residual = |psip: f64| -> Result<(f64, f64)> {
+        let (fs, _) = gc::field_state(tokamak, theta0, psip, 0.0, cache)?;
+        let alpha =
+            tokamak
+                .perturbation
+                .alpha(fs.psi, fs.theta, 0.0, 0.0, &mut cache.perturbation)?;
+        let rho = (pzeta + psip) / fs.g - alpha;
+        Ok((gc::energy_from_field_state(&fs, rho, consts) - energy, rho))
+    };
+
+    let step = psip_max / config.psip_samples as f64;
+    let (mut a, mut fa) = (0.0, residual(0.0)?.0);
+    for k in 1..=config.psip_samples {
+        let b = k as f64 * step;
+        let fb = residual(b)?.0;
+        if fa.signum() != fb.signum() {
+            // Bisection, which keeps the root bracketed.
+            let (mut lo, mut hi, flo) = (a, b, fa);
+            for _ in 0..MAX_ITER {
+                let mid = 0.5 * (lo + hi);
+                if residual(mid)?.0.signum() == flo.signum() {
+                    lo = mid;
+                } else {
+                    hi = mid;
+                }
+                if hi - lo <= TOL * hi {
+                    break;
+                }
+            }
+            let psip = 0.5 * (lo + hi);
+            let rho = residual(psip)?.1;
+            if rho.signum() == sign {
+                return Ok(Some([theta0, psip, rho, 0.0]));
+            }
+        }
+        (a, fa) = (b, fb);
+    }
+    Ok(None)
+}
+
+/// Classifies the orbit through `initial` from the constants of motion, assuming that it stays on
+/// the starting flux surface.
+///
+/// Returns `None` if the orbit is too wide, or too close to the trapped-passing boundary.
+fn thin_orbit_type<Q, B, C, E, P>(
+    tokamak: &Tokamak<Q, B, C, E, P>,
+    energy: f64,
+    initial: &State,
+    consts: &Constants,
+    config: &ClassifyConfig,
+    cache: &mut TokamakCache<Q, B, C, E, P>,
+) -> Result<Option<OrbitType>>
+where
+    Q: Qfactor,
+    B: Bfield3d,
+    C: Current,
+    E: Efield,
+    P: Perturbation,
+{
+    let Constants { mu, charge, mass } = *consts;
+    let [_, psip, rho, _] = *initial;
+
+    let psi = tokamak.qfactor.psi_from_psip(psip, &mut cache.qfactor)?;
+    let g = tokamak.current.g(psi, &mut cache.current)?;
+    let extrema = field_extrema(&tokamak.bfield, psi)?;
+    // The parallel kinetic energy `E − μB − ZΦ` and `|𝜌∥|` at a point of the flux surface.
+    let mut parallel = |b: f64, theta: f64| -> Result<(f64, f64)> {
+        let phi = tokamak.efield.phi(psi, theta, &mut cache.efield)?;
+        let par = energy - mu * b - charge * phi;
+        Ok((par, (2.0 * mass * par.max(0.0)).sqrt() / (charge.abs() * b)))
+    };
+    let (par_at_min, rho_at_min) = parallel(extrema.b_min, extrema.theta_min)?;
+    let (par_at_max, rho_at_max) = parallel(extrema.b_max, extrema.theta_max)?;
+    let (par_min, par_max) = (par_at_min.min(par_at_max), par_at_min.max(par_at_max));
+    let (rho_min, rho_max) = (rho_at_min.min(rho_at_max), rho_at_min.max(rho_at_max));
+
+    if par_min.abs() <= config.boundary_margin * par_max.abs() {
+        return Ok(None);
+    }
+    let (orbit_type, width) = if par_min > 0.0 {
+        let passing = if rho > 0.0 {
+            OrbitType::CoPassing
+        } else {
+            OrbitType::CounterPassing
+        };
+        (passing, g * (rho_max - rho_min))
+    } else {
+        (OrbitType::Trapped, 2.0 * g * rho_max)
+    };
+
+    let psip_max = tokamak.qfactor.psip(config.psi_max, &mut cache.qfactor)?;
+    if width < config.thin_orbit_ratio * psip && psip + width < psip_max {
+        Ok(Some(orbit_type))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Classifies the orbit through `initial` by integrating it for one poloidal transit.
+///
+/// A passing orbit circulates poloidally without reversing its parallel velocity. A trapped orbit
+/// reverses it and does not circulate, while a potato orbit does both. A stagnation orbit does
+/// neither.
+fn integrated_orbit_type<Q, B, C, E, P>(
+    tokamak: &Tokamak<Q, B, C, E, P>,
+    initial: &State,
+    consts: &Constants,
+    config: &ClassifyConfig,
+) -> Result<OrbitType>
+where
+    Q: Qfactor,
+    B: Bfield3d,
+    C: Current,
+    E: Efield,
+    P: Perturbation,
+{
+    let integration = IntegrationConfig {
+        psi_wall: Some(config.psi_max),
+        max_transits: Some(1),
+        ..config.integration.clone()
+    };
+    let mut particle = Particle::new(*initial, *consts);
+    particle.integrate(tokamak, &integration)?;
+
+    match particle.termination {
+        Some(Termination::Transits) => (),
+        Some(Termination::WallHit | Termination::Escaped) => return Ok(OrbitType::Lost),
+        _ => return Ok(OrbitType::Undetermined),
+    }
+
+    let rho0 = initial[2];
+    let reversal = particle
+        .evolution
+        .rho
+        .iter()
+        .any(|rho| rho.signum() != rho0.signum());
+    let circulates = (particle.state[0] - initial[0]).abs() > PI;
+
+    Ok(match (reversal, circulates) {
+        (false, true) if rho0 > 0.0 => OrbitType::CoPassing,
+        (false, true) => OrbitType::CounterPassing,
+        (true, false) => OrbitType::Trapped,
+        (true, true) => OrbitType::Potato,
+        (false, false) => OrbitType::Stagnation,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::orbit::*;
+    use crate::testing::lar_tokamak;
+
+    /// Classifies the orbit through `state`, with and without the thin orbit classification.
+    fn classify_state(
+        state: State,
+        mu: f64,
+        direction: Direction,
+        psi_max: f64,
+    ) -> (OrbitType, OrbitType) {
+        let eq = lar_tokamak();
+        let mut cache = eq.cache();
+        let consts = gc::Constants::new(mu, 1.0, 1.0);
+        let energy = gc::energy(&eq, &state, &consts, &mut cache).unwrap();
+        let pzeta = gc::pzeta(&eq, 0.0, &state, &mut cache).unwrap();
+
+        let mut config = ClassifyConfig::new(psi_max);
+        config.direction = direction;
+        let fast = classify(&eq, energy, pzeta, &consts, &config).unwrap();
+        config.thin_orbit_ratio = 0.0;
+        let integrated = classify(&eq, energy, pzeta, &consts, &config).unwrap();
+        (fast, integrated)
+    }
+
+    #[test]
+    fn test_passing() {
+        let (fast, integrated) = classify_state([0.0, 0.02, 1e-3, 0.0], 1e-7, Direction::Co, 0.03);
+        assert_eq!(fast, OrbitType::CoPassing);
+        assert_eq!(integrated, OrbitType::CoPassing);
+
+        let (fast, integrated) =
+            classify_state([0.0, 0.02, -1e-3, 0.0], 1e-7, Direction::Counter, 0.03);
+        assert_eq!(fast, OrbitType::CounterPassing);
+        assert_eq!(integrated, OrbitType::CounterPassing);
+    }
+
+    #[test]
+    fn test_trapped() {
+        let (fast, integrated) = classify_state([0.0, 0.02, 1e-3, 0.0], 1e-5, Direction::Co, 0.03);
+        assert_eq!(fast, OrbitType::Trapped);
+        assert_eq!(integrated, OrbitType::Trapped);
+    }
+
+    #[test]
+    /// A trapped orbit near the axis, started on the high field side, encircles the axis.
+    fn test_potato() {
+        let eq = lar_tokamak();
+        let mut cache = eq.cache();
+        let consts = gc::Constants::new(1e-4, 1.0, 1.0);
+        let state = [PI, 1e-4, -1e-3, 0.0];
+        let energy = gc::energy(&eq, &state, &consts, &mut cache).unwrap();
+        let pzeta = gc::pzeta(&eq, 0.0, &state, &mut cache).unwrap();
+
+        let mut config = ClassifyConfig::new(0.1);
+        config.theta0 = PI;
+        config.direction = Direction::Counter;
+        let orbit_type = classify(&eq, energy, pzeta, &consts, &config).unwrap();
+        assert_eq!(orbit_type, OrbitType::Potato);
+    }
+
+    #[test]
+    /// A fast co-passing orbit near the axis is wider than its distance from the axis, so it
+    /// neither reverses nor encircles the axis.
+    fn test_stagnation() {
+        let (fast, integrated) = classify_state([0.0, 1e-4, 1e-2, 0.0], 1e-4, Direction::Co, 0.1);
+        assert_eq!(fast, OrbitType::Stagnation);
+        assert_eq!(integrated, OrbitType::Stagnation);
+    }
+
+    #[test]
+    fn test_lost() {
+        let (fast, integrated) =
+            classify_state([0.0, 0.017, -5e-3, 0.0], 1e-4, Direction::Counter, 0.02);
+        assert_eq!(fast, OrbitType::Lost);
+        assert_eq!(integrated, OrbitType::Lost);
+    }
+
+    #[test]
+    fn test_initial_state() {
+        let eq = lar_tokamak();
+        let mut cache = eq.cache();
+        let consts = gc::Constants::new(1e-7, 1.0, 1.0);
+        let state = [0.0, 0.02, 1e-3, 0.0];
+        let energy = gc::energy(&eq, &state, &consts, &mut cache).unwrap();
+        let pzeta = gc::pzeta(&eq, 0.0, &state, &mut cache).unwrap();
+        let config = ClassifyConfig::new(0.03);
+
+        let initial = initial_state(&eq, energy, pzeta, &consts, &config)
+            .unwrap()
+            .unwrap();
+        let e = gc::energy(&eq, &initial, &consts, &mut cache).unwrap();
+        let p = gc::pzeta(&eq, 0.0, &initial, &mut cache).unwrap();
+        assert!(is_close::is_close!(e, energy, rel_tol = 1e-10));
+        assert!(is_close::is_close!(p, pzeta, rel_tol = 1e-10));
+
+        // Below μB everywhere.
+        let config = ClassifyConfig::new(0.03);
+        let orbit_type = classify(&eq, 0.5e-7, pzeta, &consts, &config).unwrap();
+        assert_eq!(orbit_type, OrbitType::Inaccessible);
+    }
+
+    #[test]
+    /// With a perturbation, the starting point must have the given canonical momentum
+    /// (𝜌∥ + α)g − 𝜓ₚ, rather than that of the equilibrium.
+    fn test_initial_state_perturbed() {
+        use crate::perturbation::{Envelope, Harmonic, Harmonics};
+
+        let envelope = Envelope::gaussian(0.022, 0.005).unwrap();
+        let perturbation = Harmonics::new(vec![Harmonic::new(1, 0, 1e-4, envelope)]).unwrap();
+        let eq = lar_tokamak().with_perturbation(perturbation);
+        let mut cache = eq.cache();
+        let consts = gc::Constants::new(1e-7, 1.0, 1.0);
+        let state = [0.0, 0.02, 1e-3, 0.0];
+        let energy = gc::energy(&eq, &state, &consts, &mut cache).unwrap();
+        let pzeta = gc::pzeta(&eq, 0.0, &state, &mut cache).unwrap();
+        let config = ClassifyConfig::new(0.03);
+
+        let initial = initial_state(&eq, energy, pzeta, &consts, &config)
+            .unwrap()
+            .unwrap();
+        assert!(is_close::is_close!(initial[1], state[1], rel_tol = 1e-8));
+        assert!(is_close::is_close!(initial[2], state[2], rel_tol = 1e-8));
+        let p = gc::pzeta(&eq, 0.0, &initial, &mut cache).unwrap();
+        assert!(is_close::is_close!(p, pzeta, rel_tol = 1e-10));
+    }
+}
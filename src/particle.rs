@@ -0,0 +1,548 @@
+//! Guiding-centre particles and their orbit integration.
+
+use std::f64::consts::TAU;
+
+use crate::bfield::Bfield3d;
+use crate::current::Current;
+use crate::efield::Efield;
+use crate::gc::{self, Constants, State};
+use crate::perturbation::Perturbation;
+use crate::qfactor::Qfactor;
+use crate::solver::Stepper;
+use crate::{EqError, Result, Tokamak, TokamakCache};
+
+/// Configuration of the orbit integration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntegrationConfig {
+    /// Relative tolerance of the local error.
+    pub rtol: f64,
+    /// Absolute tolerance of the local error.
+    pub atol: f64,
+    /// The initial step size.
+    pub first_step: f64,
+    /// The maximum number of accepted steps.
+    pub max_steps: usize,
+    /// The final integration time.
+    pub t_max: f64,
+    /// The wall position, in toroidal flux `ψ`. Ignored if `None`.
+    pub psi_wall: Option<f64>,
+    /// The number of poloidal transits after which the integration stops. Ignored if `None`.
+    pub max_transits: Option<usize>,
+}
+
+impl Default for IntegrationConfig {
+    fn default() -> Self {
+        Self {
+            rtol: 1e-10,
+            atol: 1e-12,
+            first_step: 1e-1,
+            max_steps: 1_000_000,
+            t_max: f64::INFINITY,
+            psi_wall: None,
+            max_transits: None,
+        }
+    }
+}
+
+impl IntegrationConfig {
+    /// The poloidal flux of the wall, `∞` if there is none.
+    ///
+    /// A wall outside the domain of the q-factor is an error of the configuration, rather than an
+    /// escape of the particle.
+    pub(crate) fn psip_wall<Q: Qfactor>(&self, qfactor: &Q, cache: &mut Q::Cache) -> Result<f64> {
+        let Some(psi_wall) = self.psi_wall else {
+            return Ok(f64::INFINITY);
+        };
+        let invalid = |reason: &str| {
+            EqError::IntegrationError(format!("invalid wall ψ = {psi_wall}: {reason}"))
+        };
+        if !(psi_wall > 0.0 && psi_wall.is_finite()) {
+            return Err(invalid("not a positive flux"));
+        }
+        qfactor
+            .psip(psi_wall, cache)
+            .map_err(|err| invalid(&err.to_string()))
+    }
+}
+
+/// The reason the orbit integration stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    /// The final integration time was reached.
+    TimeLimit,
+    /// The maximum number of steps was reached.
+    MaxSteps,
+    /// The particle crossed the wall.
+    WallHit,
+    /// The requested number of poloidal transits was completed.
+    Transits,
+    /// The requested number of Poincaré section intersections was collected.
+    Intersections,
+    /// The particle left the domain of the equilibrium's profiles.
+    Escaped,
+}
+
+/// The time series of a particle's orbit.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Evolution {
+    /// The time of each accepted step.
+    pub time: Vec<f64>,
+    /// The poloidal angle `θ`.
+    pub theta: Vec<f64>,
+    /// The poloidal flux `𝜓ₚ`.
+    pub psip: Vec<f64>,
+    /// The parallel gyroradius `𝜌∥`.
+    pub rho: Vec<f64>,
+    /// The toroidal angle `ζ`.
+    pub zeta: Vec<f64>,
+}
+
+impl Evolution {
+    /// Appends a state to the time series.
+    fn push(&mut self, t: f64, state: &State) {
+        self.time.push(t);
+        self.theta.push(state[0]);
+        self.psip.push(state[1]);
+        self.rho.push(state[2]);
+        self.zeta.push(state[3]);
+    }
+
+    /// The number of stored states.
+    pub fn len(&self) -> usize {
+        self.time.len()
+    }
+
+    /// Returns `true` if no states are stored.
+    pub fn is_empty(&self) -> bool {
+        self.time.is_empty()
+    }
+}
+
+/// A guiding-centre particle.
+///
+/// # Example
+///
+/// ```
+/// # use tokamak_equilibria::*;
+/// # use tokamak_equilibria::particle::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125)?;
+/// let bfield = bfield::Lar::new()?;
+/// let current = current::Lar::new()?;
+/// let efield = efield::NoEfield::new()?;
+/// let eq = Tokamak::build(qfactor, bfield, current, efield)?;
+///
+/// let consts = gc::Constants::new(1e-7, 1.0, 1.0);
+/// let mut particle = Particle::new([0.0, 0.02, 1e-3, 0.0], consts);
+///
+/// let config = IntegrationConfig {
+///     max_transits: Some(2),
+///     ..Default::default()
+/// };
+/// particle.integrate(&eq, &config)?;
+/// assert_eq!(particle.termination, Some(Termination::Transits));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Particle {
+    /// The particle's constants.
+    pub constants: Constants,
+    /// The initial state `[θ, 𝜓ₚ, 𝜌∥, ζ]`.
+    pub initial: State,
+    /// The current state `[θ, 𝜓ₚ, 𝜌∥, ζ]`.
+    pub state: State,
+    /// The current time.
+    pub t: f64,
+    /// The number of completed poloidal transits.
+    ///
+    /// A transit is completed every time θ crosses its initial value (modulo 2π), in the
+    /// direction of its initial motion. This counts the poloidal transits of passing particles
+    /// and the bounces of trapped particles alike.
+    pub transits: usize,
+    /// The orbit's time series.
+    pub evolution: Evolution,
+    /// The reason the last integration stopped, `None` if the particle has not been integrated.
+    pub termination: Option<Termination>,
+}
+
+impl Particle {
+    /// Creates a new particle at `initial = [θ, 𝜓ₚ, 𝜌∥, ζ]`, at `t = 0`.
+    pub fn new(initial: State, constants: Constants) -> Self {
+        Self {
+            constants,
+            initial,
+            state: initial,
+            t: 0.0,
+            transits: 0,
+            evolution: Evolution::default(),
+            termination: None,
+        }
+    }
+
+    /// Integrates the particle's orbit in `tokamak`, until one of the stop conditions of `config`
+    /// is met.
+    ///
+    /// Leaving the domain of the equilibrium's profiles is not an error, but is reported as
+    /// [`Termination::Escaped`]. A wall outside the domain, a non-finite local error, or too many
+    /// consecutive rejected steps are errors.
+    pub fn integrate<Q, B, C, E, P>(
+        &mut self,
+        tokamak: &Tokamak<Q, B, C, E, P>,
+        config: &IntegrationConfig,
+    ) -> Result<()>
+    where
+        Q: Qfactor,
+        B: Bfield3d,
+        C: Current,
+        E: Efield,
+        P: Perturbation,
+    {
+        let mut cache = tokamak.cache();
+        let psip_wall = config.psip_wall(&tokamak.qfactor, &mut cache.qfactor)?;
+        match self.run(tokamak, config, psip_wall, &mut cache) {
+            Err(EqError::DomainError(_)) => {
+                self.termination = Some(Termination::Escaped);
+                Ok(())
+            }
+            res => res,
+        }
+    }
+
+    fn run<Q, B, C, E, P>(
+        &mut self,
+        tokamak: &Tokamak<Q, B, C, E, P>,
+        config: &IntegrationConfig,
+        psip_wall: f64,
+        cache: &mut TokamakCache<Q, B, C, E, P>,
+    ) -> Result<()>
+    where
+        Q: Qfactor,
+        B: Bfield3d,
+        C: Current,
+        E: Efield,
+        P: Perturbation,
+    {
+        let consts = self.constants;
+        let mut f = |t: f64, y: &State| gc::rhs(tokamak, t, y, &consts, cache);
+
+        // Direction of the initial poloidal motion, used for counting the transits.
+        let direction = f(0.0, &self.initial)?[0].signum();
+        if self.evolution.is_empty() {
+            self.evolution.push(self.t, &self.state);
+        }
+
+        let mut stepper = Stepper::new(config.first_step, config.rtol, config.atol);
+        let mut steps = 0;
+        loop {
+            if self.t >= config.t_max {
+                self.termination = Some(Termination::TimeLimit);
+                return Ok(());
+            }
+            if steps >= config.max_steps {
+                self.termination = Some(Termination::MaxSteps);
+                return Ok(());
+            }
+
+            let (h, y_new) = stepper.step(&mut f, self.t, &self.state, config.t_max)?;
+            self.transits += crossings(self.state[0], y_new[0], self.initial[0], direction);
+            self.t += h;
+            self.state = y_new;
+            self.evolution.push(self.t, &self.state);
+            steps += 1;
+
+            if self.state[1] >= psip_wall {
+                self.termination = Some(Termination::WallHit);
+                return Ok(());
+            }
+            if config.max_transits.is_some_and(|max| self.transits >= max) {
+                self.termination = Some(Termination::Transits);
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// The number of times θ crossed `theta0` (modulo 2π) in `direction`, while moving from `from` to
+/// `to`.
+///
+/// Starting exactly on `theta0` does not count as a crossing.
+pub(crate) fn crossings(from: f64, to: f64, theta0: f64, direction: f64) -> usize {
+    let (from, to) = ((from - theta0) / TAU, (to - theta0) / TAU);
+    let diff = if direction > 0.0 {
+        to.floor() - from.floor()
+    } else {
+        from.ceil() - to.ceil()
+    };
+    diff.max(0.0) as usize
+}
+
+#[cfg(test)]
+mod test {
+    use is_close::is_close;
+
+    use crate::particle::*;
+    use crate::testing::{lar_tokamak, parabolic, ripple_tokamak};
+    use crate::*;
+
+    #[test]
+    fn test_crossings() {
+        assert_eq!(crossings(0.1, 0.3, 0.2, 1.0), 1);
+        assert_eq!(crossings(0.3, 0.1, 0.2, 1.0), 0);
+        assert_eq!(crossings(0.3, 0.1, 0.2, -1.0), 1);
+        assert_eq!(crossings(0.1, 0.1 + 2.0 * TAU, 0.2, 1.0), 2);
+        assert_eq!(crossings(-0.1, 0.1, 0.0, 1.0), 1);
+        assert_eq!(crossings(0.0, 0.1, 0.0, 1.0), 0);
+        assert_eq!(crossings(0.0, -0.1, 0.0, -1.0), 0);
+        assert_eq!(crossings(0.1, -0.1, 0.0, -1.0), 1);
+        assert_eq!(crossings(-TAU + 0.1, -TAU - 0.1, 0.0, -1.0), 1);
+    }
+
+    #[test]
+    fn test_passing_particle_conservation() {
+        let eq = lar_tokamak();
+        let mut cache = eq.cache();
+        let consts = gc::Constants::new(1e-7, 1.0, 1.0);
+        let mut particle = Particle::new([0.0, 0.02, 1e-3, 0.0], consts);
+
+        let config = IntegrationConfig {
+            max_transits: Some(3),
+            ..Default::default()
+        };
+        particle.integrate(&eq, &config).unwrap();
+        assert_eq!(particle.termination, Some(Termination::Transits));
+        assert_eq!(particle.transits, 3);
+
+        let e0 = gc::energy(&eq, &particle.initial, &consts, &mut cache).unwrap();
+        let e1 = gc::energy(&eq, &particle.state, &consts, &mut cache).unwrap();
+        let p0 = gc::pzeta(&eq, 0.0, &particle.initial, &mut cache).unwrap();
+        let p1 = gc::pzeta(&eq, particle.t, &particle.state, &mut cache).unwrap();
+        assert!(is_close!(e0, e1, rel_tol = 1e-7));
+        assert!(is_close!(p0, p1, rel_tol = 1e-7));
+        // Passing particles move through all θ in one direction.
+        assert!(particle.state[0] > 3.0 * TAU);
+    }
+
+    #[test]
+    fn test_trapped_particle_bounces() {
+        let eq = lar_tokamak();
+        let consts = gc::Constants::new(1e-5, 1.0, 1.0);
+        let mut particle = Particle::new([0.0, 0.02, 1e-3, 0.0], consts);
+
+        let config = IntegrationConfig {
+            max_transits: Some(2),
+            ..Default::default()
+        };
+        particle.integrate(&eq, &config).unwrap();
+        assert_eq!(particle.termination, Some(Termination::Transits));
+        // Trapped particles never complete a poloidal turn.
+        let theta = &particle.evolution.theta;
+        let max = theta.iter().cloned().fold(f64::MIN, f64::max);
+        let min = theta.iter().cloned().fold(f64::MAX, f64::min);
+        assert!(max - min < TAU);
+        // The parallel velocity changes sign at the bounce points.
+        assert!(particle.evolution.rho.iter().any(|rho| *rho < 0.0));
+    }
+
+    #[test]
+    /// The ripple breaks the conservation of P_ζ, and changes the orbit, but not the energy.
+    fn test_ripple() {
+        let axisymmetric = lar_tokamak();
+        let eq = ripple_tokamak(0.01);
+        let mut cache = eq.cache();
+        let consts = gc::Constants::new(1e-5, 1.0, 1.0);
+
+        let config = IntegrationConfig {
+            max_transits: Some(2),
+            ..Default::default()
+        };
+        let mut reference = Particle::new([0.0, 0.02, 1e-3, 0.0], consts);
+        reference.integrate(&axisymmetric, &config).unwrap();
+        let mut particle = Particle::new([0.0, 0.02, 1e-3, 0.0], consts);
+        particle.integrate(&eq, &config).unwrap();
+        assert_eq!(particle.termination, Some(Termination::Transits));
+
+        let e0 = gc::energy(&eq, &particle.initial, &consts, &mut cache).unwrap();
+        let e1 = gc::energy(&eq, &particle.state, &consts, &mut cache).unwrap();
+        let p0 = gc::pzeta(&eq, 0.0, &particle.initial, &mut cache).unwrap();
+        let p1 = gc::pzeta(&eq, particle.t, &particle.state, &mut cache).unwrap();
+        assert!(is_close!(e0, e1, rel_tol = 1e-7));
+        assert!(!is_close!(p0, p1, rel_tol = 1e-4));
+        assert!(!is_close!(particle.t, reference.t, rel_tol = 1e-3));
+    }
+
+    #[test]
+    /// A static, axisymmetric perturbation changes the orbit, but conserves the energy and P_ζ.
+    fn test_perturbation() {
+        use crate::perturbation::{Envelope, Harmonic, Harmonics};
+
+        let reference = lar_tokamak();
+        let envelope = Envelope::gaussian(0.022, 0.005).unwrap();
+        let perturbation = Harmonics::new(vec![Harmonic::new(2, 0, 1e-4, envelope)]).unwrap();
+        let eq = lar_tokamak().with_perturbation(perturbation);
+        let mut cache = eq.cache();
+        let consts = gc::Constants::new(1e-5, 1.0, 1.0);
+
+        let config = IntegrationConfig {
+            max_transits: Some(2),
+            ..Default::default()
+        };
+        let mut unperturbed = Particle::new([0.0, 0.02, 1e-3, 0.0], consts);
+        unperturbed.integrate(&reference, &config).unwrap();
+        let mut particle = Particle::new([0.0, 0.02, 1e-3, 0.0], consts);
+        particle.integrate(&eq, &config).unwrap();
+        assert_eq!(particle.termination, Some(Termination::Transits));
+
+        let p0 = gc::pzeta(&eq, 0.0, &particle.initial, &mut cache).unwrap();
+        let p1 = gc::pzeta(&eq, particle.t, &particle.state, &mut cache).unwrap();
+        let e0 = gc::energy(&eq, &particle.initial, &consts, &mut cache).unwrap();
+        let e1 = gc::energy(&eq, &particle.state, &consts, &mut cache).unwrap();
+        assert!(is_close!(e0, e1, rel_tol = 1e-7));
+        assert!(is_close!(p0, p1, rel_tol = 1e-7));
+        assert!(!is_close!(particle.t, unperturbed.t, rel_tol = 1e-3));
+    }
+
+    #[test]
+    fn test_stop_conditions() {
+        let eq = lar_tokamak();
+        let consts = gc::Constants::new(1e-7, 1.0, 1.0);
+
+        let mut particle = Particle::new([0.0, 0.02, 1e-3, 0.0], consts);
+        let config = IntegrationConfig {
+            t_max: 100.0,
+            ..Default::default()
+        };
+        particle.integrate(&eq, &config).unwrap();
+        assert_eq!(particle.termination, Some(Termination::TimeLimit));
+        assert!(is_close!(particle.t, 100.0));
+
+        let mut particle = Particle::new([0.0, 0.02, 1e-3, 0.0], consts);
+        let config = IntegrationConfig {
+            max_steps: 10,
+            ..Default::default()
+        };
+        particle.integrate(&eq, &config).unwrap();
+        assert_eq!(particle.termination, Some(Termination::MaxSteps));
+        assert_eq!(particle.evolution.len(), 11);
+    }
+
+    #[test]
+    /// A wide trapped orbit, started just inside the wall and drifting outwards, must hit it.
+    fn test_wall_hit() {
+        let eq = lar_tokamak();
+        let consts = gc::Constants::new(1e-4, 1.0, 1.0);
+        let psi_wall = 0.02;
+        let mut particle = Particle::new([0.0, 0.017, -5e-3, 0.0], consts);
+
+        let config = IntegrationConfig {
+            psi_wall: Some(psi_wall),
+            max_transits: Some(2),
+            ..Default::default()
+        };
+        particle.integrate(&eq, &config).unwrap();
+        assert_eq!(particle.termination, Some(Termination::WallHit));
+    }
+
+    #[test]
+    /// Leaving the domain of a numerical q-factor is reported as an escape.
+    fn test_escaped() {
+        let analytical = parabolic();
+        let mut qcache = analytical.cache();
+        let psi_data: Vec<f64> = (0..=50).map(|k| k as f64 * 0.02 / 50.0).collect();
+        let q_data: Vec<f64> = psi_data
+            .iter()
+            .map(|psi| analytical.q(*psi, &mut qcache).unwrap())
+            .collect();
+        let qfactor = qfactor::Numerical::from_arrays(&psi_data, &q_data, Interp1d::Cubic).unwrap();
+
+        let bfield = bfield::Lar::new().unwrap();
+        let current = current::Lar::new().unwrap();
+        let efield = efield::NoEfield::new().unwrap();
+        let eq = Tokamak::build(qfactor, bfield, current, efield).unwrap();
+
+        let consts = gc::Constants::new(1e-4, 1.0, 1.0);
+        let psip0 = eq.qfactor.psip(0.015, &mut eq.qfactor.cache()).unwrap();
+        let mut particle = Particle::new([0.0, psip0, -5e-3, 0.0], consts);
+
+        let config = IntegrationConfig {
+            max_transits: Some(1),
+            ..Default::default()
+        };
+        particle.integrate(&eq, &config).unwrap();
+        assert_eq!(particle.termination, Some(Termination::Escaped));
+    }
+
+    /// Electric field with a non-finite derivative, so that every trial step fails.
+    struct NanEfield;
+
+    impl Efield for NanEfield {
+        type Cache = cache::NoCache;
+
+        #[allow(unused_variables)]
+        fn phi(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+            Ok(0.0)
+        }
+
+        #[allow(unused_variables)]
+        fn e(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+            Ok(f64::NAN)
+        }
+
+        #[allow(unused_variables)]
+        fn dphi_dpsi(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+            Ok(f64::NAN)
+        }
+
+        #[allow(unused_variables)]
+        fn dphi_dtheta(&self, psi: f64, theta: f64, cache: &mut Self::Cache) -> Result<f64> {
+            Ok(0.0)
+        }
+    }
+
+    #[test]
+    /// A non-finite local error must fail the integration, instead of resetting the step size.
+    fn test_non_finite_error() {
+        let bfield = bfield::Lar::new().unwrap();
+        let current = current::Lar::new().unwrap();
+        let eq = Tokamak::build(parabolic(), bfield, current, NanEfield).unwrap();
+        let consts = gc::Constants::new(1e-7, 1.0, 1.0);
+        let mut particle = Particle::new([0.0, 0.02, 1e-3, 0.0], consts);
+
+        let config = IntegrationConfig {
+            t_max: 100.0,
+            ..Default::default()
+        };
+        let res = particle.integrate(&eq, &config);
+        assert!(matches!(res, Err(EqError::IntegrationError(_))));
+    }
+
+    #[test]
+    /// A wall outside the domain of the q-factor is an error, not an escape.
+    fn test_invalid_wall() {
+        let analytical = parabolic();
+        let psi_data: Vec<f64> = (0..=50).map(|k| k as f64 * 0.02 / 50.0).collect();
+        let q_data: Vec<f64> = psi_data
+            .iter()
+            .map(|psi| analytical.q(*psi, &mut analytical.cache()).unwrap())
+            .collect();
+        let qfactor = qfactor::Numerical::from_arrays(&psi_data, &q_data, Interp1d::Cubic).unwrap();
+        let bfield = bfield::Lar::new().unwrap();
+        let current = current::Lar::new().unwrap();
+        let efield = efield::NoEfield::new().unwrap();
+        let eq = Tokamak::build(qfactor, bfield, current, efield).unwrap();
+        let consts = gc::Constants::new(1e-7, 1.0, 1.0);
+
+        for psi_wall in [0.05, -0.01, f64::NAN] {
+            let mut particle = Particle::new([0.0, 0.01, 1e-3, 0.0], consts);
+            let config = IntegrationConfig {
+                psi_wall: Some(psi_wall),
+                max_transits: Some(1),
+                ..Default::default()
+            };
+            let res = particle.integrate(&eq, &config);
+            assert!(matches!(res, Err(EqError::IntegrationError(_))));
+            assert_eq!(particle.termination, None);
+        }
+    }
+}
@@ -0,0 +1,345 @@
+use crate::cache::NoCache;
+use crate::perturbation::Perturbation;
+use crate::{EqError, Result};
+
+/// The radial shape of a perturbation harmonic, normalized to 1 at its peak or core.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Envelope {
+    /// `exp(−((ψ − ψ_c)/w)²)`, localized around ψ_c.
+    Gaussian {
+        /// The center ψ_c.
+        center: f64,
+        /// The width w.
+        width: f64,
+    },
+    /// `(1 − tanh((ψ − ψ_c)/w))/2`, a step from 1 inside ψ_c to 0 outside it, or the reverse for
+    /// negative w.
+    Tanh {
+        /// The center ψ_c of the step.
+        center: f64,
+        /// The width w of the step.
+        width: f64,
+    },
+    /// `Σ c_k ψᵏ`, with the coefficients in increasing order.
+    Polynomial(Vec<f64>),
+}
+
+impl Envelope {
+    /// Creates a Gaussian envelope of `width` centered at `center`.
+    pub fn gaussian(center: f64, width: f64) -> Result<Self> {
+        if !(width > 0.0 && center.is_finite()) {
+            return Err(EqError::InvalidEquilibrium(format!(
+                "invalid Gaussian envelope with ψ_c = {center} and w = {width}"
+            )));
+        }
+        Ok(Self::Gaussian { center, width })
+    }
+
+    /// Creates a tanh step of `width` centered at `center`.
+    pub fn tanh(center: f64, width: f64) -> Result<Self> {
+        if width == 0.0 || !width.is_finite() || !center.is_finite() {
+            return Err(EqError::InvalidEquilibrium(format!(
+                "invalid tanh envelope with ψ_c = {center} and w = {width}"
+            )));
+        }
+        Ok(Self::Tanh { center, width })
+    }
+
+    /// Creates a polynomial envelope with `coefficients` in increasing order.
+    pub fn polynomial(coefficients: Vec<f64>) -> Result<Self> {
+        if coefficients.is_empty() || coefficients.iter().any(|c| !c.is_finite()) {
+            return Err(EqError::InvalidEquilibrium(format!(
+                "invalid polynomial envelope with coefficients {coefficients:?}"
+            )));
+        }
+        Ok(Self::Polynomial(coefficients))
+    }
+
+    /// Calculates the envelope at `psi`.
+    pub fn value(&self, psi: f64) -> f64 {
+        match self {
+            Self::Gaussian { center, width } => (-((psi - center) / width).powi(2)).exp(),
+            Self::Tanh { center, width } => 0.5 * (1.0 - ((psi - center) / width).tanh()),
+            Self::Polynomial(c) => c.iter().rev().fold(0.0, |acc, ck| acc * psi + ck),
+        }
+    }
+
+    /// Calculates the derivative of the envelope at `psi`.
+    pub fn derivative(&self, psi: f64) -> f64 {
+        match self {
+            Self::Gaussian { center, width } => {
+                let x = (psi - center) / width;
+                -2.0 * x / width * (-x * x).exp()
+            }
+            Self::Tanh { center, width } => {
+                let tanh = ((psi - center) / width).tanh();
+                -0.5 * (1.0 - tanh * tanh) / width
+            }
+            Self::Polynomial(c) => c
+                .iter()
+                .enumerate()
+                .skip(1)
+                .rev()
+                .fold(0.0, |acc, (k, ck)| acc * psi + k as f64 * ck),
+        }
+    }
+}
+
+/// A single Fourier harmonic `α_mn(ψ)⋅cos(mθ − nζ − ωt + φ)` of the perturbation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Harmonic {
+    /// The poloidal mode number m.
+    pub m: i32,
+    /// The toroidal mode number n.
+    pub n: i32,
+    /// The amplitude, which multiplies the envelope.
+    pub amplitude: f64,
+    /// The radial envelope.
+    pub envelope: Envelope,
+    /// The angular frequency ω.
+    pub omega: f64,
+    /// The phase φ.
+    pub phase: f64,
+}
+
+impl Harmonic {
+    /// Creates a static harmonic with mode numbers `m` and `n`, and radial profile
+    /// `α_mn = amplitude⋅envelope`.
+    pub fn new(m: i32, n: i32, amplitude: f64, envelope: Envelope) -> Self {
+        Self {
+            m,
+            n,
+            amplitude,
+            envelope,
+            omega: 0.0,
+            phase: 0.0,
+        }
+    }
+
+    /// Sets the angular frequency ω of the harmonic.
+    pub fn with_frequency(self, omega: f64) -> Self {
+        Self { omega, ..self }
+    }
+
+    /// Sets the phase φ of the harmonic.
+    pub fn with_phase(self, phase: f64) -> Self {
+        Self { phase, ..self }
+    }
+
+    /// The phase `mθ − nζ − ωt + φ`.
+    fn angle(&self, theta: f64, zeta: f64, t: f64) -> f64 {
+        f64::from(self.m) * theta - f64::from(self.n) * zeta - self.omega * t + self.phase
+    }
+}
+
+/// A perturbation made of a sum of analytical [`Harmonic`]s.
+///
+/// # Example
+///
+/// ```
+/// # use tokamak_equilibria::*;
+/// # use tokamak_equilibria::perturbation::*;
+/// #
+/// # fn main() -> Result<()> {
+/// // A rotating 3/2 tearing mode and a static 2/1 error field.
+/// let tearing = Harmonic::new(3, 2, 1e-4, Envelope::gaussian(0.06, 0.01)?)
+///     .with_frequency(1e-5)
+///     .with_phase(0.5);
+/// let error_field = Harmonic::new(2, 1, 5e-5, Envelope::polynomial(vec![0.0, 0.0, 64.0])?);
+/// let perturbation = Harmonics::new(vec![tearing, error_field])?;
+///
+/// let eq = Tokamak::build(
+///     qfactor::Parabolic::new(1.1, 3.9, 0.125)?,
+///     bfield::Lar::new()?,
+///     current::Lar::new()?,
+///     efield::NoEfield::new()?,
+/// )?
+/// .with_perturbation(perturbation);
+/// let mut cache = eq.cache();
+///
+/// let alpha = eq.perturbation.alpha(0.06, 1.0, 2.0, 0.0, &mut cache.perturbation)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Harmonics {
+    /// The harmonics of the perturbation.
+    pub harmonics: Vec<Harmonic>,
+}
+
+impl Harmonics {
+    /// Creates a new perturbation from its `harmonics`.
+    pub fn new(harmonics: Vec<Harmonic>) -> Result<Self> {
+        if let Some(h) = harmonics
+            .iter()
+            .find(|h| !(h.amplitude.is_finite() && h.omega.is_finite() && h.phase.is_finite()))
+        {
+            return Err(EqError::InvalidEquilibrium(format!(
+                "invalid harmonic m = {}, n = {}",
+                h.m, h.n
+            )));
+        }
+        Ok(Self { harmonics })
+    }
+
+    /// Sums `f(harmonic, mθ − nζ − ωt + φ)` over all harmonics.
+    fn sum<F>(&self, theta: f64, zeta: f64, t: f64, f: F) -> f64
+    where
+        F: Fn(&Harmonic, f64) -> f64,
+    {
+        self.harmonics
+            .iter()
+            .map(|h| f(h, h.angle(theta, zeta, t)))
+            .sum()
+    }
+}
+
+impl Perturbation for Harmonics {
+    type Cache = NoCache;
+
+    /// Returns `Σ α_mn⋅cos(mθ − nζ − ωt + φ)`
+    #[allow(unused_variables)]
+    fn alpha(
+        &self,
+        psi: f64,
+        theta: f64,
+        zeta: f64,
+        t: f64,
+        cache: &mut Self::Cache,
+    ) -> Result<f64> {
+        Ok(self.sum(theta, zeta, t, |h, angle| {
+            h.amplitude * h.envelope.value(psi) * angle.cos()
+        }))
+    }
+
+    /// Returns `Σ α′_mn⋅cos(mθ − nζ − ωt + φ)`
+    #[allow(unused_variables)]
+    fn dalpha_dpsi(
+        &self,
+        psi: f64,
+        theta: f64,
+        zeta: f64,
+        t: f64,
+        cache: &mut Self::Cache,
+    ) -> Result<f64> {
+        Ok(self.sum(theta, zeta, t, |h, angle| {
+            h.amplitude * h.envelope.derivative(psi) * angle.cos()
+        }))
+    }
+
+    /// Returns `−Σ m⋅α_mn⋅sin(mθ − nζ − ωt + φ)`
+    #[allow(unused_variables)]
+    fn dalpha_dtheta(
+        &self,
+        psi: f64,
+        theta: f64,
+        zeta: f64,
+        t: f64,
+        cache: &mut Self::Cache,
+    ) -> Result<f64> {
+        Ok(self.sum(theta, zeta, t, |h, angle| {
+            -f64::from(h.m) * h.amplitude * h.envelope.value(psi) * angle.sin()
+        }))
+    }
+
+    /// Returns `Σ n⋅α_mn⋅sin(mθ − nζ − ωt + φ)`
+    #[allow(unused_variables)]
+    fn dalpha_dzeta(
+        &self,
+        psi: f64,
+        theta: f64,
+        zeta: f64,
+        t: f64,
+        cache: &mut Self::Cache,
+    ) -> Result<f64> {
+        Ok(self.sum(theta, zeta, t, |h, angle| {
+            f64::from(h.n) * h.amplitude * h.envelope.value(psi) * angle.sin()
+        }))
+    }
+
+    /// Returns `Σ ω⋅α_mn⋅sin(mθ − nζ − ωt + φ)`
+    #[allow(unused_variables)]
+    fn dalpha_dt(
+        &self,
+        psi: f64,
+        theta: f64,
+        zeta: f64,
+        t: f64,
+        cache: &mut Self::Cache,
+    ) -> Result<f64> {
+        Ok(self.sum(theta, zeta, t, |h, angle| {
+            h.omega * h.amplitude * h.envelope.value(psi) * angle.sin()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use is_close::is_close;
+
+    use crate::perturbation::*;
+
+    #[test]
+    fn test_envelopes() {
+        let gaussian = Envelope::gaussian(0.05, 0.01).unwrap();
+        assert_eq!(gaussian.value(0.05), 1.0);
+        assert!(is_close!(gaussian.value(0.06), (-1.0f64).exp()));
+
+        let tanh = Envelope::tanh(0.05, 0.01).unwrap();
+        assert_eq!(tanh.value(0.05), 0.5);
+        assert!(tanh.value(0.0) > 0.99 && tanh.value(0.1) < 0.01);
+
+        let poly = Envelope::polynomial(vec![1.0, -2.0, 3.0]).unwrap();
+        assert!(is_close!(poly.value(0.5), 0.75));
+        assert!(is_close!(poly.derivative(0.5), 1.0));
+
+        assert!(Envelope::gaussian(0.05, 0.0).is_err());
+        assert!(Envelope::tanh(0.05, 0.0).is_err());
+        assert!(Envelope::polynomial(vec![]).is_err());
+
+        let h = 1e-7;
+        for envelope in [gaussian, tanh, poly] {
+            let psi = 0.043;
+            let fd = (envelope.value(psi + h) - envelope.value(psi - h)) / (2.0 * h);
+            assert!(is_close!(envelope.derivative(psi), fd, rel_tol = 1e-6));
+        }
+    }
+
+    #[test]
+    /// The derivatives match central finite differences of `α`.
+    fn test_harmonics_derivatives() {
+        let perturbation = Harmonics::new(vec![
+            Harmonic::new(3, 2, 1e-3, Envelope::gaussian(0.06, 0.02).unwrap())
+                .with_frequency(0.7)
+                .with_phase(0.3),
+            Harmonic::new(-2, 1, 5e-4, Envelope::tanh(0.08, -0.01).unwrap()).with_frequency(-0.2),
+        ])
+        .unwrap();
+        let mut cache = perturbation.cache();
+        let (psi, theta, zeta, t) = (0.05, 1.2, 2.3, 0.4);
+        let h = 1e-6;
+
+        let mut alpha =
+            |psi, theta, zeta, t| perturbation.alpha(psi, theta, zeta, t, &mut cache).unwrap();
+        let fd = [
+            (alpha(psi + h, theta, zeta, t) - alpha(psi - h, theta, zeta, t)) / (2.0 * h),
+            (alpha(psi, theta + h, zeta, t) - alpha(psi, theta - h, zeta, t)) / (2.0 * h),
+            (alpha(psi, theta, zeta + h, t) - alpha(psi, theta, zeta - h, t)) / (2.0 * h),
+            (alpha(psi, theta, zeta, t + h) - alpha(psi, theta, zeta, t - h)) / (2.0 * h),
+        ];
+        let exact = [
+            perturbation.dalpha_dpsi(psi, theta, zeta, t, &mut cache),
+            perturbation.dalpha_dtheta(psi, theta, zeta, t, &mut cache),
+            perturbation.dalpha_dzeta(psi, theta, zeta, t, &mut cache),
+            perturbation.dalpha_dt(psi, theta, zeta, t, &mut cache),
+        ];
+        for (fd, exact) in fd.into_iter().zip(exact) {
+            assert!(is_close!(
+                exact.unwrap(),
+                fd,
+                rel_tol = 1e-6,
+                abs_tol = 1e-12
+            ));
+        }
+    }
+}
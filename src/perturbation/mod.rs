@@ -0,0 +1,102 @@
+//! Magnetic perturbations `δB = ∇×(αB)`.
+//!
+//! The perturbation is described by the scalar `α(ψ, θ, ζ, t)` of White and Chance, usually a
+//! sum of Fourier harmonics
+//!
+//! ```text
+//! α = Σ α_mn(ψ)⋅cos(mθ − nζ − ω_mn t + φ_mn)
+//! ```
+//!
+//! The radial profiles `α_mn(ψ)` are either analytical ([`Harmonics`]), or interpolated from the
+//! output of an MHD code ([`Numerical`]).
+
+use crate::Result;
+
+mod harmonics;
+mod none;
+mod numerical;
+
+pub use harmonics::{Envelope, Harmonic, Harmonics};
+pub use none::NoPerturbation;
+pub use numerical::{Mode, Numerical, variable_names};
+
+/// Calculation of the perturbation `α(ψ, θ, ζ, t)` and its derivatives.
+pub trait Perturbation {
+    /// The evaluation cache of the perturbation.
+    ///
+    /// Analytical perturbations use [`NoCache`](crate::cache::NoCache), numerical perturbations
+    /// hold the spline accelerators.
+    type Cache: Default;
+
+    /// Creates a new evaluation cache, to be passed to every evaluation method.
+    fn cache(&self) -> Self::Cache {
+        Self::Cache::default()
+    }
+
+    /// Calculates `α(ψ, θ, ζ, t)`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// # use tokamak_equilibria::perturbation::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let envelope = Envelope::gaussian(0.05, 0.01)?;
+    /// let perturbation = Harmonics::new(vec![Harmonic::new(2, 1, 1e-4, envelope)])?;
+    /// let mut cache = perturbation.cache();
+    ///
+    /// let alpha = perturbation.alpha(0.05, 0.0, 0.0, 0.0, &mut cache)?;
+    /// assert_eq!(alpha, 1e-4);
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn alpha(
+        &self,
+        psi: f64,
+        theta: f64,
+        zeta: f64,
+        t: f64,
+        cache: &mut Self::Cache,
+    ) -> Result<f64>;
+
+    /// Calculates `𝜕α/𝜕ψ`.
+    fn dalpha_dpsi(
+        &self,
+        psi: f64,
+        theta: f64,
+        zeta: f64,
+        t: f64,
+        cache: &mut Self::Cache,
+    ) -> Result<f64>;
+
+    /// Calculates `𝜕α/𝜕𝜃`.
+    fn dalpha_dtheta(
+        &self,
+        psi: f64,
+        theta: f64,
+        zeta: f64,
+        t: f64,
+        cache: &mut Self::Cache,
+    ) -> Result<f64>;
+
+    /// Calculates `𝜕α/𝜕𝜁`.
+    fn dalpha_dzeta(
+        &self,
+        psi: f64,
+        theta: f64,
+        zeta: f64,
+        t: f64,
+        cache: &mut Self::Cache,
+    ) -> Result<f64>;
+
+    /// Calculates `𝜕α/𝜕t`.
+    fn dalpha_dt(
+        &self,
+        psi: f64,
+        theta: f64,
+        zeta: f64,
+        t: f64,
+        cache: &mut Self::Cache,
+    ) -> Result<f64>;
+}
@@ -0,0 +1,131 @@
+use crate::Result;
+use crate::cache::NoCache;
+use crate::perturbation::Perturbation;
+
+/// No perturbation, `α = 0`.
+///
+/// The default perturbation of a [`Tokamak`](crate::Tokamak).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoPerturbation;
+
+impl NoPerturbation {
+    /// Creates a new vanishing perturbation.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let perturbation = perturbation::NoPerturbation::new()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new() -> Result<Self> {
+        Ok(Self)
+    }
+}
+
+impl Perturbation for NoPerturbation {
+    type Cache = NoCache;
+
+    /// Always returns `0.0`.
+    #[allow(unused_variables)]
+    fn alpha(
+        &self,
+        psi: f64,
+        theta: f64,
+        zeta: f64,
+        t: f64,
+        cache: &mut Self::Cache,
+    ) -> Result<f64> {
+        Ok(0.0)
+    }
+
+    /// Always returns `0.0`.
+    #[allow(unused_variables)]
+    fn dalpha_dpsi(
+        &self,
+        psi: f64,
+        theta: f64,
+        zeta: f64,
+        t: f64,
+        cache: &mut Self::Cache,
+    ) -> Result<f64> {
+        Ok(0.0)
+    }
+
+    /// Always returns `0.0`.
+    #[allow(unused_variables)]
+    fn dalpha_dtheta(
+        &self,
+        psi: f64,
+        theta: f64,
+        zeta: f64,
+        t: f64,
+        cache: &mut Self::Cache,
+    ) -> Result<f64> {
+        Ok(0.0)
+    }
+
+    /// Always returns `0.0`.
+    #[allow(unused_variables)]
+    fn dalpha_dzeta(
+        &self,
+        psi: f64,
+        theta: f64,
+        zeta: f64,
+        t: f64,
+        cache: &mut Self::Cache,
+    ) -> Result<f64> {
+        Ok(0.0)
+    }
+
+    /// Always returns `0.0`.
+    #[allow(unused_variables)]
+    fn dalpha_dt(
+        &self,
+        psi: f64,
+        theta: f64,
+        zeta: f64,
+        t: f64,
+        cache: &mut Self::Cache,
+    ) -> Result<f64> {
+        Ok(0.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::perturbation::*;
+
+    #[test]
+    fn test_no_perturbation() {
+        let perturbation = NoPerturbation::new().unwrap();
+        let mut c = perturbation.cache();
+
+        assert_eq!(perturbation.alpha(0.1, 1.0, 2.0, 3.0, &mut c).unwrap(), 0.0);
+        assert_eq!(
+            perturbation
+                .dalpha_dpsi(0.1, 1.0, 2.0, 3.0, &mut c)
+                .unwrap(),
+            0.0
+        );
+        assert_eq!(
+            perturbation
+                .dalpha_dtheta(0.1, 1.0, 2.0, 3.0, &mut c)
+                .unwrap(),
+            0.0
+        );
+        assert_eq!(
+            perturbation
+                .dalpha_dzeta(0.1, 1.0, 2.0, 3.0, &mut c)
+                .unwrap(),
+            0.0
+        );
+        assert_eq!(
+            perturbation.dalpha_dt(0.1, 1.0, 2.0, 3.0, &mut c).unwrap(),
+            0.0
+        );
+    }
+}
@@ -0,0 +1,498 @@
+use std::path::PathBuf;
+
+use ndarray::Array2;
+use rsl_interpolation::DynSpline;
+use tokamak_netcdf::Equilibrium;
+
+use crate::Interp1d;
+use crate::cache::PsiCache;
+use crate::current::Current;
+use crate::dataset;
+use crate::perturbation::Perturbation;
+use crate::qfactor::Qfactor;
+use crate::{EqError, Result};
+
+/// The names of the variables of a perturbation dataset.
+pub mod variable_names {
+    /// The ψ coordinate of the radial profiles.
+    pub const PSI_COORD: &str = tokamak_netcdf::variable_names::PSI_COORD;
+    /// The poloidal mode numbers, over the `mode` dimension.
+    pub const M_NUMBER: &str = "m";
+    /// The toroidal mode numbers, over the `mode` dimension.
+    pub const N_NUMBER: &str = "n";
+    /// The cosine components of `α_mn(ψ)`, of shape `[mode, ψ]`.
+    pub const ALPHA_COS: &str = "alpha_cos";
+    /// The sine components of `α_mn(ψ)`, of shape `[mode, ψ]`.
+    pub const ALPHA_SIN: &str = "alpha_sin";
+    /// The cosine components of the displacement `ξ_mn(ψ) = ξ⋅∇𝜓ₚ`, of shape `[mode, ψ]`.
+    pub const XI_COS: &str = "xi_cos";
+    /// The sine components of the displacement `ξ_mn(ψ) = ξ⋅∇𝜓ₚ`, of shape `[mode, ψ]`.
+    pub const XI_SIN: &str = "xi_sin";
+}
+
+/// A single mode `A⋅(αᶜ(ψ)⋅cos χ + αˢ(ψ)⋅sin χ)` of a [`Numerical`] perturbation, with
+/// `χ = mθ − nζ − ωt`.
+pub struct Mode {
+    /// The poloidal mode number m.
+    pub m: i32,
+    /// The toroidal mode number n.
+    pub n: i32,
+    /// Spline over the cosine component `αᶜ`.
+    pub cos_spline: DynSpline<f64>,
+    /// Spline over the sine component `αˢ`.
+    pub sin_spline: DynSpline<f64>,
+    /// The amplitude scaling `A`. Defaults to 1.
+    pub amplitude: f64,
+    /// The angular frequency ω. Defaults to 0.
+    pub omega: f64,
+}
+
+/// Magnetic perturbation reconstructed from the radial profiles of its modes, usually computed by
+/// an MHD code.
+///
+/// The dataset contains the ψ coordinate, the mode numbers `m` and `n`, and the cosine and sine
+/// components of either `α_mn(ψ)` or the displacement `ξ_mn(ψ)`, with the names of
+/// [`variable_names`](crate::perturbation::variable_names).
+pub struct Numerical {
+    /// The modes of the perturbation.
+    pub modes: Vec<Mode>,
+}
+
+impl Numerical {
+    /// Constructs a [`Perturbation`] from the `α_mn` profiles of a netCDF file at `path`, with
+    /// splines of `typ` interpolation type.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use tokamak_equilibria::*;
+    /// # use std::path::PathBuf;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from("./modes.nc");
+    /// let mut perturbation = perturbation::Numerical::from_dataset(&path, Interp1d::Cubic)?;
+    /// if let Some(mode) = perturbation.mode_mut(2, 1) {
+    ///     mode.amplitude = 1e-4;
+    ///     mode.omega = 2e-5;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_dataset(path: &PathBuf, typ: Interp1d) -> Result<Self> {
+        Self::from_equilibrium(&Equilibrium::from_file(path)?, typ)
+    }
+
+    /// Constructs a [`Perturbation`] from the `α_mn` profiles of an already opened netCDF
+    /// [`Equilibrium`], with splines of `typ` interpolation type.
+    pub fn from_equilibrium(eq: &Equilibrium, typ: Interp1d) -> Result<Self> {
+        use variable_names::*;
+
+        let (psi_data, m, n) = Self::extract_modes(eq)?;
+        let cos_data = eq.get_2d(ALPHA_COS)?;
+        let sin_data = eq.get_2d(ALPHA_SIN)?;
+        Self::from_arrays(&psi_data, &m, &n, &cos_data, &sin_data, typ)
+    }
+
+    /// Constructs a [`Perturbation`] from the displacement profiles `ξ_mn = ξ⋅∇𝜓ₚ` of an already
+    /// opened netCDF [`Equilibrium`], with splines of `typ` interpolation type.
+    ///
+    /// The displacements are converted with `α_mn = (m − nq)⋅ξ_mn/(mg + nI)`, which gives the
+    /// same radial field `δB⋅∇𝜓ₚ` as the ideal displacement, with the `qfactor` and `current` of
+    /// the equilibrium.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use tokamak_equilibria::*;
+    /// # use std::path::PathBuf;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let eq = Equilibrium::from_file(&PathBuf::from("./data.nc"))?;
+    /// let qfactor = qfactor::Numerical::from_equilibrium(&eq, Interp1d::Cubic)?;
+    /// let current = current::Numerical::from_equilibrium(&eq, Interp1d::Cubic)?;
+    ///
+    /// let modes = Equilibrium::from_file(&PathBuf::from("./modes.nc"))?;
+    /// let perturbation =
+    ///     perturbation::Numerical::from_displacement(&modes, &qfactor, &current, Interp1d::Cubic)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_displacement<Q, C>(
+        eq: &Equilibrium,
+        qfactor: &Q,
+        current: &C,
+        typ: Interp1d,
+    ) -> Result<Self>
+    where
+        Q: Qfactor,
+        C: Current,
+    {
+        use variable_names::*;
+
+        let (psi_data, m, n) = Self::extract_modes(eq)?;
+        let mut cos_data = eq.get_2d(XI_COS)?;
+        let mut sin_data = eq.get_2d(XI_SIN)?;
+
+        let (mut qc, mut cc) = (qfactor.cache(), current.cache());
+        for (k, psi) in psi_data.iter().enumerate() {
+            let q = qfactor.q(*psi, &mut qc)?;
+            let g = current.g(*psi, &mut cc)?;
+            let i = current.i(*psi, &mut cc)?;
+            for (mode, (m, n)) in m.iter().zip(&n).enumerate() {
+                let (m, n) = (f64::from(*m), f64::from(*n));
+                let factor = (m - n * q) / (m * g + n * i);
+                if !factor.is_finite() {
+                    return Err(EqError::InvalidData(format!(
+                        "no α for the displacement of mode m = {m}, n = {n}"
+                    )));
+                }
+                cos_data[[mode, k]] *= factor;
+                sin_data[[mode, k]] *= factor;
+            }
+        }
+        Self::from_arrays(&psi_data, &m, &n, &cos_data, &sin_data, typ)
+    }
+
+    /// Extracts the ψ coordinate and the mode numbers.
+    fn extract_modes(eq: &Equilibrium) -> Result<(Vec<f64>, Vec<i32>, Vec<i32>)> {
+        use variable_names::*;
+
+        let psi_data = eq.get_1d(PSI_COORD)?.to_vec();
+        let mode_numbers = |name: &str| -> Result<Vec<i32>> {
+            eq.get_1d(name)?
+                .iter()
+                .map(|x| {
+                    if x.fract() == 0.0 && x.abs() <= f64::from(i32::MAX) {
+                        Ok(*x as i32)
+                    } else {
+                        Err(EqError::InvalidData(format!(
+                            "{name} has non-integer value {x}"
+                        )))
+                    }
+                })
+                .collect()
+        };
+        Ok((psi_data, mode_numbers(M_NUMBER)?, mode_numbers(N_NUMBER)?))
+    }
+
+    /// Constructs a [`Perturbation`] from the ψ data array, the mode numbers `m` and `n`, and the
+    /// cosine and sine components of `α_mn`, with splines of `typ` interpolation type.
+    ///
+    /// `psi_data` must be strictly increasing, `m` and `n` must have the same length, and
+    /// `cos_data` and `sin_data` must have the shape `[mode, ψ]`. All values must be finite.
+    ///
+    /// # Example
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// # use ndarray::Array2;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let psi_data = [0.0, 0.025, 0.05, 0.075, 0.1];
+    /// let cos_data = Array2::from_shape_vec((1, 5), vec![0.0, 0.6, 1.0, 0.6, 0.0]).unwrap();
+    /// let sin_data = Array2::zeros((1, 5));
+    /// let perturbation = perturbation::Numerical::from_arrays(
+    ///     &psi_data, &[2], &[1], &cos_data, &sin_data, Interp1d::Cubic,
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_arrays(
+        psi_data: &[f64],
+        m: &[i32],
+        n: &[i32],
+        cos_data: &Array2<f64>,
+        sin_data: &Array2<f64>,
+        typ: Interp1d,
+    ) -> Result<Self> {
+        dataset::validate_grid("ψ", psi_data)?;
+        if m.len() != n.len() {
+            return Err(EqError::InvalidData(format!(
+                "{} poloidal and {} toroidal mode numbers",
+                m.len(),
+                n.len()
+            )));
+        }
+        let shape = (m.len(), psi_data.len());
+        for (name, data) in [("cos", cos_data), ("sin", sin_data)] {
+            if data.dim() != shape {
+                return Err(EqError::InvalidData(format!(
+                    "{name} components have shape {:?}, expected {shape:?}",
+                    data.dim()
+                )));
+            }
+        }
+
+        use rsl_interpolation::*;
+
+        let mut modes = Vec::with_capacity(m.len());
+        for (k, (m, n)) in m.iter().zip(n).enumerate() {
+            let cos = cos_data.row(k).to_vec();
+            let sin = sin_data.row(k).to_vec();
+            dataset::validate_values(&format!("αᶜ({m}, {n})"), &cos, psi_data.len())?;
+            dataset::validate_values(&format!("αˢ({m}, {n})"), &sin, psi_data.len())?;
+            modes.push(Mode {
+                m: *m,
+                n: *n,
+                cos_spline: make_spline(typ.name(), psi_data, &cos)?,
+                sin_spline: make_spline(typ.name(), psi_data, &sin)?,
+                amplitude: 1.0,
+                omega: 0.0,
+            });
+        }
+        Ok(Self { modes })
+    }
+
+    /// Returns the mode with mode numbers `m` and `n`, if present.
+    pub fn mode_mut(&mut self, m: i32, n: i32) -> Option<&mut Mode> {
+        self.modes
+            .iter_mut()
+            .find(|mode| mode.m == m && mode.n == n)
+    }
+
+    /// Sums `A⋅f(mode, αᶜ, αˢ)` over all modes, with the components or, if `deriv`, their ψ
+    /// derivatives evaluated at `psi`.
+    fn sum<F>(&self, psi: f64, cache: &mut PsiCache, deriv: bool, f: F) -> Result<f64>
+    where
+        F: Fn(&Mode, f64, f64) -> f64,
+    {
+        let mut sum = 0.0;
+        for mode in self.modes.iter() {
+            let acc = &mut cache.psi_acc;
+            let (cos, sin) = if deriv {
+                (
+                    mode.cos_spline.eval_deriv(psi, acc)?,
+                    mode.sin_spline.eval_deriv(psi, acc)?,
+                )
+            } else {
+                (
+                    mode.cos_spline.eval(psi, acc)?,
+                    mode.sin_spline.eval(psi, acc)?,
+                )
+            };
+            sum += mode.amplitude * f(mode, cos, sin);
+        }
+        Ok(sum)
+    }
+}
+
+impl Mode {
+    /// The phase `χ = mθ − nζ − ωt`.
+    fn chi(&self, theta: f64, zeta: f64, t: f64) -> f64 {
+        f64::from(self.m) * theta - f64::from(self.n) * zeta - self.omega * t
+    }
+
+    /// `αᶜ⋅cos χ + αˢ⋅sin χ`.
+    fn value(&self, cos: f64, sin: f64, theta: f64, zeta: f64, t: f64) -> f64 {
+        let chi = self.chi(theta, zeta, t);
+        cos * chi.cos() + sin * chi.sin()
+    }
+
+    /// `𝜕/𝜕χ` of `αᶜ⋅cos χ + αˢ⋅sin χ`.
+    fn dchi(&self, cos: f64, sin: f64, theta: f64, zeta: f64, t: f64) -> f64 {
+        let chi = self.chi(theta, zeta, t);
+        sin * chi.cos() - cos * chi.sin()
+    }
+}
+
+impl Perturbation for Numerical {
+    type Cache = PsiCache;
+
+    fn alpha(
+        &self,
+        psi: f64,
+        theta: f64,
+        zeta: f64,
+        t: f64,
+        cache: &mut Self::Cache,
+    ) -> Result<f64> {
+        self.sum(psi, cache, false, |mode, cos, sin| {
+            mode.value(cos, sin, theta, zeta, t)
+        })
+    }
+
+    fn dalpha_dpsi(
+        &self,
+        psi: f64,
+        theta: f64,
+        zeta: f64,
+        t: f64,
+        cache: &mut Self::Cache,
+    ) -> Result<f64> {
+        self.sum(psi, cache, true, |mode, cos, sin| {
+            mode.value(cos, sin, theta, zeta, t)
+        })
+    }
+
+    fn dalpha_dtheta(
+        &self,
+        psi: f64,
+        theta: f64,
+        zeta: f64,
+        t: f64,
+        cache: &mut Self::Cache,
+    ) -> Result<f64> {
+        self.sum(psi, cache, false, |mode, cos, sin| {
+            f64::from(mode.m) * mode.dchi(cos, sin, theta, zeta, t)
+        })
+    }
+
+    fn dalpha_dzeta(
+        &self,
+        psi: f64,
+        theta: f64,
+        zeta: f64,
+        t: f64,
+        cache: &mut Self::Cache,
+    ) -> Result<f64> {
+        self.sum(psi, cache, false, |mode, cos, sin| {
+            -f64::from(mode.n) * mode.dchi(cos, sin, theta, zeta, t)
+        })
+    }
+
+    fn dalpha_dt(
+        &self,
+        psi: f64,
+        theta: f64,
+        zeta: f64,
+        t: f64,
+        cache: &mut Self::Cache,
+    ) -> Result<f64> {
+        self.sum(psi, cache, false, |mode, cos, sin| {
+            -mode.omega * mode.dchi(cos, sin, theta, zeta, t)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use is_close::is_close;
+    use ndarray::Array2;
+
+    use crate::perturbation::numerical::variable_names::*;
+    use crate::perturbation::*;
+    use crate::testing::{TempFile, parabolic};
+    use crate::*;
+
+    /// Radial profiles of the 2/1 and 3/2 modes, with both components.
+    fn profiles(psi_data: &[f64]) -> (Array2<f64>, Array2<f64>) {
+        let cos = Array2::from_shape_fn((2, psi_data.len()), |(k, j)| {
+            let psi = psi_data[j];
+            [psi * (0.1 - psi), psi.powi(2)][k]
+        });
+        let sin = Array2::from_shape_fn((2, psi_data.len()), |(k, j)| {
+            let psi = psi_data[j];
+            [0.5 * psi, psi * (0.1 - psi).powi(2)][k]
+        });
+        (cos, sin)
+    }
+
+    fn write(path: &Path, psi: &[f64], cos: &Array2<f64>, sin: &Array2<f64>, names: [&str; 2]) {
+        let mut file = netcdf::create(path).unwrap();
+        file.add_dimension(PSI_COORD, psi.len()).unwrap();
+        file.add_dimension("mode", 2).unwrap();
+        let mut put = |name: &str, dims: &[&str], values: &[f64]| {
+            file.add_variable::<f64>(name, dims)
+                .unwrap()
+                .put_values(values, ..)
+                .unwrap();
+        };
+        put(PSI_COORD, &[PSI_COORD], psi);
+        put(M_NUMBER, &["mode"], &[2.0, 3.0]);
+        put(N_NUMBER, &["mode"], &[1.0, 2.0]);
+        put(names[0], &["mode", PSI_COORD], &cos.flatten().to_vec());
+        put(names[1], &["mode", PSI_COORD], &sin.flatten().to_vec());
+    }
+
+    #[test]
+    fn test_dataset() {
+        let psi_data: Vec<f64> = (0..=50).map(|k| 0.1 * k as f64 / 50.0).collect();
+        let (cos, sin) = profiles(&psi_data);
+        let file = TempFile::new("perturbation_dataset");
+        write(&file.path, &psi_data, &cos, &sin, [ALPHA_COS, ALPHA_SIN]);
+        let mut perturbation = Numerical::from_dataset(&file.path, Interp1d::Cubic).unwrap();
+
+        let mode = perturbation.mode_mut(3, 2).unwrap();
+        mode.amplitude = 2.0;
+        mode.omega = 0.5;
+        let mut cache = perturbation.cache();
+
+        let (psi, theta, zeta, t): (f64, f64, f64, f64) = (0.037, 1.2, 2.3, 0.4);
+        let chi21 = 2.0 * theta - zeta;
+        let chi32 = 3.0 * theta - 2.0 * zeta - 0.5 * t;
+        let expected = psi * (0.1 - psi) * chi21.cos()
+            + 0.5 * psi * chi21.sin()
+            + 2.0 * (psi.powi(2) * chi32.cos() + psi * (0.1 - psi).powi(2) * chi32.sin());
+        let alpha = perturbation.alpha(psi, theta, zeta, t, &mut cache).unwrap();
+        assert!(is_close!(alpha, expected, rel_tol = 1e-6));
+
+        let h = 1e-6;
+        let mut alpha =
+            |psi, theta, zeta, t| perturbation.alpha(psi, theta, zeta, t, &mut cache).unwrap();
+        let fd = [
+            (alpha(psi + h, theta, zeta, t) - alpha(psi - h, theta, zeta, t)) / (2.0 * h),
+            (alpha(psi, theta + h, zeta, t) - alpha(psi, theta - h, zeta, t)) / (2.0 * h),
+            (alpha(psi, theta, zeta + h, t) - alpha(psi, theta, zeta - h, t)) / (2.0 * h),
+            (alpha(psi, theta, zeta, t + h) - alpha(psi, theta, zeta, t - h)) / (2.0 * h),
+        ];
+        let exact = [
+            perturbation.dalpha_dpsi(psi, theta, zeta, t, &mut cache),
+            perturbation.dalpha_dtheta(psi, theta, zeta, t, &mut cache),
+            perturbation.dalpha_dzeta(psi, theta, zeta, t, &mut cache),
+            perturbation.dalpha_dt(psi, theta, zeta, t, &mut cache),
+        ];
+        for (fd, exact) in fd.into_iter().zip(exact) {
+            assert!(is_close!(
+                exact.unwrap(),
+                fd,
+                rel_tol = 1e-5,
+                abs_tol = 1e-12
+            ));
+        }
+    }
+
+    #[test]
+    /// With `g = 1` and `I = 0`, `α_mn = (1 − nq/m)⋅ξ_mn`.
+    fn test_displacement() {
+        let qfactor = parabolic();
+        let current = current::Lar::new().unwrap();
+        let psi_data: Vec<f64> = (0..=50).map(|k| 0.1 * k as f64 / 50.0).collect();
+        let (cos, sin) = profiles(&psi_data);
+        let file = TempFile::new("perturbation_displacement");
+        write(&file.path, &psi_data, &cos, &sin, [XI_COS, XI_SIN]);
+        let eq = Equilibrium::from_file(&file.path).unwrap();
+        let perturbation =
+            Numerical::from_displacement(&eq, &qfactor, &current, Interp1d::Cubic).unwrap();
+        let mut cache = perturbation.cache();
+
+        let psi: f64 = 0.037;
+        let q = qfactor.q(psi, &mut qfactor.cache()).unwrap();
+        let expected = (1.0 - q / 2.0) * psi * (0.1 - psi) + (1.0 - 2.0 * q / 3.0) * psi.powi(2);
+        let alpha = perturbation.alpha(psi, 0.0, 0.0, 0.0, &mut cache).unwrap();
+        assert!(is_close!(alpha, expected, rel_tol = 1e-5));
+    }
+
+    #[test]
+    fn test_from_arrays_validation() {
+        let psi_data = [0.0, 0.025, 0.05, 0.075, 0.1];
+        let (cos, sin) = profiles(&psi_data);
+        let ok = Numerical::from_arrays(&psi_data, &[2, 3], &[1, 2], &cos, &sin, Interp1d::Cubic);
+        assert!(ok.is_ok());
+
+        let err = Numerical::from_arrays(&psi_data, &[2], &[1, 2], &cos, &sin, Interp1d::Cubic);
+        assert!(matches!(err, Err(EqError::InvalidData(_))));
+        let transposed = cos.t().to_owned();
+        let err = Numerical::from_arrays(
+            &psi_data,
+            &[2, 3],
+            &[1, 2],
+            &transposed,
+            &sin,
+            Interp1d::Cubic,
+        );
+        assert!(matches!(err, Err(EqError::InvalidData(_))));
+        let mut nan = sin.clone();
+        nan[[1, 3]] = f64::NAN;
+        let err = Numerical::from_arrays(&psi_data, &[2, 3], &[1, 2], &cos, &nan, Interp1d::Cubic);
+        assert!(matches!(err, Err(EqError::InvalidData(_))));
+    }
+}
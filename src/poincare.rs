@@ -0,0 +1,390 @@
+//! Poincaré maps of guiding-centre orbits.
+//!
+//! The orbits are integrated in time, and every time a step crosses the surface of section, the
+//! crossing step is replaced by Hénon's method: the section's angle becomes the independent
+//! variable, so that the integration lands exactly on the section, without interpolation. The
+//! Hénon integration uses the same error control as the orbit, and is split into several steps
+//! where the angle moves slowly.
+
+use std::f64::consts::TAU;
+
+use ndarray::Array2;
+
+use crate::bfield::Bfield3d;
+use crate::current::Current;
+use crate::efield::Efield;
+use crate::gc::{self, Constants, State};
+use crate::particle::{IntegrationConfig, Particle, Termination};
+use crate::perturbation::Perturbation;
+use crate::qfactor::Qfactor;
+use crate::solver::Stepper;
+use crate::{EqError, Result, Tokamak, TokamakCache};
+
+/// The surface of section.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Section {
+    /// The surface `ζ = ζ₀` (modulo 2π). The intersections are stored as `[θ, 𝜓ₚ]`.
+    Zeta(f64),
+    /// The surface `θ = θ₀` (modulo 2π). The intersections are stored as `[ζ, 𝜓ₚ]`.
+    Theta(f64),
+}
+
+impl Section {
+    /// The index of the section's angle in the [`State`].
+    fn index(&self) -> usize {
+        match self {
+            Section::Zeta(_) => 3,
+            Section::Theta(_) => 0,
+        }
+    }
+
+    /// The index of the stored angle in the [`State`].
+    fn other(&self) -> usize {
+        match self {
+            Section::Zeta(_) => 0,
+            Section::Theta(_) => 3,
+        }
+    }
+
+    /// The section's angle.
+    fn angle(&self) -> f64 {
+        match self {
+            Section::Zeta(angle) | Section::Theta(angle) => *angle,
+        }
+    }
+}
+
+/// Configuration of the Poincaré map.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoincareConfig {
+    /// The surface of section.
+    pub section: Section,
+    /// The number of intersections to collect for every particle.
+    pub intersections: usize,
+    /// The configuration of the orbit integration. Its `max_transits` is ignored.
+    pub integration: IntegrationConfig,
+}
+
+impl PoincareConfig {
+    /// Creates a new configuration, with the default [`IntegrationConfig`].
+    pub fn new(section: Section, intersections: usize) -> Self {
+        Self {
+            section,
+            intersections,
+            integration: IntegrationConfig::default(),
+        }
+    }
+}
+
+/// Calculates the Poincaré map of every particle in `particles`.
+///
+/// Returns the intersections of each particle, in the same order. See [`intersections`].
+///
+/// # Example
+///
+/// ```
+/// # use tokamak_equilibria::*;
+/// # use tokamak_equilibria::particle::*;
+/// # use tokamak_equilibria::poincare::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125)?;
+/// let bfield = bfield::Lar::new()?;
+/// let current = current::Lar::new()?;
+/// let efield = efield::NoEfield::new()?;
+/// let eq = Tokamak::build(qfactor, bfield, current, efield)?;
+///
+/// let consts = gc::Constants::new(1e-7, 1.0, 1.0);
+/// let mut particles: Vec<Particle> = [0.01, 0.015, 0.02]
+///     .iter()
+///     .map(|psip| Particle::new([0.0, *psip, 1e-3, 0.0], consts))
+///     .collect();
+///
+/// let config = PoincareConfig::new(Section::Zeta(0.0), 10);
+/// let maps = map(&eq, &mut particles, &config)?;
+/// assert_eq!(maps.len(), 3);
+/// assert_eq!(maps[0].dim(), (10, 2));
+/// # Ok(())
+/// # }
+/// ```
+pub fn map<Q, B, C, E, P>(
+    tokamak: &Tokamak<Q, B, C, E, P>,
+    particles: &mut [Particle],
+    config: &PoincareConfig,
+) -> Result<Vec<Array2<f64>>>
+where
+    Q: Qfactor,
+    B: Bfield3d,
+    C: Current,
+    E: Efield,
+    P: Perturbation,
+{
+    particles
+        .iter_mut()
+        .map(|particle| intersections(tokamak, particle, config))
+        .collect()
+}
+
+/// Calculates the intersections of `particle`'s orbit with the surface of section.
+///
+/// Only crossings in the direction of the initial motion of the section's angle are recorded, and
+/// starting exactly on the section does not count as one. Returns an array of shape `(n, 2)`, where
+/// each row is the other angle (modulo 2π) and `𝜓ₚ` at a crossing. Fewer than
+/// `config.intersections` rows are returned if another stop condition is met first, which is
+/// reported in the particle's `termination`.
+///
+/// The particle's state and time are advanced, but its evolution is not recorded.
+pub fn intersections<Q, B, C, E, P>(
+    tokamak: &Tokamak<Q, B, C, E, P>,
+    particle: &mut Particle,
+    config: &PoincareConfig,
+) -> Result<Array2<f64>>
+where
+    Q: Qfactor,
+    B: Bfield3d,
+    C: Current,
+    E: Efield,
+    P: Perturbation,
+{
+    let mut cache = tokamak.cache();
+    let mut points = Vec::with_capacity(config.intersections);
+    let psip_wall = config
+        .integration
+        .psip_wall(&tokamak.qfactor, &mut cache.qfactor)?;
+    match trace(
+        tokamak,
+        particle,
+        config,
+        psip_wall,
+        &mut cache,
+        &mut points,
+    ) {
+        Err(EqError::DomainError(_)) => particle.termination = Some(Termination::Escaped),
+        res => res?,
+    }
+    Ok(Array2::from_shape_fn((points.len(), 2), |(i, j)| {
+        points[i][j]
+    }))
+}
+
+fn trace<Q, B, C, E, P>(
+    tokamak: &Tokamak<Q, B, C, E, P>,
+    particle: &mut Particle,
+    config: &PoincareConfig,
+    psip_wall: f64,
+    cache: &mut TokamakCache<Q, B, C, E, P>,
+    points: &mut Vec<[f64; 2]>,
+) -> Result<()>
+where
+    Q: Qfactor,
+    B: Bfield3d,
+    C: Current,
+    E: Efield,
+    P: Perturbation,
+{
+    let integration = &config.integration;
+    let consts = particle.constants;
+    let (k, other) = (config.section.index(), config.section.other());
+    let angle0 = config.section.angle();
+    let direction = gc::rhs(tokamak, particle.t, &particle.state, &consts, cache)?[k].signum();
+
+    let mut stepper = Stepper::new(integration.first_step, integration.rtol, integration.atol);
+    let mut steps = 0;
+    loop {
+        if points.len() >= config.intersections {
+            particle.termination = Some(Termination::Intersections);
+            return Ok(());
+        }
+        if particle.t >= integration.t_max {
+            particle.termination = Some(Termination::TimeLimit);
+            return Ok(());
+        }
+        if steps >= integration.max_steps {
+            particle.termination = Some(Termination::MaxSteps);
+            return Ok(());
+        }
+
+        let mut f = |t: f64, y: &State| gc::rhs(tokamak, t, y, &consts, cache);
+        let (h, y_new) = stepper.step(&mut f, particle.t, &particle.state, integration.t_max)?;
+        steps += 1;
+        let target = next_section(particle.state[k], angle0, direction);
+        if (y_new[k] - target) * direction >= 0.0 {
+            let z = henon_step(tokamak, particle, &consts, integration, k, target, cache)?;
+            particle.state = [z[0], z[1], z[2], z[3]];
+            particle.state[k] = target;
+            particle.t = z[4];
+            points.push([particle.state[other].rem_euclid(TAU), particle.state[1]]);
+        } else {
+            particle.state = y_new;
+            particle.t += h;
+        }
+
+        if particle.state[1] >= psip_wall {
+            particle.termination = Some(Termination::WallHit);
+            return Ok(());
+        }
+    }
+}
+
+/// The first section value `θ₀ + 2πn` strictly after `from`, in `direction`.
+fn next_section(from: f64, angle0: f64, direction: f64) -> f64 {
+    let turns = (from - angle0) / TAU;
+    if direction > 0.0 {
+        let target = angle0 + TAU * (turns.floor() + 1.0);
+        // Rounding may place a state that lies exactly on the section just before it.
+        if target <= from { target + TAU } else { target }
+    } else {
+        let target = angle0 + TAU * (turns.ceil() - 1.0);
+        if target >= from { target - TAU } else { target }
+    }
+}
+
+/// Integrates the particle's state onto the section value `target` of the state's `k`-th angle,
+/// with Hénon's method.
+///
+/// The angle becomes the independent variable of the extended system `[θ, 𝜓ₚ, 𝜌∥, ζ, t]`, whose
+/// derivatives are divided by the angle's time derivative. The extended system is stiff where that
+/// rate is small, such as near the bounce point of a trapped orbit, so it is integrated with the
+/// error control of `integration`, splitting the step when needed. Returns the extended state on
+/// the section.
+fn henon_step<Q, B, C, E, P>(
+    tokamak: &Tokamak<Q, B, C, E, P>,
+    particle: &Particle,
+    consts: &Constants,
+    integration: &IntegrationConfig,
+    k: usize,
+    target: f64,
+    cache: &mut TokamakCache<Q, B, C, E, P>,
+) -> Result<[f64; 5]>
+where
+    Q: Qfactor,
+    B: Bfield3d,
+    C: Current,
+    E: Efield,
+    P: Perturbation,
+{
+    let [theta, psip, rho, zeta] = particle.state;
+    let mut z = [theta, psip, rho, zeta, particle.t];
+    // The independent variable is the distance `s` travelled by the angle towards the target.
+    let sign = (target - z[k]).signum();
+    let span = (target - z[k]).abs();
+    let mut g = |_: f64, z: &[f64; 5]| -> Result<[f64; 5]> {
+        let f = gc::rhs(tokamak, z[4], &[z[0], z[1], z[2], z[3]], consts, cache)?;
+        let rate = sign * f[k];
+        Ok([
+            f[0] / rate,
+            f[1] / rate,
+            f[2] / rate,
+            f[3] / rate,
+            1.0 / rate,
+        ])
+    };
+
+    let mut stepper = Stepper::new(span, integration.rtol, integration.atol);
+    let mut s = 0.0;
+    while s < span {
+        let (h, z_new) = stepper.step(&mut g, s, &z, span)?;
+        s += h;
+        z = z_new;
+    }
+    Ok(z)
+}
+
+#[cfg(test)]
+mod test {
+    use is_close::is_close;
+
+    use crate::poincare::*;
+    use crate::testing::lar_tokamak;
+
+    #[test]
+    fn test_next_section() {
+        assert!(is_close!(next_section(0.1, 0.2, 1.0), 0.2));
+        assert!(is_close!(next_section(0.3, 0.2, 1.0), 0.2 + TAU));
+        assert!(is_close!(next_section(0.3, 0.2, -1.0), 0.2));
+        assert!(is_close!(next_section(0.1, 0.2, -1.0), 0.2 - TAU));
+        // Starting on the section.
+        assert!(is_close!(next_section(0.0, 0.0, 1.0), TAU));
+        assert!(is_close!(next_section(0.0, 0.0, -1.0), -TAU));
+    }
+
+    #[test]
+    /// In an axisymmetric equilibrium, an orbit returns to the same `𝜓ₚ` at the same θ, so every
+    /// intersection with a θ-section through the initial point is the initial point itself.
+    fn test_theta_section_closed_orbits() {
+        let eq = lar_tokamak();
+        let config = PoincareConfig::new(Section::Theta(0.0), 5);
+        for mu in [1e-7, 1e-5] {
+            let consts = gc::Constants::new(mu, 1.0, 1.0);
+            let mut particle = Particle::new([0.0, 0.02, 1e-3, 0.0], consts);
+            let points = intersections(&eq, &mut particle, &config).unwrap();
+            assert_eq!(particle.termination, Some(Termination::Intersections));
+            assert_eq!(points.nrows(), 5);
+            for psip in points.column(1) {
+                assert!(is_close!(*psip, 0.02, rel_tol = 1e-8));
+            }
+        }
+    }
+
+    #[test]
+    /// The rate of θ vanishes at the bounce point of a trapped orbit, so a single Hénon step onto a
+    /// section close to it is inaccurate, and must be split.
+    fn test_henon_step_near_bounce() {
+        let eq = lar_tokamak();
+        let mut cache = eq.cache();
+        let consts = gc::Constants::new(1e-5, 1.0, 1.0);
+        let integration = IntegrationConfig {
+            max_transits: Some(1),
+            ..Default::default()
+        };
+        let mut bounce = Particle::new([0.0, 0.02, 1e-3, 0.0], consts);
+        bounce.integrate(&eq, &integration).unwrap();
+        let theta_max = bounce.evolution.theta.iter().copied().fold(0.0, f64::max);
+
+        let particle = Particle::new([0.0, 0.02, 1e-3, 0.0], consts);
+        let target = 0.999 * theta_max;
+        let z = henon_step(&eq, &particle, &consts, &integration, 0, target, &mut cache).unwrap();
+        let state = [target, z[1], z[2], z[3]];
+        let e0 = gc::energy(&eq, &particle.initial, &consts, &mut cache).unwrap();
+        let e1 = gc::energy(&eq, &state, &consts, &mut cache).unwrap();
+        assert!(is_close!(e0, e1, rel_tol = 1e-9));
+        assert!(is_close!(z[0], target, rel_tol = 1e-9));
+    }
+
+    #[test]
+    fn test_zeta_section() {
+        let eq = lar_tokamak();
+        let consts = gc::Constants::new(1e-7, 1.0, 1.0);
+        let mut particle = Particle::new([0.0, 0.02, 1e-3, 0.5], consts);
+        let config = PoincareConfig::new(Section::Zeta(1.0), 20);
+        let points = intersections(&eq, &mut particle, &config).unwrap();
+        assert_eq!(points.dim(), (20, 2));
+        // The particle stopped exactly on the section.
+        assert!(is_close!(particle.state[3].rem_euclid(TAU), 1.0));
+        assert!(
+            points
+                .column(0)
+                .iter()
+                .all(|theta| (0.0..TAU).contains(theta))
+        );
+        // Energy is conserved across the Hénon steps.
+        let mut cache = eq.cache();
+        let e0 = gc::energy(&eq, &particle.initial, &consts, &mut cache).unwrap();
+        let e1 = gc::energy(&eq, &particle.state, &consts, &mut cache).unwrap();
+        assert!(is_close!(e0, e1, rel_tol = 1e-7));
+    }
+
+    #[test]
+    fn test_stop_conditions() {
+        let eq = lar_tokamak();
+        let consts = gc::Constants::new(1e-7, 1.0, 1.0);
+        let mut particles = vec![Particle::new([0.0, 0.02, 1e-3, 0.0], consts); 2];
+        let mut config = PoincareConfig::new(Section::Zeta(0.0), 1000);
+        config.integration.t_max = 100.0;
+        let maps = map(&eq, &mut particles, &config).unwrap();
+        assert_eq!(maps.len(), 2);
+        assert!(maps[0].nrows() < 1000);
+        assert_eq!(maps[0], maps[1]);
+        assert_eq!(particles[0].termination, Some(Termination::TimeLimit));
+    }
+}
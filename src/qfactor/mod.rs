@@ -2,35 +2,63 @@
 
 use rsl_interpolation::Accelerator;
 
-use crate::Result;
+use crate::{EqError, Result};
 
 mod numerical;
 mod parabolic;
+mod power_law;
+mod rational;
+mod reversed_shear;
 mod unity;
 
 pub use numerical::Numerical;
 pub use parabolic::Parabolic;
+pub use power_law::PowerLaw;
+pub use rational::{RationalSurface, rational_surfaces};
+pub use reversed_shear::ReversedShear;
 pub use unity::Unity;
 
 /// Calculation of q-factor related quantities.
 pub trait Qfactor {
+    /// The evaluation cache of the q-factor profile.
+    ///
+    /// Analytical profiles use [`NoCache`](crate::cache::NoCache), numerical profiles hold the
+    /// spline accelerators.
+    type Cache: Default;
+
+    /// Creates a new evaluation cache, to be passed to every evaluation method.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125)?;
+    /// let mut cache = qfactor.cache();
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn cache(&self) -> Self::Cache {
+        Self::Cache::default()
+    }
+
     /// Calculates the q-factor `q(ψ)`.
     ///
     /// # Example
     ///
     /// ```
     /// # use tokamak_equilibria::*;
-    /// # use rsl_interpolation::*;
     /// #
     /// # fn main() -> Result<()> {
-    /// let mut acc = Accelerator::new();
     /// let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125)?;
+    /// let mut cache = qfactor.cache();
     ///
-    /// let q =  qfactor.q(0.015, &mut acc)?;
+    /// let q =  qfactor.q(0.015, &mut cache)?;
     /// # Ok(())
     /// # }
     /// ```
-    fn q(&self, psi: f64, acc: &mut Accelerator) -> Result<f64>;
+    fn q(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64>;
 
     /// Calculates the poloidal flux `𝜓ₚ(𝜓)`.
     ///
@@ -38,15 +66,164 @@ pub trait Qfactor {
     ///
     /// ```
     /// # use tokamak_equilibria::*;
-    /// # use rsl_interpolation::*;
     /// #
     /// # fn main() -> Result<()> {
-    /// let mut acc = Accelerator::new();
     /// let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125)?;
+    /// let mut cache = qfactor.cache();
+    ///
+    /// let q =  qfactor.psip(0.015, &mut cache)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn psip(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64>;
+
+    /// Calculates the derivative `dq/dψ`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125)?;
+    /// let mut cache = qfactor.cache();
+    ///
+    /// let dq =  qfactor.dq_dpsi(0.015, &mut cache)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn dq_dpsi(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64>;
+
+    /// Calculates the second derivative `d²q/dψ²`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125)?;
+    /// let mut cache = qfactor.cache();
+    ///
+    /// let d2q =  qfactor.d2q_dpsi2(0.015, &mut cache)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn d2q_dpsi2(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64>;
+
+    /// Calculates the rotational transform `ι = 1/q`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125)?;
+    /// let mut cache = qfactor.cache();
+    ///
+    /// let iota =  qfactor.iota(0.015, &mut cache)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn iota(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
+        Ok(1.0 / self.q(psi, cache)?)
+    }
+
+    /// Calculates the derivative `dι/dψ = −(dq/dψ)/q²`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125)?;
+    /// let mut cache = qfactor.cache();
+    ///
+    /// let diota =  qfactor.diota_dpsi(0.015, &mut cache)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn diota_dpsi(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
+        Ok(-self.dq_dpsi(psi, cache)? / self.q(psi, cache)?.powi(2))
+    }
+
+    /// Calculates the magnetic shear `s = (r/q)dq/dr`.
+    ///
+    /// The minor radius is defined by the normalized toroidal flux `ψ = r²/2`, so that
+    /// `s = (2ψ/q)dq/dψ`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125)?;
+    /// let mut cache = qfactor.cache();
     ///
-    /// let q =  qfactor.psip(0.015, &mut acc)?;
+    /// let shear =  qfactor.shear(0.015, &mut cache)?;
     /// # Ok(())
     /// # }
     /// ```
-    fn psip(&self, psi: f64, acc: &mut Accelerator) -> Result<f64>;
+    fn shear(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
+        Ok(2.0 * psi * self.dq_dpsi(psi, cache)? / self.q(psi, cache)?)
+    }
+
+    /// Calculates the toroidal flux `ψ(𝜓ₚ)`, the inverse of [`psip`](Qfactor::psip).
+    ///
+    /// The default implementation uses Newton's method with `d𝜓ₚ/dψ = 1/q`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125)?;
+    /// let mut cache = qfactor.cache();
+    ///
+    /// let psi =  qfactor.psi_from_psip(0.015, &mut cache)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn psi_from_psip(&self, psip: f64, cache: &mut Self::Cache) -> Result<f64> {
+        const MAX_ITER: usize = 50;
+        const TOL: f64 = 1e-14;
+
+        if psip < 0.0 {
+            return Err(negative_psip(psip));
+        }
+        let mut psi = psip;
+        for _ in 0..MAX_ITER {
+            let step = (self.psip(psi, cache)? - psip) * self.q(psi, cache)?;
+            psi = (psi - step).max(0.0);
+            if step.abs() <= TOL * psi.max(TOL) {
+                return Ok(psi);
+            }
+        }
+        Err(EqError::RootFindingError(format!(
+            "ψ(𝜓ₚ) did not converge for 𝜓ₚ = {psip}"
+        )))
+    }
+
+    /// The ψ grid of the q-factor splines, if the profile is interpolated over ψ.
+    ///
+    /// Profiles over the same ψ grid pass their [`psi_acc`](Self::psi_acc) to one another in
+    /// [`Tokamak::eval`](crate::Tokamak::eval), so that the ψ cell is only located once.
+    fn psi_grid(&self) -> Option<&[f64]> {
+        None
+    }
+
+    /// The ψ-[`Accelerator`] of `cache`, if the profile is interpolated over ψ.
+    #[allow(unused_variables)]
+    fn psi_acc<'a>(&self, cache: &'a mut Self::Cache) -> Option<&'a mut Accelerator> {
+        None
+    }
+}
+
+/// The error of inverting a negative poloidal flux.
+pub(crate) fn negative_psip(psip: f64) -> EqError {
+    EqError::RootFindingError(format!("negative poloidal flux 𝜓ₚ = {psip}"))
 }
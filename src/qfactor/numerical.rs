@@ -1,9 +1,13 @@
 use std::path::PathBuf;
 
 use rsl_interpolation::{Accelerator, DynSpline};
+use tokamak_netcdf::Equilibrium;
 
+use crate::Interp1d;
 use crate::Qfactor;
-use crate::Result;
+use crate::cache::PsiCache;
+use crate::dataset;
+use crate::{EqError, Result};
 
 /// q-factor reconstructed from a netCDF file.
 pub struct Numerical {
@@ -11,6 +15,9 @@ pub struct Numerical {
     pub q_spline: DynSpline<f64>,
     /// Spline over the 𝜓ₚ values data.
     pub psip_spline: DynSpline<f64>,
+    /// Monotonic [`Steffen`](Interp1d::Steffen) spline over the ψ data, as a function of the 𝜓ₚ
+    /// values.
+    pub psi_spline: DynSpline<f64>,
     /// The calculated 𝜓ₚ(ψ) values, for all ψ ∈ `psi_data`.
     pub psip_data: Box<[f64]>,
 }
@@ -30,31 +37,74 @@ impl Numerical {
     /// #
     /// # fn main() -> Result<()> {
     /// let path = PathBuf::from("./data.nc");
-    /// let qfactor = qfactor::Numerical::from_dataset(&path, "cubic")?;
+    /// let qfactor = qfactor::Numerical::from_dataset(&path, Interp1d::Cubic)?;
     /// # Ok(())
     /// # }
     /// ```
-    pub fn from_dataset(path: &PathBuf, typ: &str) -> Result<Self> {
-        use rsl_interpolation::*;
+    pub fn from_dataset(path: &PathBuf, typ: Interp1d) -> Result<Self> {
+        Self::from_equilibrium(&Equilibrium::from_file(path)?, typ)
+    }
+
+    /// Constructs a [`Qfactor`] from an already opened netCDF [`Equilibrium`], with spline of
+    /// `typ` interpolation type.
+    ///
+    /// The axis values are treated exactly as in [`Numerical::from_dataset`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use tokamak_equilibria::*;
+    /// # use std::path::PathBuf;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from("./data.nc");
+    /// let eq = Equilibrium::from_file(&path)?;
+    /// let qfactor = qfactor::Numerical::from_equilibrium(&eq, Interp1d::Cubic)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_equilibrium(eq: &Equilibrium, typ: Interp1d) -> Result<Self> {
         use tokamak_netcdf::variable_names::*;
-        use tokamak_netcdf::*;
 
-        let eq = Equilibrium::from_file(path)?;
+        let psi_data = dataset::psi_coord(eq)?;
+        let q_data = dataset::flux_function(eq, Q_FACTOR)?;
 
-        // Add 0.0 manualy, which corresponds to q0.
-        let psi_data = extract_var_with_axis_value(&eq.file, PSI_COORD, 0.0)?
-            .as_standard_layout()
-            .to_vec();
-        // Manually add q0 to the array.
-        let q_data = extract_var_with_first_axis_value(&eq.file, Q_FACTOR)?
-            .as_standard_layout()
-            .to_vec();
+        Self::from_arrays(&psi_data, &q_data, typ)
+    }
 
-        let q_spline = make_spline(typ, &psi_data, &q_data)?;
+    /// Constructs a [`Qfactor`] from the ψ and q data arrays, with spline of `typ` interpolation
+    /// type.
+    ///
+    /// `psi_data` must be strictly increasing and start at the magnetic axis `ψ = 0.0`, and
+    /// `q_data` must have the same length. All values must be finite.
+    ///
+    /// # Example
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let psi_data = [0.0, 0.025, 0.05, 0.075, 0.1];
+    /// let q_data = [1.1, 1.3, 1.8, 2.6, 3.8];
+    /// let qfactor = qfactor::Numerical::from_arrays(&psi_data, &q_data, Interp1d::Cubic)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_arrays(psi_data: &[f64], q_data: &[f64], typ: Interp1d) -> Result<Self> {
+        dataset::validate_grid("ψ", psi_data)?;
+        dataset::validate_values("q", q_data, psi_data.len())?;
+        if psi_data[0] != 0.0 {
+            return Err(EqError::InvalidData(format!(
+                "ψ starts at {}, instead of the magnetic axis",
+                psi_data[0]
+            )));
+        }
+
+        use rsl_interpolation::*;
+
+        let q_spline = make_spline(typ.name(), psi_data, q_data)?;
 
         // psip values calculation
         let iota_data: Vec<f64> = q_data.iter().map(|q| 1.0 / q).collect();
-        let iota_spline = make_spline(typ, &psi_data, &iota_data)?;
+        let iota_spline = make_spline(typ.name(), psi_data, &iota_data)?;
 
         let mut psip_data: Vec<f64> = Vec::with_capacity(psi_data.len());
 
@@ -64,83 +114,185 @@ impl Numerical {
             psip_data.push(psip);
         }
 
-        let psip_spline = make_spline(typ, &psi_data, &psip_data)?;
+        let psip_spline = make_spline(typ.name(), psi_data, &psip_data)?;
+        let psi_spline = make_spline(Interp1d::Steffen.name(), &psip_data, psi_data)?;
 
         debug_assert_eq!(q_spline.xa.len(), psip_spline.xa.len());
 
         Ok(Self {
             q_spline,
             psip_spline,
+            psi_spline,
             psip_data: psip_data.into(),
         })
     }
 }
 
 impl Qfactor for Numerical {
-    fn q(&self, psi: f64, acc: &mut Accelerator) -> Result<f64> {
+    type Cache = PsiCache;
+
+    fn q(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
         debug_assert!(psi.is_sign_positive());
-        Ok(self.q_spline.eval(psi, acc)?)
+        Ok(self.q_spline.eval(psi, &mut cache.psi_acc)?)
     }
 
-    fn psip(&self, psi: f64, acc: &mut Accelerator) -> Result<f64> {
+    fn psip(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
         debug_assert!(psi.is_sign_positive());
-        Ok(self.psip_spline.eval(psi, acc)?)
+        Ok(self.psip_spline.eval(psi, &mut cache.psi_acc)?)
+    }
+
+    fn dq_dpsi(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        Ok(self.q_spline.eval_deriv(psi, &mut cache.psi_acc)?)
+    }
+
+    fn d2q_dpsi2(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        Ok(self.q_spline.eval_deriv2(psi, &mut cache.psi_acc)?)
+    }
+
+    /// Evaluates the inverse spline.
+    ///
+    /// Since `𝜓ₚ(ψ)` is increasing, the inverse spline shares the grid indices of the ψ splines,
+    /// and therefore their accelerator.
+    fn psi_from_psip(&self, psip: f64, cache: &mut Self::Cache) -> Result<f64> {
+        Ok(self.psi_spline.eval(psip, &mut cache.psi_acc)?)
+    }
+
+    fn psi_grid(&self) -> Option<&[f64]> {
+        Some(&self.q_spline.xa)
+    }
+
+    fn psi_acc<'a>(&self, cache: &'a mut Self::Cache) -> Option<&'a mut Accelerator> {
+        Some(&mut cache.psi_acc)
     }
 }
 
 #[cfg(test)]
 mod test {
     use is_close::is_close;
-    use std::path::PathBuf;
-
-    use rsl_interpolation::Accelerator;
 
     use crate::qfactor::Numerical;
+    use crate::testing::{lar_dataset, parabolic};
     use crate::*;
 
     #[test]
-    #[ignore = "needs specific dataset"]
-    /// Values cross-tested with gcmotion.
-    fn test_numeric_qfactor_qvalues() {
-        let path = PathBuf::from("./reconstructed/smart_positive.nc");
+    fn test_from_arrays_validation() {
+        let psi_data = [0.0, 0.025, 0.05, 0.075, 0.1];
+        let q_data = [1.1, 1.3, 1.8, 2.6, 3.8];
+        assert!(Numerical::from_arrays(&psi_data, &q_data, Interp1d::Cubic).is_ok());
 
-        let mut acc = Accelerator::new();
-        let qf = Numerical::from_dataset(&path, "Akima").unwrap();
+        let unsorted = [0.0, 0.025, 0.025, 0.075, 0.1];
+        let err = Numerical::from_arrays(&unsorted, &q_data, Interp1d::Cubic);
+        assert!(matches!(err, Err(EqError::InvalidData(_))));
+        let err = Numerical::from_arrays(&psi_data, &q_data[1..], Interp1d::Cubic);
+        assert!(matches!(err, Err(EqError::InvalidData(_))));
+        let nan = [1.1, 1.3, f64::NAN, 2.6, 3.8];
+        let err = Numerical::from_arrays(&psi_data, &nan, Interp1d::Cubic);
+        assert!(matches!(err, Err(EqError::InvalidData(_))));
+        let off_axis = [0.01, 0.025, 0.05, 0.075, 0.1];
+        let err = Numerical::from_arrays(&off_axis, &q_data, Interp1d::Cubic);
+        assert!(matches!(err, Err(EqError::InvalidData(_))));
+    }
 
-        assert_eq!(qf.q(0.0, &mut acc).unwrap(), 0.9164152189670636); // inserted value
-        // Use a relatively high relative tolerance, since the splines are not exactly the same.
-        assert!(is_close!(
-            qf.q(0.1, &mut acc).unwrap(),
-            1.9514842302135769,
-            rel_tol = 1e-4
-        ));
-        assert!(is_close!(
-            // wall value
-            qf.q(0.19889475414290547, &mut acc).unwrap(),
-            5.996391839022671,
-            rel_tol = 1e-9
-        ));
+    #[test]
+    fn test_psi_from_psip() {
+        let parabolic = parabolic();
+        let psi_data: Vec<f64> = (0..=200).map(|k| 0.125 * k as f64 / 200.0).collect();
+        let q_data: Vec<f64> = psi_data
+            .iter()
+            .map(|psi| parabolic.q(*psi, &mut parabolic.cache()).unwrap())
+            .collect();
+        let qf = Numerical::from_arrays(&psi_data, &q_data, Interp1d::Cubic).unwrap();
+        let mut cache = qf.cache();
+
+        for psi in [0.0, 1e-3, 0.01, 0.0555, 0.1, 0.12] {
+            let psip = qf.psip(psi, &mut cache).unwrap();
+            let inv = qf.psi_from_psip(psip, &mut cache).unwrap();
+            assert!(is_close!(inv, psi, abs_tol = 1e-9));
+            let exact = parabolic.psip(psi, &mut parabolic.cache()).unwrap();
+            let inv = qf.psi_from_psip(exact, &mut cache).unwrap();
+            assert!(is_close!(inv, psi, abs_tol = 1e-7));
+        }
+        let psip_wall = qf.psip(0.125, &mut cache).unwrap();
+        let inv = qf.psi_from_psip(psip_wall, &mut cache).unwrap();
+        assert!(is_close!(inv, 0.125, abs_tol = 1e-9));
+        assert!(qf.psi_from_psip(1.1 * psip_wall, &mut cache).is_err());
     }
 
     #[test]
-    #[ignore = "needs specific dataset"]
-    /// Values cross-tested with gcmotion.
-    fn test_numeric_qfactor_psip() {
-        let path = PathBuf::from("./reconstructed/smart_positive.nc");
+    fn test_derivatives() {
+        let parabolic = parabolic();
+        let psi_data: Vec<f64> = (0..=200).map(|k| 0.125 * k as f64 / 200.0).collect();
+        let q_data: Vec<f64> = psi_data
+            .iter()
+            .map(|psi| parabolic.q(*psi, &mut parabolic.cache()).unwrap())
+            .collect();
+        let qf = Numerical::from_arrays(&psi_data, &q_data, Interp1d::Cubic).unwrap();
+        let mut cache = qf.cache();
+        let mut exact = parabolic.cache();
 
-        let mut acc = Accelerator::new();
-        let qf = Numerical::from_dataset(&path, "Akima").unwrap();
+        for psi in [0.01, 0.0555, 0.1] {
+            assert!(is_close!(
+                qf.dq_dpsi(psi, &mut cache).unwrap(),
+                parabolic.dq_dpsi(psi, &mut exact).unwrap(),
+                rel_tol = 1e-6
+            ));
+            assert!(is_close!(
+                qf.d2q_dpsi2(psi, &mut cache).unwrap(),
+                parabolic.d2q_dpsi2(psi, &mut exact).unwrap(),
+                rel_tol = 1e-3
+            ));
+            assert!(is_close!(
+                qf.shear(psi, &mut cache).unwrap(),
+                parabolic.shear(psi, &mut exact).unwrap(),
+                rel_tol = 1e-6
+            ));
+        }
+    }
 
-        assert_eq!(qf.psip(0.0, &mut acc).unwrap(), 0.0);
+    #[test]
+    fn test_dataset_qvalues() {
+        let file = lar_dataset("qfactor_qvalues", &SampleGrid::new(0.125));
+        let qf = Numerical::from_dataset(&file.path, Interp1d::Akima).unwrap();
+        let parabolic = parabolic();
+        let mut cache = qf.cache();
+        let mut exact = parabolic.cache();
+
+        assert_eq!(qf.q_spline.xa.len(), 101);
+        // The axis value duplicates the first sampled one.
         assert!(is_close!(
-            qf.psip(0.1, &mut acc).unwrap(),
-            0.07745443648243741,
-            rel_tol = 1e-4
+            qf.q(0.0, &mut cache).unwrap(),
+            parabolic.q(0.125 / 100.0, &mut exact).unwrap()
         ));
+        for psi in [0.05, 0.1, 0.125] {
+            assert!(is_close!(
+                qf.q(psi, &mut cache).unwrap(),
+                parabolic.q(psi, &mut exact).unwrap()
+            ));
+        }
         assert!(is_close!(
-            qf.psip(0.19889475414290547, &mut acc).unwrap(),
-            0.11079153406091534,
-            rel_tol = 1e-4
+            qf.q(0.0771, &mut cache).unwrap(),
+            parabolic.q(0.0771, &mut exact).unwrap(),
+            rel_tol = 1e-6
         ));
     }
+
+    #[test]
+    fn test_dataset_psip() {
+        let file = lar_dataset("qfactor_psip", &SampleGrid::new(0.125));
+        let qf = Numerical::from_dataset(&file.path, Interp1d::Akima).unwrap();
+        let parabolic = parabolic();
+        let mut cache = qf.cache();
+        let mut exact = parabolic.cache();
+
+        assert_eq!(qf.psip(0.0, &mut cache).unwrap(), 0.0);
+        for psi in [0.0771, 0.1, 0.125] {
+            assert!(is_close!(
+                qf.psip(psi, &mut cache).unwrap(),
+                parabolic.psip(psi, &mut exact).unwrap(),
+                rel_tol = 1e-4
+            ));
+        }
+    }
 }
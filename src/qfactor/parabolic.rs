@@ -1,7 +1,8 @@
-use rsl_interpolation::Accelerator;
+use std::f64::consts::FRAC_PI_2;
 
-use crate::Result;
-use crate::qfactor::Qfactor;
+use crate::cache::NoCache;
+use crate::qfactor::{Qfactor, negative_psip};
+use crate::{EqError, Result};
 
 /// Parabolic q-factor.
 ///
@@ -53,26 +54,54 @@ impl Parabolic {
 }
 
 impl Qfactor for Parabolic {
+    type Cache = NoCache;
+
     #[allow(unused_variables)]
-    fn q(&self, psi: f64, acc: &mut Accelerator) -> Result<f64> {
+    fn q(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
         debug_assert!(psi.is_sign_positive());
         Ok(self.q0 + self.diff * (psi / self.psi_wall).powi(2))
     }
 
     #[allow(unused_variables)]
-    fn psip(&self, psi: f64, acc: &mut Accelerator) -> Result<f64> {
+    fn psip(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
         debug_assert!(psi.is_sign_positive());
         let atan = (self.sqrt_diff * psi / self.sqrt_q0psi_wall).atan();
         let psip = self.psi_wall / self.sqrt_prod * atan;
         Ok(psip)
     }
+
+    #[allow(unused_variables)]
+    fn dq_dpsi(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        Ok(2.0 * self.diff * psi / self.psi_wall.powi(2))
+    }
+
+    #[allow(unused_variables)]
+    fn d2q_dpsi2(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        Ok(2.0 * self.diff / self.psi_wall.powi(2))
+    }
+
+    /// Inverts the arctangent of [`psip`](Qfactor::psip) exactly.
+    #[allow(unused_variables)]
+    fn psi_from_psip(&self, psip: f64, cache: &mut Self::Cache) -> Result<f64> {
+        if psip < 0.0 {
+            return Err(negative_psip(psip));
+        }
+        let atan = psip * self.sqrt_prod / self.psi_wall;
+        if atan >= FRAC_PI_2 {
+            return Err(EqError::RootFindingError(format!(
+                "𝜓ₚ = {psip} exceeds the poloidal flux at ψ → ∞"
+            )));
+        }
+        Ok(self.sqrt_q0psi_wall / self.sqrt_diff * atan.tan())
+    }
 }
 
 #[cfg(test)]
 mod test {
     use crate::*;
     use is_close::is_close;
-    use rsl_interpolation::Accelerator;
 
     #[test]
     /// Values cross-tested with gcmotion.
@@ -81,29 +110,68 @@ mod test {
         let qwall = 3.8;
         let psi_wall = 0.04591368227731865;
 
-        let mut acc = Accelerator::new();
         let qfactor = qfactor::Parabolic::new(q0, qwall, psi_wall).unwrap();
+        let mut cache = qfactor.cache();
 
-        assert!(is_close!(qfactor.q(0.0, &mut acc).unwrap(), q0));
-        assert!(is_close!(qfactor.q(0.01, &mut acc).unwrap(), 1.228079468));
+        assert!(is_close!(qfactor.q(0.0, &mut cache).unwrap(), q0));
+        assert!(is_close!(qfactor.q(0.01, &mut cache).unwrap(), 1.228079468));
         assert!(is_close!(
-            qfactor.q(0.03, &mut acc).unwrap(),
+            qfactor.q(0.03, &mut cache).unwrap(),
             2.2527152119999996
         ));
-        assert!(is_close!(qfactor.q(psi_wall, &mut acc).unwrap(), qwall));
+        assert!(is_close!(qfactor.q(psi_wall, &mut cache).unwrap(), qwall));
 
-        assert!(is_close!(qfactor.psip(0.0, &mut acc).unwrap(), 0.0));
+        assert!(is_close!(qfactor.psip(0.0, &mut cache).unwrap(), 0.0));
         assert!(is_close!(
-            qfactor.psip(0.01, &mut acc).unwrap(),
+            qfactor.psip(0.01, &mut cache).unwrap(),
             0.00876084223156207
         ));
         assert!(is_close!(
-            qfactor.psip(0.03, &mut acc).unwrap(),
+            qfactor.psip(0.03, &mut cache).unwrap(),
             0.021236184655956582
         ));
         assert!(is_close!(
-            qfactor.psip(psi_wall, &mut acc).unwrap(),
+            qfactor.psip(psi_wall, &mut cache).unwrap(),
             0.026713778215136246
         ));
     }
+
+    #[test]
+    fn test_derivatives() {
+        let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125).unwrap();
+        let mut cache = qfactor.cache();
+        let h = 1e-5;
+
+        for psi in [0.01, 0.05, 0.1] {
+            let q = |psi| qfactor.q(psi, &mut qfactor.cache()).unwrap();
+            let dq = qfactor.dq_dpsi(psi, &mut cache).unwrap();
+            let d2q = qfactor.d2q_dpsi2(psi, &mut cache).unwrap();
+            assert!(is_close!(dq, (q(psi + h) - q(psi - h)) / (2.0 * h)));
+            let fd2 = (q(psi + h) - 2.0 * q(psi) + q(psi - h)) / h.powi(2);
+            assert!(is_close!(d2q, fd2, rel_tol = 1e-5));
+
+            let iota = qfactor.iota(psi, &mut cache).unwrap();
+            let diota = qfactor.diota_dpsi(psi, &mut cache).unwrap();
+            assert!(is_close!(iota, 1.0 / q(psi)));
+            assert!(is_close!(diota, -dq * iota.powi(2)));
+        }
+        // s = 2ψq'/q = 4(q − q₀)/q.
+        let shear = qfactor.shear(0.125, &mut cache).unwrap();
+        assert!(is_close!(shear, 4.0 * (3.9 - 1.1) / 3.9));
+        assert_eq!(qfactor.shear(0.0, &mut cache).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_psi_from_psip() {
+        let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125).unwrap();
+        let mut cache = qfactor.cache();
+
+        for psi in [0.0, 1e-4, 0.01, 0.1, 0.125, 1.0] {
+            let psip = qfactor.psip(psi, &mut cache).unwrap();
+            let inv = qfactor.psi_from_psip(psip, &mut cache).unwrap();
+            assert!(is_close!(inv, psi, abs_tol = 1e-14));
+        }
+        assert!(qfactor.psi_from_psip(-1.0, &mut cache).is_err());
+        assert!(qfactor.psi_from_psip(1.0, &mut cache).is_err());
+    }
 }
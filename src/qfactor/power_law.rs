@@ -0,0 +1,329 @@
+use crate::cache::NoCache;
+use crate::numerics::{gauss_legendre, integrate};
+use crate::qfactor::Qfactor;
+use crate::{EqError, Result};
+
+/// Number of nodes of the Gauss–Legendre rule of every panel.
+const NODES: usize = 16;
+
+/// Power law q-factor.
+///
+/// `q` is given by the equation `q(𝜓) = q₀ + (q_w − q₀)(𝜓/𝜓_w)^α`, and reduces to
+/// [`Parabolic`](crate::qfactor::Parabolic) for `α = 2`.
+///
+/// `𝜓ₚ` has a closed form for `α = 1`, `α = 2` and `q_w = q₀`. Otherwise it is integrated with
+/// Gauss–Legendre quadrature, over panels which halve in size towards the magnetic axis to resolve
+/// the singular derivatives of non-integer powers. The integrals up to the panel boundaries are
+/// computed once, when the profile is created, so that every evaluation integrates a single panel.
+pub struct PowerLaw {
+    /// The q-factor value at the magnetic axis.
+    pub q0: f64,
+    /// the q-factor value at the wall.
+    pub qwall: f64,
+    /// The toroidal flux value at the wall.
+    pub psi_wall: f64,
+    /// The exponent `α`.
+    pub alpha: f64,
+    /// Intermediate quantity to avoid recalculation. Is equal to `qwall - q0`.
+    pub(crate) diff: f64,
+    /// The number of panels of the `psip` quadrature.
+    pub(crate) panels: usize,
+    /// The Gauss–Legendre nodes and weights.
+    pub(crate) rule: ([f64; NODES], [f64; NODES]),
+    /// The panel boundaries, from the innermost one up to `psi_wall`.
+    pub(crate) knots: Box<[f64]>,
+    /// The values of `psip` at the panel boundaries.
+    pub(crate) knot_psips: Box<[f64]>,
+}
+
+impl PowerLaw {
+    /// Crates a new power law q-factor profile.
+    ///
+    /// `q0` and `qwall` must be positive, and `alpha` must be positive.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let qfactor = qfactor::PowerLaw::new(1.1, 3.9, 0.125, 1.5)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(q0: f64, qwall: f64, psi_wall: f64, alpha: f64) -> Result<Self> {
+        if !(q0 > 0.0 && qwall > 0.0) {
+            return Err(EqError::InvalidEquilibrium(format!(
+                "q0 = {q0} and q_wall = {qwall} must be positive"
+            )));
+        }
+        if alpha <= 0.0 || alpha.is_nan() {
+            return Err(EqError::InvalidEquilibrium(format!(
+                "alpha = {alpha} must be positive"
+            )));
+        }
+        // The innermost panel contributes an error of order (1/2)^(panels(1 + α)).
+        let panels = (48.0 / (1.0 + alpha)).ceil() as usize;
+
+        let mut qfactor = Self {
+            q0,
+            qwall,
+            psi_wall,
+            alpha,
+            diff: qwall - q0,
+            panels,
+            rule: gauss_legendre(),
+            knots: Box::default(),
+            knot_psips: Box::default(),
+        };
+        if qfactor.closed_form(psi_wall).is_none() {
+            let knots: Box<[f64]> = (0..panels)
+                .rev()
+                .map(|k| psi_wall * 0.5f64.powi(k as i32))
+                .collect();
+            let mut psip = qfactor.psip_from_axis(knots[0]);
+            let mut knot_psips = vec![psip];
+            for pair in knots.windows(2) {
+                psip += qfactor.panel(pair[0], pair[1]);
+                knot_psips.push(psip);
+            }
+            qfactor.knots = knots;
+            qfactor.knot_psips = knot_psips.into();
+        }
+        Ok(qfactor)
+    }
+
+    /// Returns the closed form of `psip`, if there is one.
+    fn closed_form(&self, psi: f64) -> Option<f64> {
+        if self.diff == 0.0 {
+            return Some(psi / self.q0);
+        }
+        if self.alpha == 1.0 {
+            let ratio = self.diff / (self.q0 * self.psi_wall);
+            return Some((ratio * psi).ln_1p() / (ratio * self.q0));
+        }
+        if self.alpha == 2.0 {
+            let ratio = (self.diff.abs() / self.q0).sqrt() / self.psi_wall;
+            let x = ratio * psi;
+            let angle = if self.diff > 0.0 { x.atan() } else { x.atanh() };
+            return Some(angle / (ratio * self.q0));
+        }
+        None
+    }
+
+    /// Integrates `1/q` over `[a, b]` with a single Gauss–Legendre panel.
+    fn panel(&self, a: f64, b: f64) -> f64 {
+        let f = |p: f64| 1.0 / (self.q0 + self.diff * (p / self.psi_wall).powf(self.alpha));
+        integrate(f, a, b, &self.rule)
+    }
+
+    /// Integrates `1/q` from the magnetic axis up to `psi`, over panels which halve in size
+    /// towards the axis.
+    fn psip_from_axis(&self, psi: f64) -> f64 {
+        let mut b = psi;
+        let mut psip = 0.0;
+        for _ in 1..self.panels {
+            let a = 0.5 * b;
+            psip += self.panel(a, b);
+            b = a;
+        }
+        psip + self.panel(0.0, b)
+    }
+}
+
+impl Qfactor for PowerLaw {
+    type Cache = NoCache;
+
+    #[allow(unused_variables)]
+    fn q(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        Ok(self.q0 + self.diff * (psi / self.psi_wall).powf(self.alpha))
+    }
+
+    /// Diverges at the magnetic axis for `α < 1`, where it returns an infinity of the sign of
+    /// `q_w − q₀`.
+    #[allow(unused_variables)]
+    fn dq_dpsi(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        if self.diff == 0.0 {
+            return Ok(0.0);
+        }
+        let s = psi / self.psi_wall;
+        Ok(self.diff * self.alpha / self.psi_wall * s.powf(self.alpha - 1.0))
+    }
+
+    /// Diverges at the magnetic axis for `α < 2`, except for `α = 1`.
+    #[allow(unused_variables)]
+    fn d2q_dpsi2(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        if self.alpha == 1.0 || self.diff == 0.0 {
+            return Ok(0.0);
+        }
+        let s = psi / self.psi_wall;
+        let factor = self.diff * self.alpha * (self.alpha - 1.0) / self.psi_wall.powi(2);
+        Ok(factor * s.powf(self.alpha - 2.0))
+    }
+
+    /// Uses `s = 2α(q − q₀)/q`, which vanishes at the magnetic axis for every `α`.
+    #[allow(unused_variables)]
+    fn shear(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        let excess = self.diff * (psi / self.psi_wall).powf(self.alpha);
+        Ok(2.0 * self.alpha * excess / (self.q0 + excess))
+    }
+
+    #[allow(unused_variables)]
+    fn psip(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        if let Some(psip) = self.closed_form(psi) {
+            return Ok(psip);
+        }
+        let index = self.knots.partition_point(|knot| *knot <= psi);
+        if index == 0 {
+            return Ok(self.psip_from_axis(psi));
+        }
+        let mut a = self.knots[index - 1];
+        let mut psip = self.knot_psips[index - 1];
+        // Past the wall, the panels double in size.
+        while a < psi {
+            let b = psi.min(2.0 * a);
+            psip += self.panel(a, b);
+            a = b;
+        }
+        Ok(psip)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+    use is_close::is_close;
+
+    #[test]
+    fn test_parabolic_limit() {
+        let qfactor = qfactor::PowerLaw::new(1.1, 3.9, 0.125, 2.0).unwrap();
+        let parabolic = qfactor::Parabolic::new(1.1, 3.9, 0.125).unwrap();
+        let mut cache = qfactor.cache();
+
+        for psi in [0.0, 0.01, 0.05, 0.1, 0.125] {
+            assert!(is_close!(
+                qfactor.q(psi, &mut cache).unwrap(),
+                parabolic.q(psi, &mut cache).unwrap()
+            ));
+            assert!(is_close!(
+                qfactor.psip(psi, &mut cache).unwrap(),
+                parabolic.psip(psi, &mut cache).unwrap(),
+                rel_tol = 1e-13
+            ));
+        }
+    }
+
+    #[test]
+    fn test_quadrature() {
+        for alpha in [0.5, 1.0, 3.0, 8.0] {
+            let qfactor = qfactor::PowerLaw::new(1.1, 3.9, 0.125, alpha).unwrap();
+            let mut cache = qfactor.cache();
+            assert!(is_close!(qfactor.q(0.125, &mut cache).unwrap(), 3.9));
+
+            // Composite Simpson's rule of 1/q, in `s = √𝜓` to remove the singular derivatives.
+            let n = 20000;
+            let h = 0.125f64.sqrt() / n as f64;
+            let f = |s: f64| 2.0 * s / qfactor.q(s * s, &mut qfactor.cache()).unwrap();
+            let mut psip = 0.0;
+            for k in 0..n {
+                let a = k as f64 * h;
+                psip += h / 6.0 * (f(a) + 4.0 * f(a + h / 2.0) + f(a + h));
+            }
+            assert!(is_close!(
+                qfactor.psip(0.125, &mut cache).unwrap(),
+                psip,
+                rel_tol = 1e-10
+            ));
+        }
+    }
+
+    #[test]
+    fn test_cached_panels() {
+        for alpha in [0.5, 1.0, 1.5, 2.0, 3.0] {
+            for (q0, qwall) in [(1.1, 3.9), (3.9, 1.1), (2.0, 2.0)] {
+                let qfactor = qfactor::PowerLaw::new(q0, qwall, 0.125, alpha).unwrap();
+                let mut cache = qfactor.cache();
+                // Past the wall, a decreasing q eventually vanishes.
+                let outer = if qwall >= q0 { 0.3 } else { 0.125 };
+                for psi in [0.0, 1e-12, 1e-4, 0.01, 0.0625, 0.1, 0.125, outer] {
+                    assert!(is_close!(
+                        qfactor.psip(psi, &mut cache).unwrap(),
+                        qfactor.psip_from_axis(psi),
+                        rel_tol = 1e-13
+                    ));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_derivatives() {
+        let h = 1e-6;
+        for alpha in [0.5, 1.0, 3.0] {
+            let qfactor = qfactor::PowerLaw::new(1.1, 3.9, 0.125, alpha).unwrap();
+            let mut cache = qfactor.cache();
+            let dq = |psi| qfactor.dq_dpsi(psi, &mut qfactor.cache()).unwrap();
+
+            for psi in [0.01, 0.05, 0.1] {
+                let q = |psi| qfactor.q(psi, &mut qfactor.cache()).unwrap();
+                assert!(is_close!(
+                    qfactor.dq_dpsi(psi, &mut cache).unwrap(),
+                    (q(psi + h) - q(psi - h)) / (2.0 * h),
+                    rel_tol = 1e-8
+                ));
+                assert!(is_close!(
+                    qfactor.d2q_dpsi2(psi, &mut cache).unwrap(),
+                    (dq(psi + h) - dq(psi - h)) / (2.0 * h),
+                    abs_tol = 1e-6
+                ));
+            }
+            // s = 2ψq'/q = 2α(q − q₀)/q.
+            let shear = qfactor.shear(0.125, &mut cache).unwrap();
+            assert!(is_close!(shear, 2.0 * alpha * (3.9 - 1.1) / 3.9));
+        }
+    }
+
+    #[test]
+    fn test_axis() {
+        for (alpha, dq) in [(0.5, f64::INFINITY), (1.0, 2.8 / 0.125), (3.0, 0.0)] {
+            let qfactor = qfactor::PowerLaw::new(1.1, 3.9, 0.125, alpha).unwrap();
+            let mut cache = qfactor.cache();
+            assert_eq!(qfactor.dq_dpsi(0.0, &mut cache).unwrap(), dq);
+            assert_eq!(qfactor.shear(0.0, &mut cache).unwrap(), 0.0);
+        }
+        let qfactor = qfactor::PowerLaw::new(3.9, 1.1, 0.125, 0.5).unwrap();
+        let mut cache = qfactor.cache();
+        assert_eq!(qfactor.dq_dpsi(0.0, &mut cache).unwrap(), f64::NEG_INFINITY);
+        assert_eq!(qfactor.shear(0.0, &mut cache).unwrap(), 0.0);
+
+        let qfactor = qfactor::PowerLaw::new(2.0, 2.0, 0.125, 0.5).unwrap();
+        let mut cache = qfactor.cache();
+        assert_eq!(qfactor.dq_dpsi(0.0, &mut cache).unwrap(), 0.0);
+        assert_eq!(qfactor.d2q_dpsi2(0.0, &mut cache).unwrap(), 0.0);
+        assert_eq!(qfactor.shear(0.0, &mut cache).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_psi_from_psip() {
+        let qfactor = qfactor::PowerLaw::new(1.1, 3.9, 0.125, 1.5).unwrap();
+        let mut cache = qfactor.cache();
+
+        for psi in [0.0, 1e-4, 0.01, 0.1, 0.125] {
+            let psip = qfactor.psip(psi, &mut cache).unwrap();
+            let inv = qfactor.psi_from_psip(psip, &mut cache).unwrap();
+            assert!(is_close!(inv, psi, abs_tol = 1e-14));
+        }
+        assert!(qfactor.psi_from_psip(-1.0, &mut cache).is_err());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(qfactor::PowerLaw::new(1.1, 3.9, 0.125, 0.0).is_err());
+        assert!(qfactor::PowerLaw::new(-1.1, 3.9, 0.125, 2.0).is_err());
+    }
+}
@@ -0,0 +1,140 @@
+use std::ops::RangeInclusive;
+
+use crate::Result;
+use crate::numerics::bisect;
+use crate::qfactor::Qfactor;
+
+/// Number of uniform ψ intervals scanned for sign changes of `q − m/n`.
+const SAMPLES: usize = 1000;
+
+/// A rational flux surface `q(ψ) = m/n`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RationalSurface {
+    /// The poloidal mode number `m`.
+    pub m: u32,
+    /// The toroidal mode number `n`.
+    pub n: u32,
+    /// The toroidal flux of the surface.
+    pub psi: f64,
+    /// The poloidal flux of the surface.
+    pub psip: f64,
+    /// The magnetic shear at the surface, see [`Qfactor::shear`].
+    pub shear: f64,
+}
+
+/// Finds every flux surface in `[0, psi_max]` where `q(ψ) = m/n`, for all `m` in `m_range` and
+/// `n` in `n_range`.
+///
+/// The roots are bracketed by the sign changes of `q − m/n` over a uniform ψ grid and refined
+/// with bisection, so that non-monotonic profiles, such as
+/// [`ReversedShear`](crate::qfactor::ReversedShear), yield all their surfaces. Surfaces where `q`
+/// only touches `m/n` are not found. Pairs with a common factor, such as `(4, 2)` and `(2, 1)`,
+/// are reported separately, while `n = 0` is skipped.
+///
+/// The surfaces are ordered by `m`, `n` and then ψ.
+///
+/// # Example
+///
+/// ```
+/// # use tokamak_equilibria::*;
+/// # use tokamak_equilibria::qfactor::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125)?;
+///
+/// let surfaces = rational_surfaces(&qfactor, 0.125, 1..=4, 1..=2)?;
+/// let RationalSurface { m, n, psi, shear, .. } = surfaces[0];
+/// # Ok(())
+/// # }
+/// ```
+pub fn rational_surfaces<Q: Qfactor>(
+    qfactor: &Q,
+    psi_max: f64,
+    m_range: RangeInclusive<u32>,
+    n_range: RangeInclusive<u32>,
+) -> Result<Vec<RationalSurface>> {
+    let mut cache = qfactor.cache();
+    let psi: Vec<f64> = (0..=SAMPLES)
+        .map(|k| psi_max * k as f64 / SAMPLES as f64)
+        .collect();
+    let q: Vec<f64> = psi
+        .iter()
+        .map(|psi| qfactor.q(*psi, &mut cache))
+        .collect::<Result<_>>()?;
+
+    let mut surfaces = Vec::new();
+    for m in m_range {
+        for n in n_range.clone().filter(|n| *n != 0) {
+            let target = m as f64 / n as f64;
+            let mut roots = Vec::new();
+            for k in 0..SAMPLES {
+                let (fa, fb) = (q[k] - target, q[k + 1] - target);
+                if fa == 0.0 {
+                    roots.push(psi[k]);
+                } else if fa * fb < 0.0 {
+                    let f = |psi| Ok(qfactor.q(psi, &mut cache)? - target);
+                    roots.push(bisect(f, psi[k], psi[k + 1])?);
+                }
+            }
+            if q[SAMPLES] == target {
+                roots.push(psi_max);
+            }
+
+            for psi in roots {
+                surfaces.push(RationalSurface {
+                    m,
+                    n,
+                    psi,
+                    psip: qfactor.psip(psi, &mut cache)?,
+                    shear: qfactor.shear(psi, &mut cache)?,
+                });
+            }
+        }
+    }
+    Ok(surfaces)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::qfactor::*;
+    use crate::*;
+    use is_close::is_close;
+
+    #[test]
+    /// On `q = q₀ + (q_w − q₀)(ψ/ψ_w)²`, `ψ = ψ_w√((m/n − q₀)/(q_w − q₀))`.
+    fn test_parabolic() {
+        let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125).unwrap();
+        let mut cache = qfactor.cache();
+
+        let surfaces = rational_surfaces(&qfactor, 0.125, 1..=4, 0..=2).unwrap();
+        let pairs: Vec<(u32, u32)> = surfaces.iter().map(|s| (s.m, s.n)).collect();
+        assert_eq!(pairs, vec![(2, 1), (3, 1), (3, 2), (4, 2)]);
+
+        for surface in surfaces {
+            let target = surface.m as f64 / surface.n as f64;
+            let psi = 0.125 * ((target - 1.1) / (3.9 - 1.1)).sqrt();
+            assert!(is_close!(surface.psi, psi, rel_tol = 1e-12));
+            assert!(is_close!(
+                surface.psip,
+                qfactor.psip(psi, &mut cache).unwrap(),
+                rel_tol = 1e-12
+            ));
+            assert!(is_close!(surface.shear, 4.0 * (target - 1.1) / target));
+        }
+    }
+
+    #[test]
+    fn test_reversed_shear() {
+        let qfactor = qfactor::ReversedShear::new(3.0, 2.1, 0.05, 4.5, 0.125).unwrap();
+
+        let surfaces = rational_surfaces(&qfactor, 0.125, 5..=5, 2..=2).unwrap();
+        assert_eq!(surfaces.len(), 2);
+        assert!(surfaces[0].psi < 0.05 && surfaces[0].shear < 0.0);
+        assert!(surfaces[1].psi > 0.05 && surfaces[1].shear > 0.0);
+
+        // Only outside of the minimum.
+        let surfaces = rational_surfaces(&qfactor, 0.125, 4..=4, 1..=1).unwrap();
+        assert_eq!(surfaces.len(), 1);
+        assert!(surfaces[0].shear > 0.0);
+    }
+}
@@ -0,0 +1,245 @@
+use std::f64::consts::FRAC_PI_2;
+
+use crate::cache::NoCache;
+use crate::qfactor::{Qfactor, negative_psip};
+use crate::{EqError, Result};
+
+/// Reversed shear q-factor.
+///
+/// `q` is made of two parabolas joined at its minimum `q_min` at `𝜓_min`:
+///
+/// ```text
+/// q(𝜓) = q_min + (q₀ − q_min)(1 − 𝜓/𝜓_min)²                  for 𝜓 < 𝜓_min
+/// q(𝜓) = q_min + (q_w − q_min)((𝜓 − 𝜓_min)/(𝜓_w − 𝜓_min))²   for 𝜓 ≥ 𝜓_min
+/// ```
+///
+/// so that the magnetic shear is negative inside `𝜓_min` and positive outside.
+pub struct ReversedShear {
+    /// The q-factor value at the magnetic axis.
+    pub q0: f64,
+    /// The minimum q-factor value.
+    pub qmin: f64,
+    /// The toroidal flux value at the q-factor minimum.
+    pub psi_min: f64,
+    /// the q-factor value at the wall.
+    pub qwall: f64,
+    /// The toroidal flux value at the wall.
+    pub psi_wall: f64,
+    /// Intermediate quantity to avoid recalculation. Is equal to `(q0 - qmin)/psi_min²`.
+    pub(crate) c_in: f64,
+    /// Intermediate quantity to avoid recalculation. Is equal to
+    /// `(qwall - qmin)/(psi_wall - psi_min)²`.
+    pub(crate) c_out: f64,
+    /// Intermediate quantity to avoid recalculation. Is equal to `psip(psi_min)`.
+    pub(crate) psip_min: f64,
+}
+
+impl ReversedShear {
+    /// Crates a new reversed shear q-factor profile.
+    ///
+    /// `qmin` must be positive and not larger than `q0` and `qwall`, and `psi_min` must lie in
+    /// `(0, psi_wall)`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let qfactor = qfactor::ReversedShear::new(3.0, 2.1, 0.05, 4.5, 0.125)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(q0: f64, qmin: f64, psi_min: f64, qwall: f64, psi_wall: f64) -> Result<Self> {
+        if !(qmin > 0.0 && qmin <= q0 && qmin <= qwall) {
+            return Err(EqError::InvalidEquilibrium(format!(
+                "q_min = {qmin} must be positive and not exceed q0 = {q0} and q_wall = {qwall}"
+            )));
+        }
+        if !(psi_min > 0.0 && psi_min < psi_wall) {
+            return Err(EqError::InvalidEquilibrium(format!(
+                "psi_min = {psi_min} must lie in (0, {psi_wall})"
+            )));
+        }
+        let c_in = (q0 - qmin) / psi_min.powi(2);
+        let c_out = (qwall - qmin) / (psi_wall - psi_min).powi(2);
+
+        Ok(Self {
+            q0,
+            qmin,
+            psi_min,
+            qwall,
+            psi_wall,
+            c_in,
+            c_out,
+            psip_min: -arctan_integral(qmin, c_in, -psi_min),
+        })
+    }
+}
+
+impl Qfactor for ReversedShear {
+    type Cache = NoCache;
+
+    #[allow(unused_variables)]
+    fn q(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        let x = psi - self.psi_min;
+        let c = if x < 0.0 { self.c_in } else { self.c_out };
+        Ok(self.qmin + c * x.powi(2))
+    }
+
+    #[allow(unused_variables)]
+    fn psip(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        let x = psi - self.psi_min;
+        let c = if x < 0.0 { self.c_in } else { self.c_out };
+        Ok(self.psip_min + arctan_integral(self.qmin, c, x))
+    }
+
+    #[allow(unused_variables)]
+    fn dq_dpsi(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        let x = psi - self.psi_min;
+        let c = if x < 0.0 { self.c_in } else { self.c_out };
+        Ok(2.0 * c * x)
+    }
+
+    /// Discontinuous at `psi_min`, where the outer value is returned.
+    #[allow(unused_variables)]
+    fn d2q_dpsi2(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        let c = if psi < self.psi_min {
+            self.c_in
+        } else {
+            self.c_out
+        };
+        Ok(2.0 * c)
+    }
+
+    /// Inverts the arctangent of [`psip`](Qfactor::psip) exactly, on either side of `psi_min`.
+    #[allow(unused_variables)]
+    fn psi_from_psip(&self, psip: f64, cache: &mut Self::Cache) -> Result<f64> {
+        if psip < 0.0 {
+            return Err(negative_psip(psip));
+        }
+        let y = psip - self.psip_min;
+        let c = if y < 0.0 { self.c_in } else { self.c_out };
+        let x = if c == 0.0 {
+            y * self.qmin
+        } else {
+            let atan = y * (self.qmin * c).sqrt();
+            if atan >= FRAC_PI_2 {
+                return Err(EqError::RootFindingError(format!(
+                    "𝜓ₚ = {psip} exceeds the poloidal flux at ψ → ∞"
+                )));
+            }
+            atan.tan() / (c / self.qmin).sqrt()
+        };
+        Ok((self.psi_min + x).max(0.0))
+    }
+}
+
+/// Calculates `∫₀ˣ dx/(q + cx²)`.
+fn arctan_integral(q: f64, c: f64, x: f64) -> f64 {
+    if c == 0.0 {
+        x / q
+    } else {
+        (x * (c / q).sqrt()).atan() / (q * c).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+    use is_close::is_close;
+
+    #[test]
+    fn test_reversed_shear() {
+        let qfactor = qfactor::ReversedShear::new(3.0, 2.1, 0.05, 4.5, 0.125).unwrap();
+        let mut cache = qfactor.cache();
+
+        assert!(is_close!(qfactor.q(0.0, &mut cache).unwrap(), 3.0));
+        assert!(is_close!(qfactor.q(0.05, &mut cache).unwrap(), 2.1));
+        assert!(is_close!(qfactor.q(0.125, &mut cache).unwrap(), 4.5));
+        assert!(qfactor.q(0.04, &mut cache).unwrap() > 2.1);
+        assert!(qfactor.q(0.06, &mut cache).unwrap() > 2.1);
+        assert_eq!(qfactor.psip(0.0, &mut cache).unwrap(), 0.0);
+
+        // Composite Simpson's rule of 1/q.
+        let n = 10000;
+        let h = 0.125 / n as f64;
+        let mut psip = 0.0;
+        for k in 0..n {
+            let a = k as f64 * h;
+            let f = |psi: f64| 1.0 / qfactor.q(psi, &mut qfactor.cache()).unwrap();
+            psip += h / 6.0 * (f(a) + 4.0 * f(a + h / 2.0) + f(a + h));
+            if (k + 1) % 1000 == 0 {
+                let psi = (k + 1) as f64 * h;
+                assert!(is_close!(
+                    qfactor.psip(psi, &mut cache).unwrap(),
+                    psip,
+                    rel_tol = 1e-12
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn test_derivatives() {
+        let qfactor = qfactor::ReversedShear::new(3.0, 2.1, 0.05, 4.5, 0.125).unwrap();
+        let mut cache = qfactor.cache();
+        let q = |psi| qfactor.q(psi, &mut qfactor.cache()).unwrap();
+        let h = 1e-6;
+
+        for psi in [0.01, 0.04, 0.06, 0.1] {
+            let dq = qfactor.dq_dpsi(psi, &mut cache).unwrap();
+            assert!(is_close!(
+                dq,
+                (q(psi + h) - q(psi - h)) / (2.0 * h),
+                rel_tol = 1e-8
+            ));
+            let fd2 = (qfactor.dq_dpsi(psi + h, &mut cache).unwrap()
+                - qfactor.dq_dpsi(psi - h, &mut cache).unwrap())
+                / (2.0 * h);
+            assert!(is_close!(qfactor.d2q_dpsi2(psi, &mut cache).unwrap(), fd2));
+        }
+        // Negative shear inside q_min, positive outside.
+        assert!(qfactor.shear(0.04, &mut cache).unwrap() < 0.0);
+        assert_eq!(qfactor.shear(0.05, &mut cache).unwrap(), 0.0);
+        assert!(qfactor.shear(0.06, &mut cache).unwrap() > 0.0);
+        assert!(qfactor.diota_dpsi(0.04, &mut cache).unwrap() > 0.0);
+    }
+
+    #[test]
+    fn test_psi_from_psip() {
+        let qfactor = qfactor::ReversedShear::new(3.0, 2.1, 0.05, 4.5, 0.125).unwrap();
+        let mut cache = qfactor.cache();
+
+        for psi in [0.0, 1e-4, 0.03, 0.05, 0.07, 0.125, 0.5] {
+            let psip = qfactor.psip(psi, &mut cache).unwrap();
+            let inv = qfactor.psi_from_psip(psip, &mut cache).unwrap();
+            assert!(is_close!(inv, psi, abs_tol = 1e-14));
+        }
+        assert!(qfactor.psi_from_psip(-1.0, &mut cache).is_err());
+        assert!(qfactor.psi_from_psip(1.0, &mut cache).is_err());
+    }
+
+    #[test]
+    fn test_flat_core() {
+        let qfactor = qfactor::ReversedShear::new(2.0, 2.0, 0.05, 4.0, 0.125).unwrap();
+        let mut cache = qfactor.cache();
+        assert!(is_close!(qfactor.psip(0.04, &mut cache).unwrap(), 0.02));
+        assert!(is_close!(qfactor.psip(0.05, &mut cache).unwrap(), 0.025));
+        assert!(is_close!(
+            qfactor.psi_from_psip(0.02, &mut cache).unwrap(),
+            0.04
+        ));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(qfactor::ReversedShear::new(3.0, 3.5, 0.05, 4.5, 0.125).is_err());
+        assert!(qfactor::ReversedShear::new(3.0, 2.1, 0.2, 4.5, 0.125).is_err());
+        assert!(qfactor::ReversedShear::new(3.0, 0.0, 0.05, 4.5, 0.125).is_err());
+    }
+}
@@ -1,7 +1,6 @@
-use rsl_interpolation::Accelerator;
-
 use crate::Result;
-use crate::qfactor::Qfactor;
+use crate::cache::NoCache;
+use crate::qfactor::{Qfactor, negative_psip};
 
 /// q-factor of 1
 ///
@@ -27,33 +26,60 @@ impl Unity {
 }
 
 impl Qfactor for Unity {
+    type Cache = NoCache;
+
     /// Always returns `1.0`.
     #[allow(unused_variables)]
-    fn q(&self, psi: f64, acc: &mut Accelerator) -> Result<f64> {
+    fn q(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
         debug_assert!(psi.is_sign_positive());
         Ok(1.0)
     }
 
     /// Always returns `psi`.
     #[allow(unused_variables)]
-    fn psip(&self, psi: f64, acc: &mut Accelerator) -> Result<f64> {
+    fn psip(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
         debug_assert!(psi.is_sign_positive());
         Ok(psi)
     }
+
+    /// Always returns `0.0`.
+    #[allow(unused_variables)]
+    fn dq_dpsi(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        Ok(0.0)
+    }
+
+    /// Always returns `0.0`.
+    #[allow(unused_variables)]
+    fn d2q_dpsi2(&self, psi: f64, cache: &mut Self::Cache) -> Result<f64> {
+        debug_assert!(psi.is_sign_positive());
+        Ok(0.0)
+    }
+
+    /// Always returns `psip`.
+    #[allow(unused_variables)]
+    fn psi_from_psip(&self, psip: f64, cache: &mut Self::Cache) -> Result<f64> {
+        if psip < 0.0 {
+            return Err(negative_psip(psip));
+        }
+        Ok(psip)
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use rsl_interpolation::Accelerator;
-
     use crate::*;
 
     #[test]
     fn test_unity() {
-        let mut acc = Accelerator::new();
         let qfactor = qfactor::Unity::new().unwrap();
+        let mut cache = qfactor.cache();
 
-        assert_eq!(qfactor.q(0.01, &mut acc).unwrap(), 1.0);
-        assert_eq!(qfactor.psip(0.01, &mut acc).unwrap(), 0.01);
+        assert_eq!(qfactor.q(0.01, &mut cache).unwrap(), 1.0);
+        assert_eq!(qfactor.psip(0.01, &mut cache).unwrap(), 0.01);
+        assert_eq!(qfactor.psi_from_psip(0.01, &mut cache).unwrap(), 0.01);
+        assert_eq!(qfactor.dq_dpsi(0.01, &mut cache).unwrap(), 0.0);
+        assert_eq!(qfactor.shear(0.01, &mut cache).unwrap(), 0.0);
+        assert!(qfactor.psi_from_psip(-0.01, &mut cache).is_err());
     }
 }
@@ -0,0 +1,430 @@
+//! Solov'ev analytical equilibrium.
+//!
+//! The up-down symmetric solution of Cerfon and Freidberg, _"One size fits all" analytic
+//! solutions to the Grad–Shafranov equation_, Phys. Plasmas 17, 032502 (2010). With `x = R/R₀`
+//! and `y = Z/R₀`, the poloidal flux is `Ψ = ψ₀ψ̄(x, y)`, where
+//!
+//! ```text
+//! ψ̄ = x⁴/8 + A(x²ln(x)/2 − x⁴/8) + Σ cᵢψᵢ(x, y),   i = 1..7
+//! ```
+//!
+//! the `ψᵢ` are homogeneous solutions, and the coefficients `cᵢ` are fixed by the boundary
+//! `ψ̄ = 0` of inverse aspect ratio ε, elongation κ and triangularity δ. The pressure and
+//! `FF′` are constant, with `F² = 1 − 2Aψ₀²ψ̄` in units of `R₀B₀`, and the flux scale ψ₀ is set by
+//! the safety factor `q₀` on the magnetic axis.
+//!
+//! The equilibrium is mapped to Boozer coordinates and normalized by the major radius and the
+//! magnetic field of the magnetic axis, consistently with the numerical profiles.
+
+use crate::boozer::{BoozerData, BoozerGrid, FluxFunction, boozer_map};
+use crate::efield::NoEfield;
+use crate::numerics::{bisect, solve_linear};
+use crate::{EqError, Interp1d, Interp2d, InterpOptions, Result, Tokamak};
+use crate::{bfield, current, qfactor};
+
+/// Solov'ev analytical equilibrium.
+///
+/// # Example
+///
+/// ```
+/// # use tokamak_equilibria::*;
+/// #
+/// # fn main() -> Result<()> {
+/// // ITER-like shaping.
+/// let solovev = solovev::Solovev::new(0.32, 1.7, 0.33, -0.155, 1.1)?;
+/// let tokamak = solovev.tokamak(InterpOptions::default())?;
+///
+/// let mut cache = tokamak.cache();
+/// let q = tokamak.qfactor.q(0.5 * solovev.psi_wall(), &mut cache.qfactor)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Solovev {
+    /// The inverse aspect ratio ε.
+    pub epsilon: f64,
+    /// The elongation κ.
+    pub kappa: f64,
+    /// The triangularity δ.
+    pub delta: f64,
+    /// The ratio `A` of the `FF′` term to the total source of the Grad–Shafranov equation.
+    pub a: f64,
+    /// The safety factor on the magnetic axis.
+    pub q0: f64,
+    /// The coefficients `c₁..c₇` of the homogeneous solutions.
+    pub coefficients: [f64; 7],
+    /// The flux scale ψ₀.
+    pub psi0: f64,
+    /// The major radius of the magnetic axis, in units of `R₀`.
+    pub r_axis: f64,
+    /// The Boozer-mapped profiles.
+    data: BoozerData,
+}
+
+impl Solovev {
+    /// Creates a new Solov'ev equilibrium with inverse aspect ratio `epsilon`, elongation `kappa`,
+    /// triangularity `delta`, `FF′` fraction `a` and safety factor `q0` on the magnetic axis.
+    pub fn new(epsilon: f64, kappa: f64, delta: f64, a: f64, q0: f64) -> Result<Self> {
+        let coefficients = boundary_coefficients(epsilon, kappa, delta, a)?;
+        let shape = Shape {
+            coefficients,
+            a,
+            psi0: 1.0,
+        };
+
+        // The magnetic axis lies on the midplane, where 𝜕ψ̄/𝜕x = 0.
+        let r_axis = bisect(|x| Ok(shape.eval(x, 0.0).dx), 1.0 - epsilon, 1.0 + epsilon)
+            .map_err(|_| EqError::InvalidEquilibrium("no magnetic axis found".into()))?;
+
+        // On the axis, q₀ = F/(R ψ₀ √(ψ̄ₓₓψ̄ᵧᵧ)), with F² = 1 − 2Aψ₀²ψ̄.
+        let axis = shape.eval(r_axis, 0.0);
+        let curvature = axis.dxx * axis.dyy;
+        let denominator = q0.powi(2) * r_axis.powi(2) * curvature + 2.0 * a * axis.value;
+        if !(curvature > 0.0 && denominator > 0.0) {
+            return Err(EqError::InvalidEquilibrium(format!(
+                "no Solov'ev equilibrium with q0 = {q0}"
+            )));
+        }
+        let shape = Shape {
+            psi0: 1.0 / denominator.sqrt(),
+            ..shape
+        };
+
+        let r_max = 2.0 * epsilon * (1.0 + kappa);
+        let data = boozer_map(&shape, (r_axis, 0.0), 0.0, r_max, &BoozerGrid::default())?;
+
+        Ok(Self {
+            epsilon,
+            kappa,
+            delta,
+            a,
+            q0,
+            coefficients,
+            psi0: shape.psi0,
+            r_axis,
+            data,
+        })
+    }
+
+    /// The toroidal flux `ψ` of the plasma boundary.
+    pub fn psi_wall(&self) -> f64 {
+        *self.data.psi.last().expect("non-empty grid")
+    }
+
+    /// Returns the [`qfactor::Numerical`] of the equilibrium, with spline of `typ` interpolation
+    /// type.
+    pub fn qfactor(&self, typ: Interp1d) -> Result<qfactor::Numerical> {
+        qfactor::Numerical::from_arrays(&self.data.psi, &self.data.q, typ)
+    }
+
+    /// Returns the [`current::Numerical`] of the equilibrium, with splines of `typ` interpolation
+    /// type.
+    pub fn current(&self, typ: Interp1d) -> Result<current::Numerical> {
+        current::Numerical::from_arrays(&self.data.psi, &self.data.i, &self.data.g, typ)
+    }
+
+    /// Returns the [`bfield::Numerical`] of the equilibrium, with spline of `typ` interpolation
+    /// type.
+    pub fn bfield(&self, typ: Interp2d) -> Result<bfield::Numerical> {
+        let b_data = self.data.b.clone();
+        bfield::Numerical::from_arrays(&self.data.psi, &self.data.theta, b_data, typ)
+    }
+
+    /// The poloidal flux `Ψ(R, Z)` and toroidal field function `F(Ψ)` of the equilibrium, in
+    /// units of `R₀` and `B₀`.
+    #[cfg(test)]
+    pub(crate) fn flux_function(&self) -> impl FluxFunction {
+        Shape {
+            coefficients: self.coefficients,
+            a: self.a,
+            psi0: self.psi0,
+        }
+    }
+
+    /// Returns the [`Tokamak`] of the equilibrium.
+    pub fn tokamak(
+        &self,
+        opts: InterpOptions,
+    ) -> Result<Tokamak<qfactor::Numerical, bfield::Numerical, current::Numerical, NoEfield>> {
+        Tokamak::build(
+            self.qfactor(opts.typ1d)?,
+            self.bfield(opts.typ2d)?,
+            self.current(opts.typ1d)?,
+            NoEfield::new()?,
+        )
+    }
+}
+
+/// The value and derivatives of a function of `(x, y)`.
+#[derive(Debug, Clone, Copy, Default)]
+struct Derivatives {
+    value: f64,
+    dx: f64,
+    dy: f64,
+    dxx: f64,
+    dyy: f64,
+}
+
+impl Derivatives {
+    fn add_scaled(self, c: f64, other: &Self) -> Self {
+        Self {
+            value: self.value + c * other.value,
+            dx: self.dx + c * other.dx,
+            dy: self.dy + c * other.dy,
+            dxx: self.dxx + c * other.dxx,
+            dyy: self.dyy + c * other.dyy,
+        }
+    }
+}
+
+/// The homogeneous solutions `ψ₁..ψ₇` and their derivatives.
+fn homogeneous(x: f64, y: f64) -> [Derivatives; 7] {
+    let ln = x.ln();
+    let (x2, y2) = (x * x, y * y);
+    let (x3, y3) = (x2 * x, y2 * y);
+    let (x4, y4) = (x2 * x2, y2 * y2);
+    let (x5, y5) = (x4 * x, y4 * y);
+    let x6 = x3 * x3;
+    let y6 = y3 * y3;
+    [
+        Derivatives {
+            value: 1.0,
+            ..Default::default()
+        },
+        Derivatives {
+            value: x2,
+            dx: 2.0 * x,
+            dxx: 2.0,
+            ..Default::default()
+        },
+        Derivatives {
+            value: y2 - x2 * ln,
+            dx: -2.0 * x * ln - x,
+            dy: 2.0 * y,
+            dxx: -2.0 * ln - 3.0,
+            dyy: 2.0,
+        },
+        Derivatives {
+            value: x4 - 4.0 * x2 * y2,
+            dx: 4.0 * x3 - 8.0 * x * y2,
+            dy: -8.0 * x2 * y,
+            dxx: 12.0 * x2 - 8.0 * y2,
+            dyy: -8.0 * x2,
+        },
+        Derivatives {
+            value: 2.0 * y4 - 9.0 * y2 * x2 + 3.0 * x4 * ln - 12.0 * x2 * y2 * ln,
+            dx: 12.0 * x3 * ln + 3.0 * x3 - 24.0 * x * y2 * ln - 30.0 * x * y2,
+            dy: -24.0 * x2 * y * ln - 18.0 * x2 * y + 8.0 * y3,
+            dxx: 36.0 * x2 * ln + 21.0 * x2 - 24.0 * y2 * ln - 54.0 * y2,
+            dyy: -24.0 * x2 * ln - 18.0 * x2 + 24.0 * y2,
+        },
+        Derivatives {
+            value: x6 - 12.0 * x4 * y2 + 8.0 * x2 * y4,
+            dx: 6.0 * x5 - 48.0 * x3 * y2 + 16.0 * x * y4,
+            dy: -24.0 * x4 * y + 32.0 * x2 * y3,
+            dxx: 30.0 * x4 - 144.0 * x2 * y2 + 16.0 * y4,
+            dyy: -24.0 * x4 + 96.0 * x2 * y2,
+        },
+        Derivatives {
+            value: 8.0 * y6 - 140.0 * y4 * x2 + 75.0 * y2 * x4 - 15.0 * x6 * ln
+                + 180.0 * x4 * y2 * ln
+                - 120.0 * x2 * y4 * ln,
+            dx: -90.0 * x5 * ln - 15.0 * x5 + 720.0 * x3 * y2 * ln + 480.0 * x3 * y2
+                - 240.0 * x * y4 * ln
+                - 400.0 * x * y4,
+            dy: 360.0 * x4 * y * ln + 150.0 * x4 * y - 480.0 * x2 * y3 * ln - 560.0 * x2 * y3
+                + 48.0 * y5,
+            dxx: -450.0 * x4 * ln - 165.0 * x4 + 2160.0 * x2 * y2 * ln + 2160.0 * x2 * y2
+                - 240.0 * y4 * ln
+                - 640.0 * y4,
+            dyy: 360.0 * x4 * ln + 150.0 * x4 - 1440.0 * x2 * y2 * ln - 1680.0 * x2 * y2
+                + 240.0 * y4,
+        },
+    ]
+}
+
+/// The particular solution `x⁴/8 + A(x²ln(x)/2 − x⁴/8)` and its derivatives.
+fn particular(x: f64, a: f64) -> Derivatives {
+    let ln = x.ln();
+    let (x2, x3, x4) = (x * x, x * x * x, x * x * x * x);
+    Derivatives {
+        value: x4 / 8.0 + a * (x2 * ln / 2.0 - x4 / 8.0),
+        dx: x3 / 2.0 + a * (x * ln + x / 2.0 - x3 / 2.0),
+        dy: 0.0,
+        dxx: 1.5 * x2 + a * (ln + 1.5 - 1.5 * x2),
+        dyy: 0.0,
+    }
+}
+
+/// Solves for the coefficients `c₁..c₇` that satisfy the boundary conditions at the outer and
+/// inner equatorial points, and at the top of the plasma.
+fn boundary_coefficients(epsilon: f64, kappa: f64, delta: f64, a: f64) -> Result<[f64; 7]> {
+    if !(epsilon > 0.0 && epsilon < 1.0 && kappa > 0.0 && delta.abs() < 1.0) {
+        return Err(EqError::InvalidEquilibrium(format!(
+            "invalid shape ε = {epsilon}, κ = {kappa}, δ = {delta}"
+        )));
+    }
+    let alpha = delta.asin();
+    // Curvatures of the boundary at the three points.
+    let n1 = -(1.0 + alpha).powi(2) / (epsilon * kappa.powi(2));
+    let n2 = (1.0 - alpha).powi(2) / (epsilon * kappa.powi(2));
+    let n3 = -kappa / (epsilon * alpha.cos().powi(2));
+
+    let outer = (1.0 + epsilon, 0.0);
+    let inner = (1.0 - epsilon, 0.0);
+    let top = (1.0 - delta * epsilon, kappa * epsilon);
+
+    // Every condition is a linear functional of ψ̄, applied at a boundary point to the basis and
+    // the particular solution.
+    let points = [outer, inner, top, top, outer, inner, top];
+    let condition = |row: usize, d: &Derivatives| match row {
+        0..=2 => d.value,
+        3 => d.dx,
+        4 => d.dyy + n1 * d.dx,
+        5 => d.dyy + n2 * d.dx,
+        _ => d.dxx + n3 * d.dy,
+    };
+
+    let mut matrix = [[0.0; 7]; 7];
+    let mut rhs = [0.0; 7];
+    for (row, (x, y)) in points.iter().enumerate() {
+        for (col, basis) in homogeneous(*x, *y).iter().enumerate() {
+            matrix[row][col] = condition(row, basis);
+        }
+        rhs[row] = -condition(row, &particular(*x, a));
+    }
+    solve_linear(matrix, rhs)
+        .ok_or_else(|| EqError::InvalidEquilibrium("singular Solov'ev boundary conditions".into()))
+}
+
+/// The flux function of a Solov'ev equilibrium, in units of `R₀` and `B₀`.
+struct Shape {
+    coefficients: [f64; 7],
+    a: f64,
+    psi0: f64,
+}
+
+impl Shape {
+    /// The normalized flux ψ̄ and its derivatives.
+    fn eval(&self, x: f64, y: f64) -> Derivatives {
+        homogeneous(x, y)
+            .iter()
+            .zip(self.coefficients)
+            .fold(particular(x, self.a), |acc, (basis, c)| {
+                acc.add_scaled(c, basis)
+            })
+    }
+}
+
+impl FluxFunction for Shape {
+    fn psi(&self, r: f64, z: f64) -> Result<f64> {
+        Ok(self.psi0 * self.eval(r, z).value)
+    }
+
+    fn grad_psi(&self, r: f64, z: f64) -> Result<(f64, f64)> {
+        let d = self.eval(r, z);
+        Ok((self.psi0 * d.dx, self.psi0 * d.dy))
+    }
+
+    fn f(&self, psi: f64) -> Result<f64> {
+        Ok((1.0 - 2.0 * self.a * self.psi0 * psi).sqrt())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::{PI, TAU};
+
+    use is_close::is_close;
+
+    use crate::solovev::*;
+    use crate::*;
+
+    #[test]
+    /// ITER-like coefficients, cross-tested with an independent symbolic solution.
+    fn test_iter_coefficients() {
+        let c = boundary_coefficients(0.32, 1.7, 0.33, -0.155).unwrap();
+        let expected = [
+            0.0666504678036377,
+            -0.195497960608035,
+            -0.0511055413364285,
+            -0.0459671061428247,
+            0.00553241336025949,
+            -0.00553111122787690,
+            -0.000148005558824399,
+        ];
+        for (ci, ei) in c.iter().zip(expected) {
+            assert!(is_close!(*ci, ei, rel_tol = 1e-10));
+        }
+
+        let shape = Shape {
+            coefficients: c,
+            a: -0.155,
+            psi0: 1.0,
+        };
+        assert!(is_close!(shape.eval(1.32, 0.0).value, 0.0, abs_tol = 1e-14));
+        assert!(is_close!(shape.eval(0.68, 0.0).value, 0.0, abs_tol = 1e-14));
+        let top = shape.eval(1.0 - 0.33 * 0.32, 1.7 * 0.32);
+        assert!(is_close!(top.value, 0.0, abs_tol = 1e-14));
+        assert!(is_close!(top.dx, 0.0, abs_tol = 1e-14));
+    }
+
+    #[test]
+    fn test_solovev_profiles() {
+        let solovev = Solovev::new(0.32, 1.7, 0.33, -0.155, 1.1).unwrap();
+        assert!(is_close!(solovev.r_axis, 1.05126782035790, rel_tol = 1e-10));
+
+        let tokamak = solovev.tokamak(InterpOptions::default()).unwrap();
+        let mut cache = tokamak.cache();
+        let psi_wall = solovev.psi_wall();
+
+        let q0 = tokamak.qfactor.q(0.0, &mut cache.qfactor).unwrap();
+        assert!(is_close!(q0, 1.1, rel_tol = 1e-3));
+        let g0 = tokamak.current.g(0.0, &mut cache.current).unwrap();
+        assert!(is_close!(g0, 1.0));
+        let b0 = tokamak.bfield.b(0.0, 1.0, &mut cache.bfield).unwrap();
+        assert!(is_close!(b0, 1.0));
+
+        // The q-factor's own 𝜓ₚ integration recovers the poloidal flux `ψ₀|ψ̄ₐ|`, normalized by
+        // `B_axis R_axis² = F_axis R_axis`.
+        let shape = Shape {
+            coefficients: solovev.coefficients,
+            a: solovev.a,
+            psi0: solovev.psi0,
+        };
+        let psi_axis = shape.psi(solovev.r_axis, 0.0).unwrap();
+        let f_axis = shape.f(psi_axis).unwrap();
+        let psip_wall = psi_axis.abs() / (f_axis * solovev.r_axis);
+        let psip = tokamak.qfactor.psip(psi_wall, &mut cache.qfactor).unwrap();
+        assert!(is_close!(psip, psip_wall, rel_tol = 1e-4));
+
+        // The field is up-down symmetric, with its minimum on the outboard midplane.
+        let psi = 0.5 * psi_wall;
+        let b_out = tokamak.bfield.b(psi, 0.0, &mut cache.bfield).unwrap();
+        let b_in = tokamak.bfield.b(psi, PI, &mut cache.bfield).unwrap();
+        let b_up = tokamak.bfield.b(psi, 1.0, &mut cache.bfield).unwrap();
+        let b_down = tokamak.bfield.b(psi, TAU - 1.0, &mut cache.bfield).unwrap();
+        assert!(b_out < b_up && b_up < b_in);
+        assert!(is_close!(b_up, b_down, rel_tol = 1e-8));
+    }
+
+    #[test]
+    /// With `A = 0`, F is constant and g = 1 everywhere. At large aspect ratio and without
+    /// shaping, the field approaches that of [`bfield::Lar`], up to `O(ε²)`.
+    fn test_circular_limit() {
+        let epsilon = 0.05;
+        let solovev = Solovev::new(epsilon, 1.0, 0.0, 0.0, 1.5).unwrap();
+        assert!(solovev.data.g.iter().all(|g| is_close!(*g, 1.0)));
+
+        let psi_wall = solovev.psi_wall();
+        assert!(is_close!(psi_wall, epsilon.powi(2) / 2.0, rel_tol = 5e-2));
+
+        let bfield = solovev.bfield(Interp2d::Bicubic).unwrap();
+        let lar = bfield::Lar::new().unwrap();
+        let mut cache = bfield.cache();
+        for theta in [0.0, 1.0, 2.0, PI] {
+            let b = bfield.b(psi_wall, theta, &mut cache).unwrap();
+            let b_lar = lar.b(psi_wall, theta, &mut lar.cache()).unwrap();
+            assert!(is_close!(b, b_lar, abs_tol = 3.0 * epsilon.powi(2)));
+        }
+    }
+}
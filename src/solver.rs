@@ -0,0 +1,252 @@
+//! Dormand–Prince 5(4) embedded Runge–Kutta method.
+
+use crate::EqError;
+
+/// Nodes `c` of the Butcher tableau.
+const C: [f64; 7] = [0.0, 1.0 / 5.0, 3.0 / 10.0, 4.0 / 5.0, 8.0 / 9.0, 1.0, 1.0];
+
+/// Coefficients `a` of the Butcher tableau.
+const A: [[f64; 6]; 7] = [
+    [0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+    [1.0 / 5.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+    [3.0 / 40.0, 9.0 / 40.0, 0.0, 0.0, 0.0, 0.0],
+    [44.0 / 45.0, -56.0 / 15.0, 32.0 / 9.0, 0.0, 0.0, 0.0],
+    [
+        19372.0 / 6561.0,
+        -25360.0 / 2187.0,
+        64448.0 / 6561.0,
+        -212.0 / 729.0,
+        0.0,
+        0.0,
+    ],
+    [
+        9017.0 / 3168.0,
+        -355.0 / 33.0,
+        46732.0 / 5247.0,
+        49.0 / 176.0,
+        -5103.0 / 18656.0,
+        0.0,
+    ],
+    [
+        35.0 / 384.0,
+        0.0,
+        500.0 / 1113.0,
+        125.0 / 192.0,
+        -2187.0 / 6784.0,
+        11.0 / 84.0,
+    ],
+];
+
+/// 5th order weights.
+const B5: [f64; 7] = [
+    35.0 / 384.0,
+    0.0,
+    500.0 / 1113.0,
+    125.0 / 192.0,
+    -2187.0 / 6784.0,
+    11.0 / 84.0,
+    0.0,
+];
+
+/// 4th order weights.
+const B4: [f64; 7] = [
+    5179.0 / 57600.0,
+    0.0,
+    7571.0 / 16695.0,
+    393.0 / 640.0,
+    -92097.0 / 339200.0,
+    187.0 / 2100.0,
+    1.0 / 40.0,
+];
+
+/// Safety factor of the step size controller.
+const SAFETY: f64 = 0.9;
+/// Maximum step size decrease factor.
+const MIN_FACTOR: f64 = 0.2;
+/// Maximum step size increase factor.
+const MAX_FACTOR: f64 = 5.0;
+/// Steps smaller than this fraction of the current time (or of unity, near `t = 0`) are not
+/// attempted; an interpolation domain error at that point means the particle has escaped.
+pub(crate) const MIN_STEP_RATIO: f64 = 1e-12;
+
+/// Maximum number of consecutive rejected steps, after which the integration is abandoned.
+pub(crate) const MAX_REJECTIONS: usize = 100;
+
+/// Performs a single Dormand–Prince step of size `h` from `(x, y)`, for the system `dy/dx = f(x, y)`.
+///
+/// Returns the 5th order solution and the difference from the embedded 4th order solution.
+pub(crate) fn dopri_step<const N: usize, F, Err>(
+    f: &mut F,
+    x: f64,
+    y: &[f64; N],
+    h: f64,
+) -> Result<([f64; N], [f64; N]), Err>
+where
+    F: FnMut(f64, &[f64; N]) -> Result<[f64; N], Err>,
+{
+    let mut k = [[0.0; N]; 7];
+    for s in 0..7 {
+        let ys: [f64; N] =
+            std::array::from_fn(|n| y[n] + h * (0..s).map(|j| A[s][j] * k[j][n]).sum::<f64>());
+        k[s] = f(x + C[s] * h, &ys)?;
+    }
+
+    let y5 = std::array::from_fn(|n| y[n] + h * (0..7).map(|s| B5[s] * k[s][n]).sum::<f64>());
+    let err = std::array::from_fn(|n| h * (0..7).map(|s| (B5[s] - B4[s]) * k[s][n]).sum::<f64>());
+    Ok((y5, err))
+}
+
+/// The RMS norm of the local error `err`, scaled by the mixed tolerance `atol + rtol⋅|y|`.
+///
+/// The step is accepted if the norm is `≤ 1`.
+pub(crate) fn error_norm<const N: usize>(
+    y: &[f64; N],
+    y_new: &[f64; N],
+    err: &[f64; N],
+    rtol: f64,
+    atol: f64,
+) -> f64 {
+    let sum: f64 = (0..N)
+        .map(|n| {
+            let scale = atol + rtol * y[n].abs().max(y_new[n].abs());
+            (err[n] / scale).powi(2)
+        })
+        .sum();
+    (sum / N as f64).sqrt()
+}
+
+/// Decides whether a step at time `t` with error norm `norm` is accepted, counting the
+/// consecutive `rejections`.
+///
+/// Fails if the norm is not finite, or after [`MAX_REJECTIONS`] consecutive rejections, since the
+/// step size would otherwise never settle.
+pub(crate) fn accept_step(norm: f64, t: f64, rejections: &mut usize) -> crate::Result<bool> {
+    if !norm.is_finite() {
+        return Err(EqError::IntegrationError(format!(
+            "non-finite local error at t = {t}"
+        )));
+    }
+    if norm <= 1.0 {
+        *rejections = 0;
+        return Ok(true);
+    }
+    *rejections += 1;
+    if *rejections > MAX_REJECTIONS {
+        return Err(EqError::IntegrationError(format!(
+            "{MAX_REJECTIONS} consecutive rejected steps at t = {t}"
+        )));
+    }
+    Ok(false)
+}
+
+/// The factor by which the step size should be multiplied, given the error norm of the last step.
+pub(crate) fn step_factor(norm: f64) -> f64 {
+    if norm == 0.0 {
+        return MAX_FACTOR;
+    }
+    (SAFETY * norm.powf(-1.0 / 5.0)).clamp(MIN_FACTOR, MAX_FACTOR)
+}
+
+/// Adaptive step size control of [`dopri_step`]s.
+///
+/// Keeps the step size and the count of consecutive rejections between the steps of an
+/// integration.
+pub(crate) struct Stepper {
+    /// The size of the next step to attempt.
+    h: f64,
+    rtol: f64,
+    atol: f64,
+    rejections: usize,
+}
+
+impl Stepper {
+    /// Creates a new stepper, whose first attempted step has size `first_step`.
+    pub(crate) fn new(first_step: f64, rtol: f64, atol: f64) -> Self {
+        Self {
+            h: first_step,
+            rtol,
+            atol,
+            rejections: 0,
+        }
+    }
+
+    /// Takes one accepted step from `(x, y)` for the system `dy/dx = f(x, y)`, without stepping
+    /// past `x_end`.
+    ///
+    /// Rejected steps are retried with a smaller step size. Returns the size of the accepted step
+    /// and the new state.
+    pub(crate) fn step<const N: usize, F>(
+        &mut self,
+        f: &mut F,
+        x: f64,
+        y: &[f64; N],
+        x_end: f64,
+    ) -> crate::Result<(f64, [f64; N])>
+    where
+        F: FnMut(f64, &[f64; N]) -> crate::Result<[f64; N]>,
+    {
+        loop {
+            self.h = self.h.min(x_end - x);
+            let h = self.h;
+            let (y_new, err) = match dopri_step(f, x, y, h) {
+                Ok(step) => step,
+                // The trial stages may leave the domain even if the solution itself does not.
+                // The solution approaches the boundary with ever smaller steps, so the retries are
+                // bounded by the step size rather than by their number.
+                Err(EqError::DomainError(_)) if h > MIN_STEP_RATIO * x.abs().max(1.0) => {
+                    self.h /= 4.0;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            let norm = error_norm(y, &y_new, &err, self.rtol, self.atol);
+            let accepted = accept_step(norm, x, &mut self.rejections)?;
+            self.h *= step_factor(norm);
+            if accepted {
+                return Ok((h, y_new));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use is_close::is_close;
+
+    #[test]
+    fn test_accept_step() {
+        let mut rejections = 0;
+        assert!(accept_step(0.5, 0.0, &mut rejections).unwrap());
+        assert!(!accept_step(2.0, 0.0, &mut rejections).unwrap());
+        assert_eq!(rejections, 1);
+        assert!(accept_step(1.0, 0.0, &mut rejections).unwrap());
+        assert_eq!(rejections, 0);
+
+        assert!(accept_step(f64::NAN, 0.0, &mut rejections).is_err());
+        assert!(accept_step(f64::INFINITY, 0.0, &mut rejections).is_err());
+        for _ in 0..MAX_REJECTIONS {
+            assert!(!accept_step(2.0, 0.0, &mut rejections).unwrap());
+        }
+        assert!(accept_step(2.0, 0.0, &mut rejections).is_err());
+    }
+
+    #[test]
+    /// Integration of the harmonic oscillator for one period must return to the initial state.
+    fn test_harmonic_oscillator() {
+        let mut f = |_: f64, y: &[f64; 2]| -> crate::Result<[f64; 2]> { Ok([y[1], -y[0]]) };
+        let (rtol, atol) = (1e-12, 1e-12);
+
+        let mut x = 0.0;
+        let mut y = [1.0, 0.0];
+        let mut stepper = Stepper::new(0.1, rtol, atol);
+        let x_end = 2.0 * std::f64::consts::PI;
+        while x < x_end {
+            let (h, y_new) = stepper.step(&mut f, x, &y, x_end).unwrap();
+            x += h;
+            y = y_new;
+        }
+        assert!(is_close!(y[0], 1.0, abs_tol = 1e-10));
+        assert!(is_close!(y[1], 0.0, abs_tol = 1e-10));
+    }
+}
@@ -0,0 +1,367 @@
+//! Flux-surface averages.
+//!
+//! In Boozer coordinates `(ψ, θ, ζ)` the Jacobian is `𝒥 = (g + I/q)/B²`, so that the average of
+//! a quantity `f` over the surface `ψ` is
+//!
+//! ```text
+//! ⟨f⟩ = ∮ f 𝒥 dθ / ∮ 𝒥 dθ = ∮ f/B² dθ / ∮ 1/B² dθ
+//! ```
+//!
+//! since `g`, `I` and `q` are constant on the surface. The averages only involve the equilibrium,
+//! and ignore any [`Perturbation`]. Fields are independent of ζ, so the θ integrals are evaluated
+//! with the trapezoidal rule, which converges exponentially for smooth periodic integrands.
+
+use std::f64::consts::TAU;
+
+use crate::bfield::Bfield;
+use crate::current::Current;
+use crate::efield::Efield;
+use crate::numerics::{bisect, gauss_legendre, integrate};
+use crate::perturbation::Perturbation;
+use crate::qfactor::Qfactor;
+use crate::{FieldState, Result, Tokamak};
+
+/// The number of θ samples of a flux-surface average.
+const THETA_SAMPLES: usize = 256;
+/// The number of λ panels of the trapped fraction integral, which halve towards `λ = 1/B_max`.
+const LAMBDA_PANELS: usize = 12;
+/// The number of θ samples scanned for roots of `𝜕B/𝜕θ`.
+const EXTREMA_SAMPLES: usize = 64;
+
+/// Calculates the flux-surface average `⟨f⟩` over the surface `ψ = psi`.
+///
+/// `f` is evaluated at the [`FieldState`] of every θ sample.
+///
+/// # Example
+///
+/// ```
+/// # use tokamak_equilibria::*;
+/// # use tokamak_equilibria::surface::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125)?;
+/// let bfield = bfield::Lar::new()?;
+/// let current = current::Lar::new()?;
+/// let efield = efield::NoEfield::new()?;
+/// let eq = Tokamak::build(qfactor, bfield, current, efield)?;
+///
+/// let db2 = flux_surface_average(&eq, 0.02, |state| state.db_dtheta.powi(2))?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn flux_surface_average<Q, B, C, E, P, F>(
+    tokamak: &Tokamak<Q, B, C, E, P>,
+    psi: f64,
+    mut f: F,
+) -> Result<f64>
+where
+    Q: Qfactor,
+    B: Bfield,
+    C: Current,
+    E: Efield,
+    P: Perturbation,
+    F: FnMut(&FieldState) -> f64,
+{
+    let mut cache = tokamak.cache();
+    let (mut sum, mut norm) = (0.0, 0.0);
+    for theta in theta_samples() {
+        let state = tokamak.eval(psi, theta, &mut cache)?;
+        let weight = state.b.powi(-2);
+        sum += weight * f(&state);
+        norm += weight;
+    }
+    Ok(sum / norm)
+}
+
+/// The flux-surface averaged quantities of a surface.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceQuantities {
+    /// The toroidal flux `ψ` of the surface.
+    pub psi: f64,
+    /// The average `⟨B⟩`.
+    pub b_avg: f64,
+    /// The average `⟨B²⟩`.
+    pub b2_avg: f64,
+    /// The average `⟨1/B⟩`.
+    pub inv_b_avg: f64,
+    /// The minimum of `B`.
+    pub b_min: f64,
+    /// The maximum of `B`.
+    pub b_max: f64,
+    /// The fraction of trapped particles,
+    /// `f_t = 1 − ¾⟨B²⟩ ∫ λ dλ/⟨√(1 − λB)⟩` over `0 ≤ λ ≤ 1/B_max`.
+    pub trapped_fraction: f64,
+    /// The derivative `dV/dψ = 2π∮𝒥 dθ` of the enclosed volume.
+    pub dv_dpsi: f64,
+}
+
+/// Calculates the [`SurfaceQuantities`] of the surface `ψ = psi`.
+///
+/// # Example
+///
+/// ```
+/// # use tokamak_equilibria::*;
+/// # use tokamak_equilibria::surface::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125)?;
+/// let bfield = bfield::Lar::new()?;
+/// let current = current::Lar::new()?;
+/// let efield = efield::NoEfield::new()?;
+/// let eq = Tokamak::build(qfactor, bfield, current, efield)?;
+///
+/// let surface = surface_quantities(&eq, 0.02)?;
+/// assert!(surface.b_min < surface.b_avg && surface.b_avg < surface.b_max);
+/// # Ok(())
+/// # }
+/// ```
+pub fn surface_quantities<Q, B, C, E, P>(
+    tokamak: &Tokamak<Q, B, C, E, P>,
+    psi: f64,
+) -> Result<SurfaceQuantities>
+where
+    Q: Qfactor,
+    B: Bfield,
+    C: Current,
+    E: Efield,
+    P: Perturbation,
+{
+    let mut cache = tokamak.cache();
+    let b: Vec<f64> = theta_samples()
+        .map(|theta| tokamak.bfield.b(psi, theta, &mut cache.bfield))
+        .collect::<Result<_>>()?;
+    let q = tokamak.qfactor.q(psi, &mut cache.qfactor)?;
+    let i = tokamak.current.i(psi, &mut cache.current)?;
+    let g = tokamak.current.g(psi, &mut cache.current)?;
+
+    let norm: f64 = b.iter().map(|b| b.powi(-2)).sum();
+    let average = |f: &dyn Fn(f64) -> f64| b.iter().map(|b| f(*b) * b.powi(-2)).sum::<f64>() / norm;
+
+    let FieldExtrema { b_min, b_max, .. } = field_extrema(&tokamak.bfield, psi)?;
+    let b2_avg = average(&|b| b * b);
+
+    // With `x = λB_max`, `f_t = 1 − ¾⟨B²⟩/B_max² ∫₀¹ x dx/⟨√(1 − xB/B_max)⟩`. The integrand has a
+    // square root singularity in its derivative at `x = 1`.
+    let rule = gauss_legendre::<16>();
+    let integrand = |x: f64| x / average(&|b| (1.0 - x * b / b_max).max(0.0).sqrt());
+    let mut lambda_integral = 0.0;
+    let mut a = 0.0;
+    for k in 1..=LAMBDA_PANELS {
+        let b = if k == LAMBDA_PANELS {
+            1.0
+        } else {
+            1.0 - 0.5f64.powi(k as i32)
+        };
+        lambda_integral += integrate(integrand, a, b, &rule);
+        a = b;
+    }
+
+    Ok(SurfaceQuantities {
+        psi,
+        b_avg: average(&|b| b),
+        b2_avg,
+        inv_b_avg: average(&|b| 1.0 / b),
+        b_min,
+        b_max,
+        trapped_fraction: 1.0 - 0.75 * b2_avg / b_max.powi(2) * lambda_integral,
+        dv_dpsi: TAU * (g + i / q) * norm * TAU / THETA_SAMPLES as f64,
+    })
+}
+
+/// The extrema of `B` on a flux surface.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FieldExtrema {
+    /// The toroidal flux `ψ` of the surface.
+    pub psi: f64,
+    /// The minimum of `B`.
+    pub b_min: f64,
+    /// The poloidal angle of the minimum, in `[0, 2π)`.
+    pub theta_min: f64,
+    /// The maximum of `B`.
+    pub b_max: f64,
+    /// The poloidal angle of the maximum, in `[0, 2π)`.
+    pub theta_max: f64,
+}
+
+impl FieldExtrema {
+    /// The mirror ratio `B_max/B_min`.
+    pub fn mirror_ratio(&self) -> f64 {
+        self.b_max / self.b_min
+    }
+
+    /// The critical pitch `λ_c = 1/B_max` of the trapped-passing boundary, with `λ = μ/E`.
+    pub fn lambda_c(&self) -> f64 {
+        1.0 / self.b_max
+    }
+
+    /// Returns `true` if a particle of pitch `λ = μ/E` is trapped on the surface, that is if
+    /// `1/B_max < λ ≤ 1/B_min`.
+    pub fn is_trapped(&self, lambda: f64) -> bool {
+        lambda > self.lambda_c() && lambda * self.b_min <= 1.0
+    }
+}
+
+/// Finds the minimum and maximum of `B` on the surface `ψ = psi`.
+///
+/// The roots of `𝜕B/𝜕θ` are bracketed on a uniform θ grid and refined with bisection, so the
+/// extrema are exact up to the bisection tolerance.
+///
+/// # Example
+///
+/// ```
+/// # use tokamak_equilibria::*;
+/// # use tokamak_equilibria::surface::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let bfield = bfield::Lar::new()?;
+///
+/// let extrema = field_extrema(&bfield, 0.02)?;
+/// let lambda_c = extrema.lambda_c();
+/// assert!(extrema.is_trapped(1.01 * lambda_c));
+/// # Ok(())
+/// # }
+/// ```
+pub fn field_extrema<B: Bfield>(bfield: &B, psi: f64) -> Result<FieldExtrema> {
+    let mut cache = bfield.cache();
+    let step = TAU / EXTREMA_SAMPLES as f64;
+
+    let mut roots = vec![0.0];
+    let mut theta = 0.0;
+    let mut db = bfield.db_dtheta(psi, theta, &mut cache)?;
+    for k in 1..=EXTREMA_SAMPLES {
+        let next = step * k as f64;
+        let db_next = bfield.db_dtheta(psi, next, &mut cache)?;
+        if db == 0.0 {
+            roots.push(theta);
+        } else if db.signum() != db_next.signum() && db_next != 0.0 {
+            let db_dtheta = |t| bfield.db_dtheta(psi, t, &mut cache);
+            roots.push(bisect(db_dtheta, theta, next)?);
+        }
+        (theta, db) = (next, db_next);
+    }
+
+    let mut extrema = FieldExtrema {
+        psi,
+        b_min: f64::INFINITY,
+        theta_min: 0.0,
+        b_max: f64::NEG_INFINITY,
+        theta_max: 0.0,
+    };
+    for theta in roots {
+        let theta = theta.rem_euclid(TAU);
+        let b = bfield.b(psi, theta, &mut cache)?;
+        if b < extrema.b_min {
+            (extrema.b_min, extrema.theta_min) = (b, theta);
+        }
+        if b > extrema.b_max {
+            (extrema.b_max, extrema.theta_max) = (b, theta);
+        }
+    }
+    Ok(extrema)
+}
+
+/// The θ samples of a flux-surface average, uniform over `[0, 2π)`.
+fn theta_samples() -> impl Iterator<Item = f64> {
+    (0..THETA_SAMPLES).map(|k| TAU * k as f64 / THETA_SAMPLES as f64)
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::{PI, TAU};
+
+    use is_close::is_close;
+
+    use crate::surface::*;
+    use crate::testing::lar_tokamak;
+    use crate::*;
+
+    #[test]
+    /// With `B = 1 − ε cos θ` and `ε = √(2ψ)`, the averages weighted by `1/B²` have closed forms.
+    fn test_lar_averages() {
+        let eq = lar_tokamak();
+        for psi in [1e-4f64, 0.02, 0.08] {
+            let eps = (2.0 * psi).sqrt();
+            let s = surface_quantities(&eq, psi).unwrap();
+            let one_minus = 1.0 - eps * eps;
+
+            assert!(is_close!(s.b_avg, one_minus, rel_tol = 1e-12));
+            assert!(is_close!(s.b2_avg, one_minus.powf(1.5), rel_tol = 1e-12));
+            assert!(is_close!(
+                s.inv_b_avg,
+                (1.0 + eps * eps / 2.0) / one_minus,
+                rel_tol = 1e-12
+            ));
+            assert!(is_close!(s.b_min, 1.0 - eps, rel_tol = 1e-12));
+            assert!(is_close!(s.b_max, 1.0 + eps, rel_tol = 1e-12));
+            assert!(is_close!(
+                s.dv_dpsi,
+                4.0 * PI * PI / one_minus.powf(1.5),
+                rel_tol = 1e-12
+            ));
+
+            let b_avg = flux_surface_average(&eq, psi, |state| state.b).unwrap();
+            assert!(is_close!(b_avg, s.b_avg, rel_tol = 1e-12));
+        }
+    }
+
+    #[test]
+    fn test_lar_extrema() {
+        let bfield = bfield::Lar::new().unwrap();
+        let psi: f64 = 0.02;
+        let eps = (2.0 * psi).sqrt();
+        let extrema = field_extrema(&bfield, psi).unwrap();
+
+        assert!(is_close!(extrema.b_min, 1.0 - eps, rel_tol = 1e-14));
+        assert!(is_close!(extrema.theta_min, 0.0, abs_tol = 1e-12));
+        assert!(is_close!(extrema.b_max, 1.0 + eps, rel_tol = 1e-14));
+        assert!(is_close!(extrema.theta_max, PI, rel_tol = 1e-12));
+        assert!(is_close!(extrema.mirror_ratio(), (1.0 + eps) / (1.0 - eps)));
+
+        let lambda_c = extrema.lambda_c();
+        assert!(!extrema.is_trapped(0.99 * lambda_c));
+        assert!(extrema.is_trapped(1.01 * lambda_c));
+        assert!(!extrema.is_trapped(1.01 / extrema.b_min));
+    }
+
+    #[test]
+    /// On a shaped surface the extrema are refined between the θ samples, and bound `B` on a
+    /// fine scan.
+    fn test_extrema_refinement() {
+        let solovev = solovev::Solovev::new(0.32, 1.7, 0.33, -0.155, 1.1).unwrap();
+        let bfield = solovev.bfield(Interp2d::Bicubic).unwrap();
+        let psi = 0.5 * solovev.psi_wall();
+        let extrema = field_extrema(&bfield, psi).unwrap();
+
+        let mut cache = bfield.cache();
+        let db = bfield
+            .db_dtheta(psi, extrema.theta_max, &mut cache)
+            .unwrap();
+        assert!(is_close!(db, 0.0, abs_tol = 1e-10));
+        for k in 0..1000 {
+            let theta = TAU * k as f64 / 1000.0;
+            let b = bfield.b(psi, theta, &mut cache).unwrap();
+            assert!(extrema.b_min <= b + 1e-14 && b <= extrema.b_max + 1e-14);
+        }
+    }
+
+    #[test]
+    /// The trapped fraction approaches `1.46√ε` at large aspect ratio, and the approximation of
+    /// Lin-Liu and Miller, Phys. Plasmas 2, 1666 (1995), for finite ε.
+    fn test_trapped_fraction() {
+        let eq = lar_tokamak();
+        let eps: f64 = 1e-4;
+        let s = surface_quantities(&eq, eps * eps / 2.0).unwrap();
+        assert!(is_close!(
+            s.trapped_fraction,
+            1.46 * eps.sqrt(),
+            rel_tol = 1e-2
+        ));
+
+        for eps in [0.1f64, 0.3] {
+            let s = surface_quantities(&eq, eps * eps / 2.0).unwrap();
+            let lin_liu =
+                1.0 - (1.0 - eps).powi(2) / ((1.0 - eps * eps).sqrt() * (1.0 + 1.46 * eps.sqrt()));
+            assert!(is_close!(s.trapped_fraction, lin_liu, rel_tol = 3e-2));
+        }
+    }
+}
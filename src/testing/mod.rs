@@ -0,0 +1,59 @@
+//! Fixtures shared by the unit tests.
+
+mod temp_file;
+
+pub(crate) use temp_file::TempFile;
+
+use crate::*;
+
+/// The large aspect ratio equilibrium with a parabolic q-factor.
+pub(crate) type LarTokamak =
+    Tokamak<qfactor::Parabolic, bfield::Lar, current::Lar, efield::NoEfield>;
+
+/// The parabolic q-factor used by the test equilibria.
+pub(crate) fn parabolic() -> qfactor::Parabolic {
+    qfactor::Parabolic::new(1.1, 3.9, 0.125).unwrap()
+}
+
+/// The large aspect ratio equilibrium with a parabolic q-factor and no electric field.
+pub(crate) fn lar_tokamak() -> LarTokamak {
+    let bfield = bfield::Lar::new().unwrap();
+    let current = current::Lar::new().unwrap();
+    let efield = efield::NoEfield::new().unwrap();
+    Tokamak::build(parabolic(), bfield, current, efield).unwrap()
+}
+
+/// The large aspect ratio equilibrium with `q = 1`, for which the guiding-centre equations have
+/// closed forms.
+pub(crate) fn unity_tokamak() -> Tokamak<qfactor::Unity, bfield::Lar, current::Lar, efield::NoEfield>
+{
+    let qfactor = qfactor::Unity::new().unwrap();
+    let bfield = bfield::Lar::new().unwrap();
+    let current = current::Lar::new().unwrap();
+    let efield = efield::NoEfield::new().unwrap();
+    Tokamak::build(qfactor, bfield, current, efield).unwrap()
+}
+
+/// The equilibrium of [`lar_tokamak`], with a constant ripple of amplitude `delta` and 18 coils.
+pub(crate) fn ripple_tokamak(
+    delta: f64,
+) -> Tokamak<
+    qfactor::Parabolic,
+    bfield::Ripple<bfield::Lar, bfield::ConstantRipple>,
+    current::Lar,
+    efield::NoEfield,
+> {
+    let delta = bfield::ConstantRipple::new(delta).unwrap();
+    let bfield = bfield::Ripple::new(bfield::Lar::new().unwrap(), delta, 18).unwrap();
+    let current = current::Lar::new().unwrap();
+    let efield = efield::NoEfield::new().unwrap();
+    Tokamak::build(parabolic(), bfield, current, efield).unwrap()
+}
+
+/// Writes [`lar_tokamak`] with [`Tokamak::to_dataset`], sampled on `grid`, to the temporary file
+/// `name`.
+pub(crate) fn lar_dataset(name: &str, grid: &SampleGrid) -> TempFile {
+    let file = TempFile::new(name);
+    lar_tokamak().to_dataset(&file.path, grid).unwrap();
+    file
+}
@@ -0,0 +1,30 @@
+//! Temporary files of the tests and benchmarks.
+
+use std::path::PathBuf;
+
+/// A path in the temporary directory, unique to the process and to `name`.
+///
+/// The file is removed when the `TempFile` is dropped, so that it is not left behind if the test
+/// panics.
+pub(crate) struct TempFile {
+    /// The path of the file.
+    pub(crate) path: PathBuf,
+}
+
+impl TempFile {
+    /// Creates the path of the temporary netCDF file `name`, without creating the file.
+    pub(crate) fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "tokamak_equilibria_{}_{name}.nc",
+            std::process::id()
+        ));
+        Self { path }
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        // The file may not have been created.
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
@@ -1,19 +1,32 @@
+use std::f64::consts::TAU;
+use std::path::{Path, PathBuf};
+
+use ndarray::Array2;
+use rsl_interpolation::Accelerator;
+use tokamak_netcdf::Equilibrium;
+
 use crate::Result;
-use crate::bfield::Bfield;
+use crate::bfield::{Bfield, Bfield3d};
 use crate::current::Current;
-use crate::efield::Efield;
+use crate::efield::{Efield, NoEfield};
+use crate::perturbation::{NoPerturbation, Perturbation};
 use crate::qfactor::Qfactor;
+use crate::units::Units;
+use crate::{Interp1d, Interp2d};
+use crate::{bfield, current, dataset, qfactor};
 
 /// Representation of a Tokamak Equilibrium.
 ///
-/// Contains all the information of the magnetic field, electric field and q-factor.
+/// Contains all the information of the magnetic field, electric field and q-factor, and of the
+/// [`Perturbation`] of the magnetic field, which is [`NoPerturbation`] by default.
 #[non_exhaustive]
-pub struct Tokamak<Q, B, C, E>
+pub struct Tokamak<Q, B, C, E, P = NoPerturbation>
 where
     Q: Qfactor,
     B: Bfield,
     C: Current,
     E: Efield,
+    P: Perturbation,
 {
     /// The equilibrium's [`q-factor`](Qfactor).
     pub qfactor: Q,
@@ -23,6 +36,10 @@ where
     pub current: C,
     /// The equilibrium's [`electric field`](Efield).
     pub efield: E,
+    /// The equilibrium's [`magnetic perturbation`](Perturbation).
+    pub perturbation: P,
+    /// The reference scales of the normalized units, if known.
+    pub units: Option<Units>,
 }
 
 impl<Q, B, C, E> Tokamak<Q, B, C, E>
@@ -56,56 +73,639 @@ where
             bfield,
             current,
             efield,
+            perturbation: NoPerturbation,
+            units: None,
+        })
+    }
+}
+
+impl<Q, B, C, E, P> Tokamak<Q, B, C, E, P>
+where
+    Q: Qfactor,
+    B: Bfield,
+    C: Current,
+    E: Efield,
+    P: Perturbation,
+{
+    /// Attaches a magnetic [`Perturbation`] to the `Tokamak`, replacing the current one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// # use tokamak_equilibria::perturbation::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125)?;
+    /// let bfield = bfield::Lar::new()?;
+    /// let current = current::Lar::new()?;
+    /// let efield = efield::NoEfield::new()?;
+    ///
+    /// let harmonic = Harmonic::new(2, 1, 1e-4, Envelope::gaussian(0.05, 0.01)?);
+    /// let eq = Tokamak::build(qfactor, bfield, current, efield)?
+    ///     .with_perturbation(Harmonics::new(vec![harmonic])?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_perturbation<P2: Perturbation>(self, perturbation: P2) -> Tokamak<Q, B, C, E, P2> {
+        Tokamak {
+            qfactor: self.qfactor,
+            bfield: self.bfield,
+            current: self.current,
+            efield: self.efield,
+            perturbation,
+            units: self.units,
+        }
+    }
+
+    /// Attaches the reference scales of the normalized units to the `Tokamak`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125)?;
+    /// let bfield = bfield::Lar::new()?;
+    /// let current = current::Lar::new()?;
+    /// let efield = efield::NoEfield::new()?;
+    ///
+    /// let eq = Tokamak::build(qfactor, bfield, current, efield)?
+    ///     .with_units(units::Units::new(2.5, 1.65)?);
+    /// let tesla = eq.units.unwrap().b_to_si(1.0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_units(self, units: Units) -> Self {
+        Self {
+            units: Some(units),
+            ..self
+        }
+    }
+
+    /// Creates a new evaluation cache for every profile of the `Tokamak`.
+    ///
+    /// Each thread evaluating the `Tokamak` should create its own cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// # use std::f64::consts::PI;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125)?;
+    /// let bfield = bfield::Lar::new()?;
+    /// let current = current::Lar::new()?;
+    /// let efield = efield::NoEfield::new()?;
+    ///
+    /// let eq = Tokamak::build(qfactor, bfield, current, efield)?;
+    /// let mut cache = eq.cache();
+    ///
+    /// let q = eq.qfactor.q(0.01, &mut cache.qfactor)?;
+    /// let b = eq.bfield.b(0.01, PI, &mut cache.bfield)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn cache(&self) -> TokamakCache<Q, B, C, E, P> {
+        let mut grids = [
+            self.qfactor.psi_grid(),
+            self.current.psi_grid(),
+            self.bfield.psi_grid(),
+        ]
+        .into_iter()
+        .flatten();
+        let share_psi_acc = match grids.next() {
+            Some(first) => grids.all(|grid| grid == first),
+            None => false,
+        };
+
+        TokamakCache {
+            qfactor: self.qfactor.cache(),
+            bfield: self.bfield.cache(),
+            current: self.current.cache(),
+            efield: self.efield.cache(),
+            perturbation: self.perturbation.cache(),
+            share_psi_acc,
+        }
+    }
+
+    /// Evaluates every field quantity at the point `(ψ, θ)` in one pass.
+    ///
+    /// All the quantities of each profile are evaluated consecutively at the same point, so the
+    /// spline cell located by the first evaluation is reused from the cache by the rest, instead
+    /// of being searched for again.
+    ///
+    /// If the interpolated profiles share the same ψ grid, as those of [`Tokamak::from_dataset`]
+    /// do, the ψ-[`Accelerator`] is also passed from the
+    /// q-factor to the currents and then to the magnetic field, so the ψ cell is located only
+    /// once for all of the `q`, `𝜓ₚ`, `I`, `g` and `B` splines.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// # use std::f64::consts::PI;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125)?;
+    /// let bfield = bfield::Lar::new()?;
+    /// let current = current::Lar::new()?;
+    /// let efield = efield::NoEfield::new()?;
+    ///
+    /// let eq = Tokamak::build(qfactor, bfield, current, efield)?;
+    /// let mut cache = eq.cache();
+    ///
+    /// let state = eq.eval(0.01, PI, &mut cache)?;
+    /// assert_eq!(state.b, eq.bfield.b(0.01, PI, &mut cache.bfield)?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn eval(
+        &self,
+        psi: f64,
+        theta: f64,
+        cache: &mut TokamakCache<Q, B, C, E, P>,
+    ) -> Result<FieldState> {
+        self.eval_with(psi, theta, cache, |bfield, bc| {
+            Ok([
+                bfield.b(psi, theta, bc)?,
+                bfield.db_dpsi(psi, theta, bc)?,
+                bfield.db_dtheta(psi, theta, bc)?,
+            ])
+        })
+    }
+
+    /// Evaluates the quantities of [`Tokamak::eval`], with `B`, `𝜕B/𝜕ψ` and `𝜕B/𝜕θ` those of the
+    /// full field at `ζ`, and also returns `𝜕B/𝜕ζ`.
+    pub(crate) fn eval_3d(
+        &self,
+        psi: f64,
+        theta: f64,
+        zeta: f64,
+        cache: &mut TokamakCache<Q, B, C, E, P>,
+    ) -> Result<(FieldState, f64)>
+    where
+        B: Bfield3d,
+    {
+        if self.bfield.is_axisymmetric() {
+            return Ok((self.eval(psi, theta, cache)?, 0.0));
+        }
+        let mut db_dzeta = 0.0;
+        let fs = self.eval_with(psi, theta, cache, |bfield, bc| {
+            db_dzeta = bfield.db_dzeta(psi, theta, zeta, bc)?;
+            Ok([
+                bfield.b_3d(psi, theta, zeta, bc)?,
+                bfield.db_dpsi_3d(psi, theta, zeta, bc)?,
+                bfield.db_dtheta_3d(psi, theta, zeta, bc)?,
+            ])
+        })?;
+        Ok((fs, db_dzeta))
+    }
+
+    /// Evaluates the quantities of [`Tokamak::eval`], with `[B, 𝜕B/𝜕ψ, 𝜕B/𝜕θ]` given by
+    /// `eval_bfield`.
+    fn eval_with<F>(
+        &self,
+        psi: f64,
+        theta: f64,
+        cache: &mut TokamakCache<Q, B, C, E, P>,
+        eval_bfield: F,
+    ) -> Result<FieldState>
+    where
+        F: FnOnce(&B, &mut B::Cache) -> Result<[f64; 3]>,
+    {
+        let qc = &mut cache.qfactor;
+        let q = self.qfactor.q(psi, qc)?;
+        let psip = self.qfactor.psip(psi, qc)?;
+
+        if cache.share_psi_acc {
+            swap_accelerators(
+                self.qfactor.psi_acc(&mut cache.qfactor),
+                self.current.psi_acc(&mut cache.current),
+            );
+        }
+        let cc = &mut cache.current;
+        let i = self.current.i(psi, cc)?;
+        let g = self.current.g(psi, cc)?;
+        let i_der = self.current.i_der(psi, cc)?;
+        let g_der = self.current.g_der(psi, cc)?;
+
+        if cache.share_psi_acc {
+            swap_accelerators(
+                self.current.psi_acc(&mut cache.current),
+                self.bfield.psi_acc(&mut cache.bfield),
+            );
+        }
+        let [b, db_dpsi, db_dtheta] = eval_bfield(&self.bfield, &mut cache.bfield)?;
+
+        let ec = &mut cache.efield;
+        let phi = self.efield.phi(psi, theta, ec)?;
+        let dphi_dpsi = self.efield.dphi_dpsi(psi, theta, ec)?;
+        let dphi_dtheta = self.efield.dphi_dtheta(psi, theta, ec)?;
+
+        Ok(FieldState {
+            psi,
+            theta,
+            q,
+            psip,
+            b,
+            db_dpsi,
+            db_dtheta,
+            i,
+            g,
+            i_der,
+            g_der,
+            phi,
+            dphi_dpsi,
+            dphi_dtheta,
+        })
+    }
+
+    /// Samples the `Tokamak` on the ψ×θ grid `grid`, and writes it to a netCDF file at `path`,
+    /// with the variable layout read by [`Tokamak::from_dataset`].
+    ///
+    /// The magnetic axis `ψ = 0` is not written, since it is prepended when the file is read, and
+    /// the electric field and the perturbation are ignored. The [`Units`] are written if known.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use tokamak_equilibria::*;
+    /// # use std::path::PathBuf;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let qfactor = qfactor::Parabolic::new(1.1, 3.9, 0.125)?;
+    /// let bfield = bfield::Lar::new()?;
+    /// let current = current::Lar::new()?;
+    /// let efield = efield::NoEfield::new()?;
+    /// let eq = Tokamak::build(qfactor, bfield, current, efield)?;
+    ///
+    /// let path = PathBuf::from("./lar.nc");
+    /// eq.to_dataset(&path, &SampleGrid::new(0.125))?;
+    /// let numerical = Tokamak::from_dataset(&path, InterpOptions::default())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_dataset(&self, path: &Path, grid: &SampleGrid) -> Result<()> {
+        let psi: Vec<f64> = (1..=grid.psi)
+            .map(|k| grid.psi_wall * k as f64 / grid.psi as f64)
+            .collect();
+        let theta: Vec<f64> = (0..=grid.theta)
+            .map(|k| TAU * k as f64 / grid.theta as f64)
+            .collect();
+
+        let mut cache = self.cache();
+        let mut q = Vec::with_capacity(psi.len());
+        let mut i = Vec::with_capacity(psi.len());
+        let mut g = Vec::with_capacity(psi.len());
+        for p in psi.iter() {
+            q.push(self.qfactor.q(*p, &mut cache.qfactor)?);
+            i.push(self.current.i(*p, &mut cache.current)?);
+            g.push(self.current.g(*p, &mut cache.current)?);
+        }
+        let mut b = Array2::zeros((psi.len(), theta.len()));
+        for ((k, n), value) in b.indexed_iter_mut() {
+            *value = self.bfield.b(psi[k], theta[n], &mut cache.bfield)?;
+        }
+
+        let profiles = dataset::Profiles {
+            psi,
+            theta,
+            q,
+            i,
+            g,
+            b,
+        };
+        dataset::write(path, &profiles, self.units.as_ref())
+    }
+}
+
+/// Passes a located accelerator to a profile over the same grid, if both profiles have one.
+fn swap_accelerators(from: Option<&mut Accelerator>, to: Option<&mut Accelerator>) {
+    if let (Some(from), Some(to)) = (from, to) {
+        std::mem::swap(from, to);
+    }
+}
+
+/// The ψ×θ grid on which a [`Tokamak`] is sampled by [`Tokamak::to_dataset`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampleGrid {
+    /// The toroidal flux of the outermost surface.
+    pub psi_wall: f64,
+    /// Number of uniform ψ values in `(0, psi_wall]`.
+    pub psi: usize,
+    /// Number of intervals of the uniform θ grid over `[0, 2π]`.
+    pub theta: usize,
+}
+
+impl SampleGrid {
+    /// Creates a grid of 100 ψ values up to `psi_wall` and 256 θ intervals.
+    pub fn new(psi_wall: f64) -> Self {
+        Self {
+            psi_wall,
+            psi: 100,
+            theta: 256,
+        }
+    }
+}
+
+/// All the field quantities of a [`Tokamak`] at a single `(ψ, θ)` point.
+///
+/// Returned by [`Tokamak::eval()`]. All derivatives are with respect to `ψ` and `θ`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FieldState {
+    /// The toroidal flux `ψ`.
+    pub psi: f64,
+    /// The poloidal angle `θ`.
+    pub theta: f64,
+    /// The q-factor `q(ψ)`.
+    pub q: f64,
+    /// The poloidal flux `𝜓ₚ(ψ)`.
+    pub psip: f64,
+    /// The magnetic field strength `B(ψ, θ)`.
+    pub b: f64,
+    /// `𝜕B /𝜕ψ`.
+    pub db_dpsi: f64,
+    /// `𝜕B /𝜕𝜃`.
+    pub db_dtheta: f64,
+    /// The toroidal current `I(ψ)`.
+    pub i: f64,
+    /// The poloidal current `g(ψ)`.
+    pub g: f64,
+    /// `𝜕I /𝜕ψ`.
+    pub i_der: f64,
+    /// `𝜕g /𝜕ψ`.
+    pub g_der: f64,
+    /// The electric potential `Φ(ψ, θ)`.
+    pub phi: f64,
+    /// `𝜕𝛷 /𝜕𝜓`.
+    pub dphi_dpsi: f64,
+    /// `𝜕𝛷 /𝜕𝜃`.
+    pub dphi_dtheta: f64,
+}
+
+/// The evaluation caches of all the profiles of a [`Tokamak`].
+pub struct TokamakCache<Q, B, C, E, P = NoPerturbation>
+where
+    Q: Qfactor,
+    B: Bfield,
+    C: Current,
+    E: Efield,
+    P: Perturbation,
+{
+    /// The [`q-factor`](Qfactor)'s cache.
+    pub qfactor: Q::Cache,
+    /// The [`magnetic field`](Bfield)'s cache.
+    pub bfield: B::Cache,
+    /// The [`currents`](Current)' cache.
+    pub current: C::Cache,
+    /// The [`electric field`](Efield)'s cache.
+    pub efield: E::Cache,
+    /// The [`magnetic perturbation`](Perturbation)'s cache.
+    pub perturbation: P::Cache,
+    /// Whether the ψ-accelerators may be passed between the profiles in [`Tokamak::eval`].
+    share_psi_acc: bool,
+}
+
+/// Interpolation types used for the reconstruction of a numerical [`Tokamak`].
+pub struct InterpOptions {
+    /// Interpolation type of the 1D splines (q-factor and plasma currents).
+    pub typ1d: Interp1d,
+    /// Interpolation type of the 2D splines (magnetic field).
+    pub typ2d: Interp2d,
+}
+
+impl InterpOptions {
+    /// Creates a new set of interpolation types.
+    pub fn new(typ1d: Interp1d, typ2d: Interp2d) -> Self {
+        Self { typ1d, typ2d }
+    }
+}
+
+impl Default for InterpOptions {
+    /// Cubic splines for the 1D profiles, and bicubic splines for the 2D profiles.
+    fn default() -> Self {
+        Self::new(Interp1d::Cubic, Interp2d::Bicubic)
+    }
+}
+
+impl Tokamak<qfactor::Numerical, bfield::Numerical, current::Numerical, NoEfield> {
+    /// Constructs a numerical `Tokamak` from a netCDF file at `path`.
+    ///
+    /// The profiles are parsed only once, and all of them are constructed over the same ψ and θ
+    /// grids, with the same treatment of the magnetic axis values. The [`Units`] are read from the
+    /// scalar variables `baxis` and `raxis`, if present.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use tokamak_equilibria::*;
+    /// # use std::path::PathBuf;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let path = PathBuf::from("./data.nc");
+    /// let tokamak = Tokamak::from_dataset(&path, InterpOptions::new(Interp1d::Cubic, Interp2d::Bicubic))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_dataset(path: &PathBuf, opts: InterpOptions) -> Result<Self> {
+        use tokamak_netcdf::variable_names::*;
+
+        let eq = Equilibrium::from_file(path)?;
+
+        let psi_data = dataset::psi_coord(&eq)?;
+        let theta_data = dataset::theta_coord(&eq)?;
+
+        let q_data = dataset::flux_function(&eq, Q_FACTOR)?;
+        let i_data = dataset::flux_function(&eq, CURRENT_I)?;
+        let g_data = dataset::flux_function(&eq, CURRENT_G)?;
+        let b_data = bfield::Numerical::extract_b_data(&eq)?;
+
+        let qfactor = qfactor::Numerical::from_arrays(&psi_data, &q_data, opts.typ1d)?;
+        let current = current::Numerical::from_arrays(&psi_data, &i_data, &g_data, opts.typ1d)?;
+        let bfield = bfield::Numerical::from_arrays(&psi_data, &theta_data, b_data, opts.typ2d)?;
+        let efield = NoEfield::new()?;
+
+        let tokamak = Self::build(qfactor, bfield, current, efield)?;
+        Ok(match dataset::units(&eq)? {
+            Some(units) => tokamak.with_units(units),
+            None => tokamak,
         })
     }
 }
 
 #[cfg(test)]
 mod test {
-    use std::path::PathBuf;
+    use std::f64::consts::TAU;
 
-    use rsl_interpolation::Accelerator;
+    use is_close::is_close;
+    use ndarray::Array2;
 
+    use crate::testing::{TempFile, lar_tokamak, parabolic};
     use crate::*;
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_analytical_tokamak() {
         let qfactor = qfactor::Unity::new().unwrap();
         let bfield = bfield::Lar::new().unwrap();
         let current = current::Lar::new().unwrap();
         let efield = efield::NoEfield::new().unwrap();
 
-        let mut psi_acc = Accelerator::new();
-        let mut theta_acc = Accelerator::new();
         let eq = Tokamak::build(qfactor, bfield, current, efield).unwrap();
+        let mut cache = eq.cache();
 
-        eq.bfield
-            .b(0.01, 3.14, &mut psi_acc, &mut theta_acc)
-            .unwrap();
-        eq.efield
-            .phi(0.01, 3.14, &mut psi_acc, &mut theta_acc)
-            .unwrap();
-        eq.current.i(0.01, &mut psi_acc).unwrap();
-        eq.qfactor.q(0.01, &mut psi_acc).unwrap();
+        eq.bfield.b(0.01, 3.14, &mut cache.bfield).unwrap();
+        eq.efield.phi(0.01, 3.14, &mut cache.efield).unwrap();
+        eq.current.i(0.01, &mut cache.current).unwrap();
+        eq.qfactor.q(0.01, &mut cache.qfactor).unwrap();
     }
 
     #[test]
-    #[ignore = "needs specific dataset"]
-    fn test_numerical_tokamak() {
-        let path = PathBuf::from("./reconstructed/smart_positive.nc");
-        let typ = "Cubic";
-        let typ2d = "Bicubic";
-
-        let qfactor = crate::qfactor::Numerical::from_dataset(&path, typ).unwrap();
-        let bfield = crate::bfield::Numerical::from_dataset(&path, typ2d).unwrap();
-        let current = crate::current::Numerical::from_dataset(&path, typ).unwrap();
-        let efield = crate::efield::NoEfield::new().unwrap();
-
-        let _t = Tokamak {
-            qfactor,
-            bfield,
-            current,
-            efield,
-        };
+    fn test_eval_matches_individual_calls() {
+        let eq = lar_tokamak();
+        let mut cache = eq.cache();
+        let (psi, theta) = (0.02, 1.0);
+
+        let state = eq.eval(psi, theta, &mut cache).unwrap();
+        let c = &mut cache;
+        assert_eq!(state.psi, psi);
+        assert_eq!(state.theta, theta);
+        assert_eq!(state.q, eq.qfactor.q(psi, &mut c.qfactor).unwrap());
+        assert_eq!(state.psip, eq.qfactor.psip(psi, &mut c.qfactor).unwrap());
+        assert_eq!(state.b, eq.bfield.b(psi, theta, &mut c.bfield).unwrap());
+        assert_eq!(
+            state.db_dpsi,
+            eq.bfield.db_dpsi(psi, theta, &mut c.bfield).unwrap()
+        );
+        assert_eq!(
+            state.db_dtheta,
+            eq.bfield.db_dtheta(psi, theta, &mut c.bfield).unwrap()
+        );
+        assert_eq!(state.i, eq.current.i(psi, &mut c.current).unwrap());
+        assert_eq!(state.g, eq.current.g(psi, &mut c.current).unwrap());
+        assert_eq!(state.i_der, eq.current.i_der(psi, &mut c.current).unwrap());
+        assert_eq!(state.g_der, eq.current.g_der(psi, &mut c.current).unwrap());
+        assert_eq!(state.phi, eq.efield.phi(psi, theta, &mut c.efield).unwrap());
+    }
+
+    /// Numerical profiles of the `Parabolic`-`Lar` equilibrium, with the magnetic field over
+    /// `b_psi_data`.
+    fn numerical_profiles(
+        psi_data: &[f64],
+        b_psi_data: &[f64],
+    ) -> Tokamak<qfactor::Numerical, bfield::Numerical, current::Numerical, efield::NoEfield> {
+        let parabolic = parabolic();
+        let q_data: Vec<f64> = psi_data
+            .iter()
+            .map(|psi| parabolic.q(*psi, &mut parabolic.cache()).unwrap())
+            .collect();
+        let i_data = vec![0.0; psi_data.len()];
+        let g_data = vec![1.0; psi_data.len()];
+        let theta_data: Vec<f64> = (0..=64).map(|k| TAU * k as f64 / 64.0).collect();
+        let b_data = Array2::from_shape_fn((b_psi_data.len(), theta_data.len()), |(k, n)| {
+            1.0 - (2.0 * b_psi_data[k]).sqrt() * theta_data[n].cos()
+        });
+
+        Tokamak::build(
+            qfactor::Numerical::from_arrays(psi_data, &q_data, Interp1d::Cubic).unwrap(),
+            bfield::Numerical::from_arrays(b_psi_data, &theta_data, b_data, Interp2d::Bicubic)
+                .unwrap(),
+            current::Numerical::from_arrays(psi_data, &i_data, &g_data, Interp1d::Cubic).unwrap(),
+            efield::NoEfield::new().unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    /// Passing the ψ-accelerators between the profiles does not change any value.
+    fn test_eval_shared_psi_acc() {
+        let psi_data: Vec<f64> = (0..=50).map(|k| 0.125 * k as f64 / 50.0).collect();
+        let other_data: Vec<f64> = (0..=40).map(|k| 0.125 * k as f64 / 40.0).collect();
+
+        for (b_psi_data, shared) in [(&psi_data, true), (&other_data, false)] {
+            let eq = numerical_profiles(&psi_data, b_psi_data);
+            let mut cache = eq.cache();
+            assert_eq!(cache.share_psi_acc, shared);
+
+            // Points in different ψ cells, so that every evaluation has to locate its cell.
+            for (psi, theta) in [
+                (0.1, 1.0),
+                (0.01, 5.0),
+                (0.06, 2.0),
+                (0.12, 0.3),
+                (0.003, 4.0),
+            ] {
+                let state = eq.eval(psi, theta, &mut cache).unwrap();
+                let mut fresh = eq.cache();
+                let c = &mut fresh;
+                assert_eq!(state.q, eq.qfactor.q(psi, &mut c.qfactor).unwrap());
+                assert_eq!(state.psip, eq.qfactor.psip(psi, &mut c.qfactor).unwrap());
+                assert_eq!(state.i, eq.current.i(psi, &mut c.current).unwrap());
+                assert_eq!(state.g_der, eq.current.g_der(psi, &mut c.current).unwrap());
+                assert_eq!(state.b, eq.bfield.b(psi, theta, &mut c.bfield).unwrap());
+                assert_eq!(
+                    state.db_dpsi,
+                    eq.bfield.db_dpsi(psi, theta, &mut c.bfield).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_with_perturbation() {
+        use crate::perturbation::*;
+
+        let units = units::Units::new(2.5, 1.65).unwrap();
+        let eq = lar_tokamak().with_units(units);
+
+        let envelope = Envelope::gaussian(0.05, 0.01).unwrap();
+        let harmonics = Harmonics::new(vec![Harmonic::new(2, 1, 1e-4, envelope)]).unwrap();
+        let perturbed = eq.with_perturbation(harmonics);
+        let mut cache = perturbed.cache();
+
+        assert_eq!(perturbed.units, Some(units));
+        let alpha = perturbed
+            .perturbation
+            .alpha(0.05, 0.0, 0.0, 0.0, &mut cache.perturbation);
+        assert_eq!(alpha.unwrap(), 1e-4);
+        let state = perturbed.eval(0.02, 1.0, &mut cache).unwrap();
+        assert_eq!(state.b, 1.0 - 0.2 * 1.0f64.cos());
+    }
+
+    #[test]
+    fn test_dataset_round_trip() {
+        let units = units::Units::new(2.5, 1.65).unwrap();
+        let eq = lar_tokamak().with_units(units);
+        let mut cache = eq.cache();
+
+        let file = TempFile::new("tokamak_round_trip");
+        eq.to_dataset(&file.path, &SampleGrid::new(0.125)).unwrap();
+        let numerical = Tokamak::from_dataset(&file.path, InterpOptions::default()).unwrap();
+        let qfactor = crate::qfactor::Numerical::from_dataset(&file.path, Interp1d::Cubic).unwrap();
+        let mut ncache = numerical.cache();
+
+        assert_eq!(numerical.units, Some(units));
+        assert_eq!(
+            numerical.qfactor.psip_spline.xa,
+            numerical.current.i_spline.xa
+        );
+        assert_eq!(numerical.qfactor.q_spline.xa.len(), 101);
+        assert_eq!(
+            numerical.qfactor.q(0.1, &mut ncache.qfactor).unwrap(),
+            qfactor.q(0.1, &mut qfactor.cache()).unwrap()
+        );
+        for (psi, theta) in [(0.03, 0.5), (0.07, 2.0), (0.11, 4.0)] {
+            let exact = eq.eval(psi, theta, &mut cache).unwrap();
+            let state = numerical.eval(psi, theta, &mut ncache).unwrap();
+            assert!(is_close!(state.q, exact.q, rel_tol = 1e-8));
+            assert!(is_close!(state.psip, exact.psip, rel_tol = 1e-4));
+            assert!(is_close!(state.b, exact.b, rel_tol = 1e-6));
+            assert!(is_close!(state.db_dtheta, exact.db_dtheta, rel_tol = 1e-4));
+            assert!(is_close!(state.g, exact.g));
+            assert!(is_close!(state.i, exact.i, abs_tol = 1e-12));
+        }
     }
 }
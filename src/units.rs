@@ -0,0 +1,263 @@
+//! Conversion between normalized units and SI units.
+//!
+//! Normalized units are defined by the magnetic field `B₀` and the major radius `R₀` of the
+//! magnetic axis, and by the proton, so that
+//!
+//! ```text
+//! length       R₀
+//! B            B₀
+//! ψ, 𝜓ₚ        B₀R₀²                 (flux per radian)
+//! I, g         B₀R₀
+//! time         1/ω₀,  ω₀ = eB₀/m_p   (proton gyrofrequency)
+//! energy       E₀ = m_pω₀²R₀²
+//! Φ            E₀/e
+//! μ            E₀/B₀
+//! mass         m_p
+//! charge       e
+//! ```
+
+use crate::gc::Constants;
+use crate::{EqError, Result};
+
+/// The proton mass `m_p` in kg.
+pub const PROTON_MASS: f64 = 1.67262192595e-27;
+/// The elementary charge `e` in C.
+pub const ELEMENTARY_CHARGE: f64 = 1.602176634e-19;
+
+/// The mass and charge of a particle species, in units of the proton mass and the elementary
+/// charge.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Species {
+    /// The mass, in proton masses.
+    pub mass: f64,
+    /// The charge, in elementary charges.
+    pub charge: f64,
+}
+
+impl Species {
+    /// The proton.
+    pub const PROTON: Self = Self::new(1.0, 1.0);
+    /// The deuteron.
+    pub const DEUTERON: Self = Self::new(1.99900750139, 1.0);
+    /// The triton.
+    pub const TRITON: Self = Self::new(2.99371703414, 1.0);
+    /// The alpha particle.
+    pub const ALPHA: Self = Self::new(3.97259969025, 2.0);
+    /// The electron.
+    pub const ELECTRON: Self = Self::new(5.446170214889e-4, -1.0);
+
+    /// Creates a new species of `mass` proton masses and `charge` elementary charges.
+    pub const fn new(mass: f64, charge: f64) -> Self {
+        Self { mass, charge }
+    }
+
+    /// The guiding-centre [`Constants`] of a particle of this species with magnetic moment `mu`,
+    /// in normalized units.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tokamak_equilibria::*;
+    /// # use tokamak_equilibria::units::*;
+    /// #
+    /// let consts = Species::DEUTERON.constants(1e-5);
+    /// assert_eq!(consts.charge, 1.0);
+    /// ```
+    pub fn constants(&self, mu: f64) -> Constants {
+        Constants::new(mu, self.charge, self.mass)
+    }
+}
+
+/// The reference scales of the normalized units.
+///
+/// # Example
+///
+/// ```
+/// # use tokamak_equilibria::*;
+/// # use tokamak_equilibria::units::*;
+/// #
+/// # fn main() -> Result<()> {
+/// let units = Units::new(5.3, 6.2)?;
+///
+/// // A 3.5 MeV alpha particle.
+/// let energy = units.energy_from_kev(3500.0);
+/// let seconds = units.time_to_si(1e4);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Units {
+    /// The magnetic field `B₀` on the magnetic axis, in T.
+    pub b0: f64,
+    /// The major radius `R₀` of the magnetic axis, in m.
+    pub r0: f64,
+}
+
+impl Units {
+    /// Creates the normalized units of a tokamak with field `b0` in T and major radius `r0` in m,
+    /// on the magnetic axis.
+    pub fn new(b0: f64, r0: f64) -> Result<Self> {
+        if !(b0 > 0.0 && r0 > 0.0 && b0.is_finite() && r0.is_finite()) {
+            return Err(EqError::InvalidEquilibrium(format!(
+                "B0 = {b0} and R0 = {r0} must be positive"
+            )));
+        }
+        Ok(Self { b0, r0 })
+    }
+
+    /// The proton gyrofrequency `ω₀ = eB₀/m_p`, in rad/s.
+    pub fn omega0(&self) -> f64 {
+        ELEMENTARY_CHARGE * self.b0 / PROTON_MASS
+    }
+
+    /// The energy `E₀ = m_pω₀²R₀²`, in J.
+    pub fn energy0(&self) -> f64 {
+        PROTON_MASS * (self.omega0() * self.r0).powi(2)
+    }
+
+    /// Converts a normalized length to m.
+    pub fn length_to_si(&self, length: f64) -> f64 {
+        length * self.r0
+    }
+
+    /// Converts a length in m to normalized units.
+    pub fn length_from_si(&self, length: f64) -> f64 {
+        length / self.r0
+    }
+
+    /// Converts a normalized magnetic field to T.
+    pub fn b_to_si(&self, b: f64) -> f64 {
+        b * self.b0
+    }
+
+    /// Converts a magnetic field in T to normalized units.
+    pub fn b_from_si(&self, b: f64) -> f64 {
+        b / self.b0
+    }
+
+    /// Converts a normalized magnetic flux `ψ` or `𝜓ₚ` to Wb/rad.
+    pub fn psi_to_si(&self, psi: f64) -> f64 {
+        psi * self.b0 * self.r0.powi(2)
+    }
+
+    /// Converts a magnetic flux in Wb/rad to normalized units.
+    pub fn psi_from_si(&self, psi: f64) -> f64 {
+        psi / (self.b0 * self.r0.powi(2))
+    }
+
+    /// Converts a normalized current function `I` or `g` to T⋅m.
+    pub fn current_to_si(&self, current: f64) -> f64 {
+        current * self.b0 * self.r0
+    }
+
+    /// Converts a current function in T⋅m to normalized units.
+    pub fn current_from_si(&self, current: f64) -> f64 {
+        current / (self.b0 * self.r0)
+    }
+
+    /// Converts a normalized electric potential `Φ` to V.
+    pub fn phi_to_si(&self, phi: f64) -> f64 {
+        phi * self.energy0() / ELEMENTARY_CHARGE
+    }
+
+    /// Converts an electric potential in V to normalized units.
+    pub fn phi_from_si(&self, phi: f64) -> f64 {
+        phi * ELEMENTARY_CHARGE / self.energy0()
+    }
+
+    /// Converts a normalized time to s.
+    pub fn time_to_si(&self, time: f64) -> f64 {
+        time / self.omega0()
+    }
+
+    /// Converts a time in s to normalized units.
+    pub fn time_from_si(&self, time: f64) -> f64 {
+        time * self.omega0()
+    }
+
+    /// Converts a normalized angular frequency to rad/s.
+    pub fn frequency_to_si(&self, frequency: f64) -> f64 {
+        frequency * self.omega0()
+    }
+
+    /// Converts an angular frequency in rad/s to normalized units.
+    pub fn frequency_from_si(&self, frequency: f64) -> f64 {
+        frequency / self.omega0()
+    }
+
+    /// Converts a normalized energy to keV.
+    pub fn energy_to_kev(&self, energy: f64) -> f64 {
+        energy * self.energy0() / (1e3 * ELEMENTARY_CHARGE)
+    }
+
+    /// Converts an energy in keV to normalized units.
+    pub fn energy_from_kev(&self, energy: f64) -> f64 {
+        energy * 1e3 * ELEMENTARY_CHARGE / self.energy0()
+    }
+
+    /// Converts a normalized magnetic moment `μ` to J/T.
+    pub fn mu_to_si(&self, mu: f64) -> f64 {
+        mu * self.energy0() / self.b0
+    }
+
+    /// Converts a magnetic moment in J/T to normalized units.
+    pub fn mu_from_si(&self, mu: f64) -> f64 {
+        mu * self.b0 / self.energy0()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use is_close::is_close;
+
+    use crate::units::*;
+
+    #[test]
+    fn test_reference_scales() {
+        let units = Units::new(1.0, 1.0).unwrap();
+        assert!(is_close!(units.omega0(), 9.57883315e7, rel_tol = 1e-8));
+        // E₀ = eB₀²R₀²/m_p in eV.
+        assert!(is_close!(
+            units.energy_to_kev(1.0),
+            9.57883315e4,
+            rel_tol = 1e-8
+        ));
+        assert!(is_close!(
+            units.phi_to_si(1.0),
+            9.57883315e7,
+            rel_tol = 1e-8
+        ));
+
+        assert!(Units::new(0.0, 1.0).is_err());
+        assert!(Units::new(1.0, f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let units = Units::new(5.3, 6.2).unwrap();
+        let x = 0.123;
+
+        assert!(is_close!(units.length_from_si(units.length_to_si(x)), x));
+        assert!(is_close!(units.b_from_si(units.b_to_si(x)), x));
+        assert!(is_close!(units.psi_from_si(units.psi_to_si(x)), x));
+        assert!(is_close!(units.current_from_si(units.current_to_si(x)), x));
+        assert!(is_close!(units.phi_from_si(units.phi_to_si(x)), x));
+        assert!(is_close!(units.time_from_si(units.time_to_si(x)), x));
+        assert!(is_close!(
+            units.frequency_from_si(units.frequency_to_si(x)),
+            x
+        ));
+        assert!(is_close!(units.energy_from_kev(units.energy_to_kev(x)), x));
+        assert!(is_close!(units.mu_from_si(units.mu_to_si(x)), x));
+    }
+
+    #[test]
+    /// A gyrating particle with `μ = E⊥/B` has the same `μ` in both unit systems.
+    fn test_magnetic_moment() {
+        let units = Units::new(2.5, 1.65).unwrap();
+        let (energy, b) = (10.0, 2.0);
+        let mu = units.energy_from_kev(energy) / units.b_from_si(b);
+        let mu_si = energy * 1e3 * ELEMENTARY_CHARGE / b;
+        assert!(is_close!(units.mu_to_si(mu), mu_si));
+    }
+}